@@ -0,0 +1,298 @@
+//! Procedural macros for the [`anthropic`](https://docs.rs/anthropic) crate.
+//!
+//! Use them through the `anthropic` crate's `macros` feature, which
+//! re-exports [`macro@tool`] as `anthropic::tool`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{Attribute, Expr, ExprLit, FnArg, Ident, ItemFn, Lit, LitStr, Meta, Pat, Type};
+
+/// Turn an `async fn` into a tool the model can call.
+///
+/// ```ignore
+/// /// Get the current weather for a city.
+/// ///
+/// /// # Arguments
+/// ///
+/// /// * `city` - City name, e.g. "Paris".
+/// /// * `unit` - Temperature unit; defaults to Celsius.
+/// #[anthropic::tool]
+/// async fn get_weather(city: String, unit: Option<Unit>) -> Result<String, WeatherError> {
+///     // ...
+/// }
+///
+/// let request = builder.tool(GetWeather.definition()).build()?;
+/// let outcome = run_tool_loop(&client, request, GetWeather::executor(), config).await?;
+/// ```
+///
+/// The function is kept as-is. Next to it the macro generates a unit
+/// struct named after the function in `PascalCase` (`GetWeather`) that
+/// implements `anthropic::typed_tool::TypedTool`:
+///
+/// - the tool name is the function name and the description is the doc
+///   comment up to its first heading;
+/// - every parameter becomes a property of `input_schema`, described by its
+///   entry in the `# Arguments` (or `# Parameters`) section, written as
+///   ``* `name` - description``. `Option<T>` parameters are optional;
+/// - the return value is converted with `IntoToolOutput`: `String`, `&str`,
+///   `serde_json::Value`, and `ToolOutput` are successful results, and an
+///   `Err` is reported to the model as a tool error.
+///
+/// `GetWeather::executor()` returns an executor for `run_tool_loop`.
+///
+/// Parameter types must implement `serde::Deserialize` and
+/// `schemars::JsonSchema`. Override the generated name or description with
+/// `#[tool(name = "...", description = "...")]`.
+#[proc_macro_attribute]
+pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = ToolArgs::default();
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            args.name = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("description") {
+            args.description = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported #[tool] argument; expected `name` or `description`"))
+        }
+    });
+    syn::parse_macro_input!(attr with parser);
+    let function = syn::parse_macro_input!(item as ItemFn);
+    expand(args, function).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[derive(Default)]
+struct ToolArgs {
+    name: Option<LitStr>,
+    description: Option<LitStr>,
+}
+
+struct Param {
+    ident: Ident,
+    ty: Type,
+    description: Option<String>,
+}
+
+fn expand(args: ToolArgs, function: ItemFn) -> syn::Result<TokenStream2> {
+    let sig = &function.sig;
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new(sig.fn_token.span, "#[tool] functions must be `async`"));
+    }
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        return Err(syn::Error::new(sig.generics.span(), "#[tool] functions cannot be generic"));
+    }
+
+    let docs = Docs::parse(&function.attrs);
+    let mut params = Vec::new();
+    for input in &sig.inputs {
+        match input {
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new(receiver.span(), "#[tool] functions cannot take `self`"));
+            }
+            FnArg::Typed(typed) => match &*typed.pat {
+                Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => {
+                    let ident = pat.ident.clone();
+                    let description = docs.param(&ident);
+                    params.push(Param { ident, ty: (*typed.ty).clone(), description });
+                }
+                other => return Err(syn::Error::new(other.span(), "#[tool] parameters must be plain identifiers")),
+            },
+        }
+    }
+    if let Some(unknown) = docs.params.iter().find(|(name, _)| !params.iter().any(|p| p.ident == name)) {
+        return Err(syn::Error::new(
+            sig.ident.span(),
+            format!("documented parameter `{}` is not a parameter of `{}`", unknown.0, sig.ident),
+        ));
+    }
+
+    let fn_ident = &sig.ident;
+    let name = args.name.unwrap_or_else(|| LitStr::new(&fn_ident.to_string(), fn_ident.span()));
+    let description = match args.description {
+        Some(description) => description,
+        None if !docs.description.is_empty() => LitStr::new(&docs.description, Span::call_site()),
+        None => {
+            return Err(syn::Error::new(
+                fn_ident.span(),
+                "#[tool] functions need a doc comment (or `description = \"...\"`) describing the tool",
+            ))
+        }
+    };
+
+    let vis = &function.vis;
+    let tool_ident = format_ident!("{}", pascal_case(&fn_ident.to_string()), span = fn_ident.span());
+    let input_ident = format_ident!("{}Input", tool_ident);
+    let struct_doc = format!("Tool generated by `#[tool]` from [`{fn_ident}`].");
+    let fields = params.iter().map(|Param { ident, ty, description }| {
+        let doc = description.as_ref().map(|d| quote!(#[doc = #d]));
+        quote!(#doc #ident: #ty)
+    });
+    let args = params.iter().map(|p| &p.ident);
+
+    Ok(quote! {
+        #function
+
+        #[doc = #struct_doc]
+        #[derive(Clone, Copy, Debug, Default)]
+        #vis struct #tool_ident;
+
+        #[doc(hidden)]
+        #[derive(::anthropic::__private::serde::Deserialize, ::anthropic::__private::schemars::JsonSchema)]
+        #[serde(crate = "::anthropic::__private::serde")]
+        #[schemars(crate = "::anthropic::__private::schemars")]
+        #vis struct #input_ident {
+            #(#fields,)*
+        }
+
+        impl ::anthropic::typed_tool::TypedTool for #tool_ident {
+            type Input = #input_ident;
+
+            fn name(&self) -> &str {
+                #name
+            }
+
+            fn description(&self) -> &str {
+                #description
+            }
+
+            async fn call(
+                &self,
+                input: #input_ident,
+            ) -> ::core::result::Result<::anthropic::ToolOutput, ::anthropic::AnthropicError> {
+                ::core::result::Result::Ok(::anthropic::typed_tool::IntoToolOutput::into_tool_output(
+                    #fn_ident(#(input.#args),*).await,
+                ))
+            }
+        }
+
+        impl #tool_ident {
+            /// Executor for `run_tool_loop` that dispatches calls to this tool.
+            #vis fn executor() -> impl FnMut(
+                ::std::string::String,
+                ::anthropic::__private::serde_json::Value,
+            ) -> ::anthropic::__private::BoxFuture<
+                'static,
+                ::core::result::Result<::anthropic::ToolOutput, ::anthropic::AnthropicError>,
+            > {
+                ::anthropic::typed_tool::typed_executor(#tool_ident)
+            }
+        }
+    })
+}
+
+fn pascal_case(snake: &str) -> String {
+    snake
+        .trim_start_matches("r#")
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Tool description and per-parameter descriptions parsed from doc comments.
+#[derive(Debug, Default, PartialEq)]
+struct Docs {
+    description: String,
+    params: Vec<(String, String)>,
+}
+
+impl Docs {
+    fn parse(attrs: &[Attribute]) -> Self {
+        let lines = attrs.iter().filter(|attr| attr.path().is_ident("doc")).filter_map(|attr| match &attr.meta {
+            Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(doc), .. }) => Some(doc.value()),
+                _ => None,
+            },
+            _ => None,
+        });
+        Self::from_lines(lines)
+    }
+
+    fn from_lines(lines: impl IntoIterator<Item = String>) -> Self {
+        let mut description = Vec::new();
+        let mut params: Vec<(String, String)> = Vec::new();
+        let mut section: Option<bool> = None; // Some(true) inside `# Arguments`.
+        for line in lines.into_iter().flat_map(|doc| doc.lines().map(str::to_string).collect::<Vec<_>>()) {
+            let line = line.strip_prefix(' ').unwrap_or(&line).trim_end();
+            let trimmed = line.trim();
+            if let Some(heading) = trimmed.strip_prefix('#') {
+                let heading = heading.trim_start_matches('#').trim().to_ascii_lowercase();
+                section = Some(heading == "arguments" || heading == "parameters");
+                continue;
+            }
+            match section {
+                None => description.push(line.to_string()),
+                Some(true) => match parse_param_line(trimmed) {
+                    Some(param) => params.push(param),
+                    None if !trimmed.is_empty() => {
+                        if let Some((_, text)) = params.last_mut() {
+                            text.push(' ');
+                            text.push_str(trimmed);
+                        }
+                    }
+                    None => {}
+                },
+                Some(false) => {}
+            }
+        }
+        Self { description: description.join("\n").trim().to_string(), params }
+    }
+
+    fn param(&self, ident: &Ident) -> Option<String> {
+        let name = ident.to_string();
+        let name = name.trim_start_matches("r#");
+        self.params.iter().find(|(param, _)| param == name).map(|(_, text)| text.clone())
+    }
+}
+
+/// Parse ``* `name` - description`` (also `-` bullets and `:` separators).
+fn parse_param_line(line: &str) -> Option<(String, String)> {
+    let item = line.strip_prefix("* ").or_else(|| line.strip_prefix("- "))?.trim_start();
+    let (name, rest) = match item.strip_prefix('`') {
+        Some(quoted) => quoted.split_once('`')?,
+        None => item.split_once([':', ' '])?,
+    };
+    let text = rest.trim_start().trim_start_matches([':', '-', '–', '—']).trim();
+    (!name.is_empty()).then(|| (name.trim().to_string(), text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn docs(text: &str) -> Docs {
+        Docs::from_lines(text.lines().map(|line| format!(" {line}")))
+    }
+
+    #[test]
+    fn parses_description_and_arguments() {
+        let parsed = docs(
+            "Get the weather.\n\nUses the public API.\n\n# Arguments\n\n* `city` - City name,\n  e.g. \"Paris\".\n- `unit`: Unit.\n\n# Errors\n\n* `ignored` - not an argument",
+        );
+        assert_eq!(parsed.description, "Get the weather.\n\nUses the public API.");
+        assert_eq!(
+            parsed.params,
+            vec![("city".into(), "City name, e.g. \"Paris\".".into()), ("unit".into(), "Unit.".into())]
+        );
+    }
+
+    #[test]
+    fn accepts_unquoted_parameter_names() {
+        assert_eq!(parse_param_line("* city - The city"), Some(("city".into(), "The city".into())));
+        assert_eq!(parse_param_line("- city: The city"), Some(("city".into(), "The city".into())));
+        assert_eq!(parse_param_line("not a bullet"), None);
+    }
+
+    #[test]
+    fn converts_names_to_pascal_case() {
+        assert_eq!(pascal_case("get_weather"), "GetWeather");
+        assert_eq!(pascal_case("search"), "Search");
+        assert_eq!(pascal_case("r#match"), "Match");
+    }
+}
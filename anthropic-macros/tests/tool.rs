@@ -0,0 +1,85 @@
+//! Runtime tests for the `#[tool]` attribute.
+
+use anthropic::tool;
+use anthropic::typed_tool::TypedTool;
+use anthropic::ToolOutput;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum Unit {
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(Debug)]
+struct WeatherError(String);
+
+impl std::fmt::Display for WeatherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "weather service: {}", self.0)
+    }
+}
+
+/// Get the current weather for a city.
+///
+/// # Arguments
+///
+/// * `city` - City name, e.g. "Paris".
+/// * `unit` - Temperature unit; defaults to Celsius.
+#[tool]
+async fn get_weather(city: String, unit: Option<Unit>) -> Result<String, WeatherError> {
+    if city.is_empty() {
+        return Err(WeatherError("empty city".into()));
+    }
+    let unit = match unit {
+        Some(Unit::Fahrenheit) => "F",
+        _ => "C",
+    };
+    Ok(format!("{city}: 22{unit}"))
+}
+
+/// Current UTC time.
+#[tool(name = "clock")]
+async fn now() -> &'static str {
+    "12:00"
+}
+
+#[test]
+fn generates_the_tool_definition() {
+    let tool = GetWeather.definition();
+    assert_eq!(tool.name, "get_weather");
+    assert_eq!(tool.description, "Get the current weather for a city.");
+    let schema = &tool.input_schema;
+    assert_eq!(schema["type"], "object");
+    assert_eq!(schema["required"], json!(["city"]));
+    assert_eq!(schema["properties"]["city"]["description"], "City name, e.g. \"Paris\".");
+    assert_eq!(schema["properties"]["unit"]["description"], "Temperature unit; defaults to Celsius.");
+
+    let clock = Now.definition();
+    assert_eq!(clock.name, "clock");
+    assert_eq!(clock.input_schema["type"], "object");
+    assert!(clock.input_schema.get("required").is_none());
+}
+
+#[tokio::test]
+async fn dispatches_calls_and_reports_errors_to_the_model() {
+    let mut executor = GetWeather::executor();
+    let output = executor("get_weather".into(), json!({"city": "Paris", "unit": "fahrenheit"})).await.unwrap();
+    assert_eq!(output, ToolOutput::ok("Paris: 22F"));
+
+    let output = executor("get_weather".into(), json!({"city": ""})).await.unwrap();
+    assert_eq!(output, ToolOutput::error("weather service: empty city"));
+
+    let output = executor("get_weather".into(), json!({"unit": "celsius"})).await.unwrap();
+    assert!(output.is_error);
+    assert!(output.text().contains("missing field `city`"), "{}", output.text());
+
+    let output = executor("clock".into(), json!({})).await.unwrap();
+    assert_eq!(output, ToolOutput::error("unknown tool clock"));
+
+    // The original function is still callable directly.
+    assert_eq!(now().await, "12:00");
+}
@@ -0,0 +1,8 @@
+//! Compile tests for `#[tool]`: valid uses build, misuse fails with a clear error.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass_*.rs");
+    t.compile_fail("tests/ui/fail_*.rs");
+}
@@ -0,0 +1,7 @@
+/// Unknown attribute argument.
+#[anthropic::tool(title = "Lookup")]
+async fn lookup(key: String) -> String {
+    key
+}
+
+fn main() {}
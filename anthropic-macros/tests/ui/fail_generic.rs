@@ -0,0 +1,7 @@
+/// Generic tools have no single schema.
+#[anthropic::tool]
+async fn echo<T: ToString>(value: T) -> String {
+    value.to_string()
+}
+
+fn main() {}
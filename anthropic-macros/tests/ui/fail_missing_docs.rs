@@ -0,0 +1,6 @@
+#[anthropic::tool]
+async fn undocumented(key: String) -> String {
+    key
+}
+
+fn main() {}
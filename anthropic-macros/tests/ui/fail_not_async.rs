@@ -0,0 +1,7 @@
+/// Not async.
+#[anthropic::tool]
+fn lookup(key: String) -> String {
+    key
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+/// Destructured parameters have no property name.
+#[anthropic::tool]
+async fn point((x, y): (i32, i32)) -> String {
+    format!("{x},{y}")
+}
+
+fn main() {}
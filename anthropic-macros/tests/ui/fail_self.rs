@@ -0,0 +1,11 @@
+struct Db;
+
+impl Db {
+    /// Methods are not supported.
+    #[anthropic::tool]
+    async fn lookup(&self, key: String) -> String {
+        key
+    }
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+/// Look up a key.
+///
+/// # Arguments
+///
+/// * `name` - Renamed parameter that no longer exists.
+#[anthropic::tool]
+async fn lookup(key: String) -> String {
+    key
+}
+
+fn main() {}
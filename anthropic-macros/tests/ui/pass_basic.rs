@@ -0,0 +1,25 @@
+use anthropic::typed_tool::TypedTool;
+
+/// Search the knowledge base.
+///
+/// # Parameters
+///
+/// - `query`: Full-text query.
+/// - `limit`: Maximum number of hits.
+#[anthropic::tool]
+pub async fn search(query: String, limit: Option<u32>) -> Result<serde_json::Value, std::io::Error> {
+    Ok(serde_json::json!({ "query": query, "limit": limit }))
+}
+
+/// Say hello.
+#[anthropic::tool(description = "Greets someone")]
+async fn greet(mut name: String) -> String {
+    name.insert_str(0, "hello ");
+    name
+}
+
+fn main() {
+    let _ = Search.definition();
+    let _ = Greet.definition();
+    let _executor = Search::executor();
+}
@@ -45,6 +45,18 @@ impl CreateBatchRequest {
         Ok(())
     }
 
+    /// Union of the `anthropic-beta` flags required by every request in the
+    /// batch. The header applies to the whole submission.
+    pub fn required_betas(&self) -> Vec<String> {
+        let mut betas: Vec<String> = Vec::new();
+        for beta in self.requests.iter().flat_map(|r| r.params.required_betas()) {
+            if !betas.contains(&beta) {
+                betas.push(beta);
+            }
+        }
+        betas
+    }
+
     /// Override the retry policy for this batch submission. See [`RetryPolicy`].
     pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
         self.retry_policy = policy;
@@ -175,7 +187,7 @@ impl ListBatchesParams {
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum BatchRequestResult {
-    Succeeded { message: MessagesResponse },
+    Succeeded { message: Box<MessagesResponse> },
     Errored { error: serde_json::Value },
     Canceled,
     Expired,
@@ -0,0 +1,297 @@
+//! Map citations in a response back to the request that produced them.
+//!
+//! When a `document` or `search_result` block is sent with
+//! [`CitationsConfig`](crate::types::CitationsConfig) enabled, Claude attaches
+//! [`Citation`]s to the text blocks of its answer. Each citation identifies
+//! its source by position (`document_index` or `search_result_index`) and a
+//! span inside that source. [`MessagesRequest::resolve_citation`] performs the
+//! lookup so callers can render provenance without re-implementing the API's
+//! indexing rules.
+
+use std::ops::Range;
+
+use crate::types::{Citation, ContentBlock, DocumentSource, MessagesRequest, ToolResultContent};
+
+/// Span inside a cited source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CitationSpan {
+    /// 0-indexed character range of a plain-text document.
+    Chars(Range<usize>),
+    /// 1-indexed page range of a PDF document.
+    Pages(Range<u32>),
+    /// 0-indexed range of content blocks inside a custom-content document
+    /// or a search result.
+    ContentBlocks(Range<usize>),
+}
+
+/// A citation resolved against the request it was produced from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CitedSource<'a> {
+    /// The source block the citation points at.
+    pub block: &'a ContentBlock,
+    /// Location of the cited passage inside `block`.
+    pub span: CitationSpan,
+}
+
+impl CitedSource<'_> {
+    /// Title of the cited source, if one was provided on the request.
+    pub fn title(&self) -> Option<&str> {
+        match self.block {
+            ContentBlock::Document { title, .. } => title.as_deref(),
+            ContentBlock::SearchResult { title, .. } => Some(title),
+            _ => None,
+        }
+    }
+
+    /// Extract the cited passage from the original source.
+    ///
+    /// Returns `None` for sources whose text is not available locally (PDFs,
+    /// URL-backed documents) or when the span falls outside the source.
+    pub fn source_text(&self) -> Option<String> {
+        match (self.block, &self.span) {
+            (ContentBlock::Document { source: DocumentSource::Text { data, .. }, .. }, CitationSpan::Chars(range)) => {
+                if range.start > range.end || data.chars().count() < range.end {
+                    return None;
+                }
+                Some(data.chars().skip(range.start).take(range.end - range.start).collect())
+            }
+            (
+                ContentBlock::Document { source: DocumentSource::Content { content }, .. },
+                CitationSpan::ContentBlocks(range),
+            )
+            | (ContentBlock::SearchResult { content, .. }, CitationSpan::ContentBlocks(range)) => {
+                blocks_text(content, range)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn blocks_text(blocks: &[ContentBlock], range: &Range<usize>) -> Option<String> {
+    let slice = blocks.get(range.clone())?;
+    Some(slice.iter().filter_map(|b| b.as_text()).collect())
+}
+
+impl MessagesRequest {
+    /// Iterate over every `document` block in the request in the order the API
+    /// assigns `document_index` values: message by message, including
+    /// documents nested inside `tool_result` blocks.
+    pub fn documents(&self) -> impl Iterator<Item = &ContentBlock> {
+        self.source_blocks().filter(|block| matches!(block, ContentBlock::Document { .. }))
+    }
+
+    /// Iterate over every `search_result` block in the request in the order
+    /// the API assigns `search_result_index` values, including results
+    /// returned inside `tool_result` blocks.
+    pub fn search_results(&self) -> impl Iterator<Item = &ContentBlock> {
+        self.source_blocks().filter(|block| matches!(block, ContentBlock::SearchResult { .. }))
+    }
+
+    fn source_blocks(&self) -> impl Iterator<Item = &ContentBlock> {
+        self.messages.iter().flat_map(|m| m.content.iter()).flat_map(|block| match block {
+            ContentBlock::ToolResult { content: ToolResultContent::Blocks(inner), .. } => inner.iter().collect(),
+            other => vec![other],
+        })
+    }
+
+    /// Resolve a citation from a response to this request back to the source
+    /// block and span it refers to.
+    ///
+    /// Returns `None` when the citation does not refer to a block in this
+    /// request (for example web search results, which live in the response)
+    /// or when its index is out of range.
+    ///
+    /// Search result citations resolve against this request's
+    /// `search_result` blocks. When the results came from a tool during a
+    /// [`run_tool_loop`](crate::run_tool_loop), resolve against the final
+    /// request, which contains every tool result.
+    pub fn resolve_citation(&self, citation: &Citation) -> Option<CitedSource<'_>> {
+        let (index, span) = match citation {
+            Citation::CharLocation { document_index, start_char_index, end_char_index, .. } => {
+                (*document_index, CitationSpan::Chars(*start_char_index..*end_char_index))
+            }
+            Citation::PageLocation { document_index, start_page_number, end_page_number, .. } => {
+                (*document_index, CitationSpan::Pages(*start_page_number..*end_page_number))
+            }
+            Citation::ContentBlockLocation { document_index, start_block_index, end_block_index, .. } => {
+                (*document_index, CitationSpan::ContentBlocks(*start_block_index..*end_block_index))
+            }
+            Citation::SearchResultLocation { search_result_index, start_block_index, end_block_index, .. } => {
+                let block = self.search_results().nth(*search_result_index)?;
+                return Some(CitedSource {
+                    block,
+                    span: CitationSpan::ContentBlocks(*start_block_index..*end_block_index),
+                });
+            }
+            Citation::WebSearchResultLocation { .. } => return None,
+        };
+        let block = self.documents().nth(index)?;
+        Some(CitedSource { block, span })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Message, MessagesRequestBuilder, Role};
+
+    fn char_citation(document_index: usize, start: usize, end: usize) -> Citation {
+        Citation::CharLocation {
+            cited_text: String::new(),
+            document_index,
+            document_title: None,
+            start_char_index: start,
+            end_char_index: end,
+        }
+    }
+
+    fn request_with(blocks: Vec<ContentBlock>) -> MessagesRequest {
+        MessagesRequestBuilder::new("m", vec![Message::new(Role::User, blocks)], 10).build().unwrap()
+    }
+
+    #[test]
+    fn resolves_char_location_against_text_document() {
+        let request = request_with(vec![
+            ContentBlock::document_text("The grass is green. The sky is blue."),
+            ContentBlock::document_text("Water is wet."),
+            ContentBlock::text("What color is the sky?"),
+        ]);
+
+        let cited = request.resolve_citation(&char_citation(0, 20, 36)).unwrap();
+        assert_eq!(cited.span, CitationSpan::Chars(20..36));
+        assert_eq!(cited.source_text().as_deref(), Some("The sky is blue."));
+
+        let cited = request.resolve_citation(&char_citation(1, 0, 5)).unwrap();
+        assert_eq!(cited.source_text().as_deref(), Some("Water"));
+    }
+
+    #[test]
+    fn char_location_counts_characters_not_bytes() {
+        let request = request_with(vec![ContentBlock::document_text("héllo wörld")]);
+        let cited = request.resolve_citation(&char_citation(0, 6, 11)).unwrap();
+        assert_eq!(cited.source_text().as_deref(), Some("wörld"));
+    }
+
+    #[test]
+    fn resolves_content_block_location() {
+        let document = ContentBlock::Document {
+            source: DocumentSource::Content {
+                content: vec![
+                    ContentBlock::text("First. "),
+                    ContentBlock::text("Second. "),
+                    ContentBlock::text("Third."),
+                ],
+            },
+            title: Some("Chunks".into()),
+            context: None,
+            citations: None,
+            cache_control: None,
+        };
+        let request = request_with(vec![document]);
+        let cited = request
+            .resolve_citation(&Citation::ContentBlockLocation {
+                cited_text: "Second. Third.".into(),
+                document_index: 0,
+                document_title: Some("Chunks".into()),
+                start_block_index: 1,
+                end_block_index: 3,
+            })
+            .unwrap();
+        assert_eq!(cited.title(), Some("Chunks"));
+        assert_eq!(cited.source_text().as_deref(), Some("Second. Third."));
+    }
+
+    #[test]
+    fn document_index_spans_messages_and_tool_results() {
+        let request = MessagesRequestBuilder::new(
+            "m",
+            vec![
+                Message::new(Role::User, vec![ContentBlock::document_url("https://example.com/a.pdf")]),
+                Message::new(Role::Assistant, vec![ContentBlock::tool_use("tu_1", "fetch", serde_json::json!({}))]),
+                Message::new(
+                    Role::User,
+                    vec![ContentBlock::tool_result_blocks("tu_1", vec![ContentBlock::document_text("fetched")])],
+                ),
+            ],
+            10,
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(request.documents().count(), 2);
+        let cited = request
+            .resolve_citation(&Citation::PageLocation {
+                cited_text: String::new(),
+                document_index: 0,
+                document_title: None,
+                start_page_number: 2,
+                end_page_number: 3,
+            })
+            .unwrap();
+        assert_eq!(cited.span, CitationSpan::Pages(2..3));
+        assert_eq!(cited.source_text(), None);
+
+        let cited = request.resolve_citation(&char_citation(1, 0, 7)).unwrap();
+        assert_eq!(cited.source_text().as_deref(), Some("fetched"));
+    }
+
+    #[test]
+    fn resolves_search_result_location_across_tool_results() {
+        let request = MessagesRequestBuilder::new(
+            "m",
+            vec![
+                Message::new(
+                    Role::User,
+                    vec![
+                        ContentBlock::search_result("https://docs.example.com/a", "A", ["Alpha one.", "Alpha two."]),
+                        ContentBlock::document_text("not a search result"),
+                    ],
+                ),
+                Message::new(Role::Assistant, vec![ContentBlock::tool_use("tu_1", "search", serde_json::json!({}))]),
+                Message::new(
+                    Role::User,
+                    vec![ContentBlock::tool_result_blocks(
+                        "tu_1",
+                        vec![ContentBlock::search_result("https://docs.example.com/b", "B", ["Beta."])],
+                    )],
+                ),
+            ],
+            10,
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(request.search_results().count(), 2);
+        let citation = |search_result_index, start, end| Citation::SearchResultLocation {
+            cited_text: String::new(),
+            search_result_index,
+            source: String::new(),
+            title: None,
+            start_block_index: start,
+            end_block_index: end,
+        };
+        let cited = request.resolve_citation(&citation(0, 1, 2)).unwrap();
+        assert_eq!(cited.title(), Some("A"));
+        assert_eq!(cited.span, CitationSpan::ContentBlocks(1..2));
+        assert_eq!(cited.source_text().as_deref(), Some("Alpha two."));
+
+        let cited = request.resolve_citation(&citation(1, 0, 1)).unwrap();
+        assert_eq!(cited.source_text().as_deref(), Some("Beta."));
+        assert!(request.resolve_citation(&citation(2, 0, 1)).is_none());
+    }
+
+    #[test]
+    fn out_of_range_and_response_side_citations_do_not_resolve() {
+        let request = request_with(vec![ContentBlock::document_text("short")]);
+        assert!(request.resolve_citation(&char_citation(3, 0, 1)).is_none());
+        assert_eq!(request.resolve_citation(&char_citation(0, 0, 50)).unwrap().source_text(), None);
+        assert!(request
+            .resolve_citation(&Citation::WebSearchResultLocation {
+                cited_text: "x".into(),
+                url: "https://example.com".into(),
+                title: None,
+                encrypted_index: "enc".into(),
+            })
+            .is_none());
+    }
+}
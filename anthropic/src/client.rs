@@ -1,3 +1,5 @@
+use std::future::Future;
+use std::path::Path;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
@@ -6,16 +8,23 @@ use std::time::{Duration, Instant};
 pub use backoff::ExponentialBackoff;
 use futures_util::StreamExt;
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, USER_AGENT};
+use reqwest::multipart::Form;
 use reqwest_eventsource::{Event, EventSource, RequestBuilderExt};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use tokio::io::AsyncRead;
 use tokio_stream::Stream;
+use tokio_util::io::ReaderStream;
+use tokio_util::sync::CancellationToken;
 
 use crate::batches::{
     parse_results_jsonl, BatchResultItem, CreateBatchRequest, ListBatchesParams, MessageBatch, MessageBatchList,
 };
 use crate::count_tokens::{CountTokensRequest, CountTokensResponse};
 use crate::error::{AnthropicError, ErrorResponse};
+use crate::files::{
+    guess_mime_type, FileDeleted, FileDownloadStream, FileList, FileMetadata, ListFilesParams, FILES_API_BETA,
+};
 use crate::models::{ListModelsParams, Model, ModelList};
 use crate::types::{MessagesRequest, MessagesResponse, MessagesStreamEvent, RetryPolicy};
 
@@ -206,7 +215,8 @@ impl Client {
         }
         request.stream = None;
         let retry = self.resolve_retry(&request.retry_policy);
-        self.post("/v1/messages", &request, retry).await
+        let betas = request.required_betas();
+        self.post("/v1/messages", &request, &betas, retry).await
     }
 
     pub async fn messages_stream(
@@ -214,47 +224,121 @@ impl Client {
         mut request: MessagesRequest,
     ) -> Result<MessagesResponseStream, AnthropicError> {
         request.stream = Some(true);
-        self.post_stream("/v1/messages", &request).await
+        let betas = request.required_betas();
+        self.post_stream("/v1/messages", &request, &betas).await
+    }
+
+    /// Like [`Client::messages`], but stops as soon as `token` is cancelled:
+    /// the in-flight HTTP request (including any retry backoff) is dropped
+    /// and [`AnthropicError::Cancelled`] is returned.
+    pub async fn messages_with_cancellation(
+        &self,
+        request: MessagesRequest,
+        token: &CancellationToken,
+    ) -> Result<MessagesResponse, AnthropicError> {
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => Err(AnthropicError::Cancelled),
+            response = self.messages(request) => response,
+        }
+    }
+
+    /// Like [`Client::messages_stream`], but cancelling `token` closes the
+    /// connection. A stream that is already open yields one
+    /// [`AnthropicError::Cancelled`] and then ends.
+    pub async fn messages_stream_with_cancellation(
+        &self,
+        request: MessagesRequest,
+        token: &CancellationToken,
+    ) -> Result<MessagesResponseStream, AnthropicError> {
+        let stream = tokio::select! {
+            biased;
+            _ = token.cancelled() => return Err(AnthropicError::Cancelled),
+            stream = self.messages_stream(request) => stream?,
+        };
+        let state = Some((stream, token.clone()));
+        Ok(Box::pin(futures_util::stream::unfold(state, |state| async move {
+            let (mut stream, token) = state?;
+            tokio::select! {
+                biased;
+                _ = token.cancelled() => Some((Err(AnthropicError::Cancelled), None)),
+                event = stream.next() => event.map(|event| (event, Some((stream, token)))),
+            }
+        })))
     }
 
     /// `POST /v1/messages/count_tokens` — compute the input-token cost of a
     /// Messages request without actually generating a response.
     pub async fn count_tokens(&self, request: CountTokensRequest) -> Result<CountTokensResponse, AnthropicError> {
         let retry = self.resolve_retry(&request.retry_policy);
-        self.post("/v1/messages/count_tokens", &request, retry).await
+        let betas = request.required_betas();
+        self.post("/v1/messages/count_tokens", &request, &betas, retry).await
     }
 
     /// `GET /v1/models` — list every model available to the authenticated key.
     pub async fn list_models(&self, params: &ListModelsParams) -> Result<ModelList, AnthropicError> {
         let retry = self.resolve_retry(&RetryPolicy::default());
-        self.get("/v1/models", &params.as_query(), retry).await
+        self.get("/v1/models", &params.as_query(), &[], retry).await
     }
 
     /// `GET /v1/models/{model_id}` — fetch metadata about a single model.
     pub async fn get_model(&self, model_id: &str) -> Result<Model, AnthropicError> {
         let path = format!("/v1/models/{}", model_id);
         let retry = self.resolve_retry(&RetryPolicy::default());
-        self.get::<Model>(&path, &[], retry).await
+        self.get::<Model>(&path, &[], &[], retry).await
     }
 
     /// `POST /v1/messages/batches` — submit a new batch of Messages requests.
     pub async fn create_batch(&self, request: CreateBatchRequest) -> Result<MessageBatch, AnthropicError> {
         request.validate()?;
         let retry = self.resolve_retry(&request.retry_policy);
-        self.post("/v1/messages/batches", &request, retry).await
+        let betas = request.required_betas();
+        self.post("/v1/messages/batches", &request, &betas, retry).await
     }
 
     /// `GET /v1/messages/batches` — list batches submitted by this workspace.
     pub async fn list_batches(&self, params: &ListBatchesParams) -> Result<MessageBatchList, AnthropicError> {
         let retry = self.resolve_retry(&RetryPolicy::default());
-        self.get("/v1/messages/batches", &params.as_query(), retry).await
+        self.get("/v1/messages/batches", &params.as_query(), &[], retry).await
     }
 
     /// `GET /v1/messages/batches/{id}` — fetch current metadata for a batch.
     pub async fn get_batch(&self, batch_id: &str) -> Result<MessageBatch, AnthropicError> {
         let path = format!("/v1/messages/batches/{}", batch_id);
         let retry = self.resolve_retry(&RetryPolicy::default());
-        self.get::<MessageBatch>(&path, &[], retry).await
+        self.get::<MessageBatch>(&path, &[], &[], retry).await
+    }
+
+    /// Poll [`Client::get_batch`] every `poll_interval` until the batch has
+    /// ended, and return its final metadata.
+    pub async fn wait_for_batch(
+        &self,
+        batch_id: &str,
+        poll_interval: Duration,
+    ) -> Result<MessageBatch, AnthropicError> {
+        loop {
+            let batch = self.get_batch(batch_id).await?;
+            if batch.is_complete() {
+                return Ok(batch);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Like [`Client::wait_for_batch`], but cancelling `token` stops polling
+    /// with [`AnthropicError::Cancelled`]; the batch itself keeps processing
+    /// (see [`Client::cancel_batch`]).
+    pub async fn wait_for_batch_with_cancellation(
+        &self,
+        batch_id: &str,
+        poll_interval: Duration,
+        token: &CancellationToken,
+    ) -> Result<MessageBatch, AnthropicError> {
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => Err(AnthropicError::Cancelled),
+            batch = self.wait_for_batch(batch_id, poll_interval) => batch,
+        }
     }
 
     /// `POST /v1/messages/batches/{id}/cancel` — request cancellation of a
@@ -269,7 +353,7 @@ impl Client {
     pub async fn delete_batch(&self, batch_id: &str) -> Result<serde_json::Value, AnthropicError> {
         let path = format!("/v1/messages/batches/{}", batch_id);
         let retry = self.resolve_retry(&RetryPolicy::default());
-        self.delete::<serde_json::Value>(&path, retry).await
+        self.delete::<serde_json::Value>(&path, &[], retry).await
     }
 
     /// `GET /v1/messages/batches/{id}/results` — download and parse the
@@ -281,6 +365,88 @@ impl Client {
         parse_results_jsonl(&body)
     }
 
+    /// `POST /v1/files` — upload a file from disk.
+    ///
+    /// The MIME type is inferred from the file extension. The file is read
+    /// into memory and the multipart form is rebuilt for every attempt, so
+    /// the upload is retried on 429 like any other call.
+    pub async fn upload_file(&self, path: impl AsRef<Path>) -> Result<FileMetadata, AnthropicError> {
+        let path = path.as_ref();
+        let filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| AnthropicError::InvalidRequest(format!("{} has no file name", path.display())))?
+            .to_string();
+        let data = tokio::fs::read(path)
+            .await
+            .map_err(|err| AnthropicError::InvalidRequest(format!("failed to read {}: {err}", path.display())))?;
+        let data = bytes::Bytes::from(data);
+        let mime_type = guess_mime_type(path);
+        let form = move || {
+            let part = reqwest::multipart::Part::stream_with_length(data.clone(), data.len() as u64)
+                .file_name(filename.clone())
+                .mime_str(mime_type)?;
+            Ok(Form::new().part("file", part))
+        };
+        let retry = self.resolve_retry(&RetryPolicy::default());
+        self.post_multipart("/v1/files", form, &files_betas(), retry).await
+    }
+
+    /// `POST /v1/files` — upload a file by streaming it from `reader`.
+    ///
+    /// The body is streamed, so the upload is attempted exactly once.
+    pub async fn upload_file_from_reader<R>(
+        &self,
+        reader: R,
+        filename: impl Into<String>,
+        mime_type: &str,
+    ) -> Result<FileMetadata, AnthropicError>
+    where
+        R: AsyncRead + Send + Sync + 'static,
+    {
+        let body = reqwest::Body::wrap_stream(ReaderStream::new(reader));
+        let part = reqwest::multipart::Part::stream(body).file_name(filename.into()).mime_str(mime_type)?;
+        let mut form = Some(Form::new().part("file", part));
+        let form = move || form.take().ok_or_else(|| AnthropicError::InvalidRequest("upload body already sent".into()));
+        self.post_multipart("/v1/files", form, &files_betas(), None).await
+    }
+
+    /// `GET /v1/files` — list uploaded files, newest first.
+    pub async fn list_files(&self, params: &ListFilesParams) -> Result<FileList, AnthropicError> {
+        let retry = self.resolve_retry(&RetryPolicy::default());
+        self.get("/v1/files", &params.as_query(), &files_betas(), retry).await
+    }
+
+    /// `GET /v1/files/{file_id}` — fetch metadata about a single file.
+    pub async fn get_file_metadata(&self, file_id: &str) -> Result<FileMetadata, AnthropicError> {
+        let path = format!("/v1/files/{}", file_id);
+        let retry = self.resolve_retry(&RetryPolicy::default());
+        self.get::<FileMetadata>(&path, &[], &files_betas(), retry).await
+    }
+
+    /// `GET /v1/files/{file_id}/content` — download a file's contents as a
+    /// byte stream. Only files created by tools are downloadable.
+    pub async fn download_file(&self, file_id: &str) -> Result<FileDownloadStream, AnthropicError> {
+        let mut headers = self.headers_with_betas(&files_betas())?;
+        headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+        let request =
+            self.http_client.get(format!("{}/v1/files/{}/content", self.api_base, file_id)).headers(headers).build()?;
+        let retry = self.resolve_retry(&RetryPolicy::default());
+        // The stream is consumed after this returns, so the span only covers
+        // the exchange up to the response headers.
+        self.execute_response(replay(request), retry, |response| async move {
+            Ok(Box::pin(response.bytes_stream().map(|chunk| chunk.map_err(AnthropicError::Http))) as FileDownloadStream)
+        })
+        .await
+    }
+
+    /// `DELETE /v1/files/{file_id}` — permanently delete a file.
+    pub async fn delete_file(&self, file_id: &str) -> Result<FileDeleted, AnthropicError> {
+        let path = format!("/v1/files/{}", file_id);
+        let retry = self.resolve_retry(&RetryPolicy::default());
+        self.delete::<FileDeleted>(&path, &files_betas(), retry).await
+    }
+
     /// Resolve an in-memory [`RetryPolicy`] to an optional [`ExponentialBackoff`].
     ///
     /// Returns `None` when retries should be disabled for this call. Returns
@@ -295,25 +461,49 @@ impl Client {
     }
 
     fn headers(&self) -> Result<HeaderMap, AnthropicError> {
+        self.headers_with_betas(&[])
+    }
+
+    /// Build the default headers, merging the client-wide `beta` with any
+    /// per-request betas into a single comma-separated `anthropic-beta`
+    /// header. Duplicates are dropped.
+    fn headers_with_betas(&self, betas: &[String]) -> Result<HeaderMap, AnthropicError> {
         let mut headers = HeaderMap::new();
         headers.insert(API_KEY_HEADER, HeaderValue::from_str(&self.api_key)?);
         headers.insert(VERSION_HEADER, HeaderValue::from_str(&self.api_version)?);
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
         headers.insert(USER_AGENT, HeaderValue::from_str(&format!("anthropic-rs/{}", env!("CARGO_PKG_VERSION")))?);
-        if let Some(beta) = &self.beta {
-            headers.insert(BETA_HEADER, HeaderValue::from_str(beta)?);
+        let mut merged: Vec<&str> = Vec::new();
+        let client_betas = self.beta.as_deref().unwrap_or_default().split(',');
+        for beta in client_betas.chain(betas.iter().map(String::as_str)).map(str::trim) {
+            if !beta.is_empty() && !merged.contains(&beta) {
+                merged.push(beta);
+            }
+        }
+        if !merged.is_empty() {
+            headers.insert(BETA_HEADER, HeaderValue::from_str(&merged.join(","))?);
         }
         Ok(headers)
     }
 
-    async fn post<I, O>(&self, path: &str, request: &I, retry: Option<ExponentialBackoff>) -> Result<O, AnthropicError>
+    async fn post<I, O>(
+        &self,
+        path: &str,
+        request: &I,
+        betas: &[String],
+        retry: Option<ExponentialBackoff>,
+    ) -> Result<O, AnthropicError>
     where
         I: Serialize + ?Sized,
         O: DeserializeOwned,
     {
-        let request =
-            self.http_client.post(format!("{}{path}", self.api_base)).headers(self.headers()?).json(request).build()?;
+        let request = self
+            .http_client
+            .post(format!("{}{path}", self.api_base))
+            .headers(self.headers_with_betas(betas)?)
+            .json(request)
+            .build()?;
 
         self.execute(request, retry).await
     }
@@ -322,13 +512,18 @@ impl Client {
         &self,
         path: &str,
         query: &[(&str, String)],
+        betas: &[String],
         retry: Option<ExponentialBackoff>,
     ) -> Result<O, AnthropicError>
     where
         O: DeserializeOwned,
     {
-        let request =
-            self.http_client.get(format!("{}{path}", self.api_base)).headers(self.headers()?).query(query).build()?;
+        let request = self
+            .http_client
+            .get(format!("{}{path}", self.api_base))
+            .headers(self.headers_with_betas(betas)?)
+            .query(query)
+            .build()?;
 
         self.execute(request, retry).await
     }
@@ -346,18 +541,51 @@ impl Client {
         self.execute(request, retry).await
     }
 
-    async fn delete<O>(&self, path: &str, retry: Option<ExponentialBackoff>) -> Result<O, AnthropicError>
+    async fn delete<O>(
+        &self,
+        path: &str,
+        betas: &[String],
+        retry: Option<ExponentialBackoff>,
+    ) -> Result<O, AnthropicError>
     where
         O: DeserializeOwned,
     {
-        let request = self.http_client.delete(format!("{}{path}", self.api_base)).headers(self.headers()?).build()?;
+        let request = self
+            .http_client
+            .delete(format!("{}{path}", self.api_base))
+            .headers(self.headers_with_betas(betas)?)
+            .build()?;
         self.execute(request, retry).await
     }
 
+    /// `form` is called once per attempt: a multipart body is a stream and
+    /// cannot be cloned, so retries need a freshly built form.
+    async fn post_multipart<O, F>(
+        &self,
+        path: &str,
+        mut form: F,
+        betas: &[String],
+        retry: Option<ExponentialBackoff>,
+    ) -> Result<O, AnthropicError>
+    where
+        O: DeserializeOwned,
+        F: FnMut() -> Result<Form, AnthropicError> + Send,
+    {
+        // reqwest sets the multipart boundary itself; drop the JSON default so
+        // the request doesn't carry two content types.
+        let mut headers = self.headers_with_betas(betas)?;
+        headers.remove(CONTENT_TYPE);
+        let url = format!("{}{path}", self.api_base);
+        let client = self.http_client.clone();
+        let next_request = move || Ok(client.post(&url).headers(headers.clone()).multipart(form()?).build()?);
+        self.execute_response(next_request, retry, read_json).await
+    }
+
     async fn post_stream<I>(
         &self,
         path: &str,
         request: &I,
+        betas: &[String],
     ) -> Result<Pin<Box<dyn Stream<Item = Result<MessagesStreamEvent, AnthropicError>> + Send>>, AnthropicError>
     where
         I: Serialize + ?Sized,
@@ -365,7 +593,7 @@ impl Client {
         let event_source = self
             .http_client
             .post(format!("{}{path}", self.api_base))
-            .headers(self.headers()?)
+            .headers(self.headers_with_betas(betas)?)
             .json(request)
             .eventsource()
             .map_err(|err| AnthropicError::EventSourceCannotClone(err.into()))?;
@@ -381,8 +609,8 @@ impl Client {
     where
         O: DeserializeOwned,
     {
-        let bytes = self.execute_bytes(request, retry).await?;
-        serde_json::from_slice::<O>(&bytes).map_err(AnthropicError::Deserialize)
+        let retry = retry.filter(|_| request.try_clone().is_some());
+        self.execute_response(replay(request), retry, read_json).await
     }
 
     async fn execute_raw(
@@ -390,30 +618,46 @@ impl Client {
         request: reqwest::Request,
         retry: Option<ExponentialBackoff>,
     ) -> Result<String, AnthropicError> {
-        let bytes = self.execute_bytes(request, retry).await?;
-        Ok(String::from_utf8_lossy(&bytes).into_owned())
+        // `reqwest::Request` cannot be cloned when its body is a stream. Fall
+        // back to a single attempt in that case — there's no safe way to
+        // retry a consumed body.
+        let retry = retry.filter(|_| request.try_clone().is_some());
+        self.execute_response(replay(request), retry, |response| async move {
+            let bytes = response.bytes().await?;
+            Ok(String::from_utf8_lossy(&bytes).into_owned())
+        })
+        .await
     }
 
-    /// Send a request with retry-on-429, returning the raw success body.
+    /// Send a request with retry-on-429 and hand the successful response to
+    /// `read`, which consumes the body.
     ///
-    /// All response parsing happens in callers; this method only deals with
-    /// transport, retries, and HTTP-level error mapping. When the `tracing`
-    /// Cargo feature is enabled, each call emits an `anthropic.http` span with
-    /// `method`, `path`, `attempts`, `status`, and `duration_ms` fields, plus
-    /// a per-attempt event carrying the attempt number, response status, and
-    /// attempt duration.
-    async fn execute_bytes(
+    /// `next_request` is called once per attempt. All response parsing
+    /// happens in `read`; this method only deals with transport, retries,
+    /// and HTTP-level error mapping. When the `tracing` Cargo feature is
+    /// enabled, each call emits an `anthropic.http` span with `method`,
+    /// `path`, `attempts`, `status`, and `duration_ms` fields, plus a
+    /// per-attempt event carrying the attempt number, response status, and
+    /// attempt duration. The span stays open while `read` runs, so body
+    /// reads and decoding are attributed to it too.
+    async fn execute_response<F, R, Fut, T>(
         &self,
-        request: reqwest::Request,
+        mut next_request: F,
         retry: Option<ExponentialBackoff>,
-    ) -> Result<Vec<u8>, AnthropicError> {
-        // Snapshot the method + path before the request is moved into the
-        // retry closure — they're used by the tracing span as well as any
-        // per-attempt events below.
+        read: R,
+    ) -> Result<T, AnthropicError>
+    where
+        F: FnMut() -> Result<reqwest::Request, AnthropicError> + Send,
+        R: FnOnce(reqwest::Response) -> Fut + Send,
+        Fut: Future<Output = Result<T, AnthropicError>> + Send,
+    {
+        // Build the first attempt up front so the method + path are known
+        // for the tracing span and any per-attempt events below.
+        let first = next_request()?;
         #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
-        let method = request.method().clone();
+        let method = first.method().clone();
         #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
-        let path = request.url().path().to_string();
+        let path = first.url().path().to_string();
 
         #[cfg(feature = "tracing")]
         let span = tracing::info_span!(
@@ -425,105 +669,151 @@ impl Client {
             duration_ms = tracing::field::Empty,
         );
         #[cfg(feature = "tracing")]
-        let _entered = span.enter();
+        let recorder = span.clone();
+
+        let exchange = async move {
+            #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+            let overall_started = Instant::now();
+            let attempt_counter = Arc::new(AtomicU32::new(0));
+            let result = self.send_with_retry(first, next_request, retry, &attempt_counter).await;
+
+            #[cfg(feature = "tracing")]
+            {
+                let attempts = attempt_counter.load(Ordering::SeqCst);
+                recorder.record("attempts", attempts);
+                match &result {
+                    Ok(_) => {
+                        recorder.record("status", 200u16);
+                    }
+                    Err(AnthropicError::Api(api)) => {
+                        tracing::warn!(
+                            target: "anthropic::http",
+                            error_type = %api.error_type,
+                            message = %api.message,
+                            "anthropic api error"
+                        );
+                    }
+                    Err(AnthropicError::UnexpectedResponse { status, .. }) => {
+                        recorder.record("status", *status);
+                    }
+                    Err(_) => {}
+                }
+            }
 
-        #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
-        let overall_started = Instant::now();
-        let attempt_counter = Arc::new(AtomicU32::new(0));
+            let output = match result {
+                Ok(response) => read(response).await,
+                Err(err) => Err(err),
+            };
+
+            #[cfg(feature = "tracing")]
+            recorder.record("duration_ms", overall_started.elapsed().as_millis() as u64);
+
+            output
+        };
+
+        #[cfg(feature = "tracing")]
+        let exchange = tracing::Instrument::instrument(exchange, span);
+
+        exchange.await
+    }
 
-        let result = match retry {
+    /// Run the attempts for one call, counting them in `attempt_counter`.
+    async fn send_with_retry<F>(
+        &self,
+        first: reqwest::Request,
+        mut next_request: F,
+        retry: Option<ExponentialBackoff>,
+        attempt_counter: &Arc<AtomicU32>,
+    ) -> Result<reqwest::Response, AnthropicError>
+    where
+        F: FnMut() -> Result<reqwest::Request, AnthropicError> + Send,
+    {
+        match retry {
             // No retries — fail on the first non-success response.
-            None => execute_once(self.http_client.clone(), request, &attempt_counter).await,
+            None => execute_once(self.http_client.clone(), first, attempt_counter).await,
             Some(backoff) => {
-                // `reqwest::Request` cannot be cloned when its body is a
-                // stream. Fall back to a single attempt in that case — there's
-                // no safe way to retry a consumed body.
-                match request.try_clone() {
-                    None => execute_once(self.http_client.clone(), request, &attempt_counter).await,
-                    Some(retryable) => {
-                        let client = self.http_client.clone();
-                        let attempts = attempt_counter.clone();
-                        backoff::future::retry(backoff, move || {
-                            let client = client.clone();
-                            let attempts = attempts.clone();
-                            let cloned = retryable.try_clone().ok_or_else(|| {
-                                backoff::Error::Permanent(AnthropicError::InvalidRequest(
-                                    "request could not be cloned".into(),
-                                ))
-                            });
-                            async move {
-                                let request = cloned?;
-                                #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
-                                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
-                                #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
-                                let attempt_started = Instant::now();
-
-                                let response = client
-                                    .execute(request)
-                                    .await
-                                    .map_err(AnthropicError::Http)
-                                    .map_err(backoff::Error::Permanent)?;
-
-                                let status = response.status();
-                                let retry_after =
-                                    parse_retry_after(response.headers().get(reqwest::header::RETRY_AFTER));
-                                let bytes = response
-                                    .bytes()
-                                    .await
-                                    .map_err(AnthropicError::Http)
-                                    .map_err(backoff::Error::Permanent)?;
-
-                                #[cfg(feature = "tracing")]
-                                tracing::debug!(
-                                    target: "anthropic::http",
-                                    attempt,
-                                    status = status.as_u16(),
-                                    duration_ms = attempt_started.elapsed().as_millis() as u64,
-                                    "anthropic.http.attempt"
-                                );
-
-                                if !status.is_success() {
-                                    let error = parse_error(status.as_u16(), bytes.as_ref());
-                                    if status.as_u16() == 429 {
-                                        return Err(backoff::Error::Transient { err: error, retry_after });
-                                    }
-                                    return Err(backoff::Error::Permanent(error));
-                                }
-
-                                Ok(bytes.to_vec())
+                let client = self.http_client.clone();
+                let attempts = attempt_counter.clone();
+                let mut first = Some(first);
+                backoff::future::retry(backoff, move || {
+                    let client = client.clone();
+                    let attempts = attempts.clone();
+                    let request = match first.take() {
+                        Some(request) => Ok(request),
+                        None => next_request().map_err(backoff::Error::Permanent),
+                    };
+                    async move {
+                        let request = request?;
+                        #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                        let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                        #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                        let attempt_started = Instant::now();
+
+                        let response = client
+                            .execute(request)
+                            .await
+                            .map_err(AnthropicError::Http)
+                            .map_err(backoff::Error::Permanent)?;
+
+                        let status = response.status();
+
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!(
+                            target: "anthropic::http",
+                            attempt,
+                            status = status.as_u16(),
+                            duration_ms = attempt_started.elapsed().as_millis() as u64,
+                            "anthropic.http.attempt"
+                        );
+
+                        if !status.is_success() {
+                            let retry_after = parse_retry_after(response.headers().get(reqwest::header::RETRY_AFTER));
+                            let bytes = response
+                                .bytes()
+                                .await
+                                .map_err(AnthropicError::Http)
+                                .map_err(backoff::Error::Permanent)?;
+                            let error = parse_error(status.as_u16(), bytes.as_ref());
+                            if status.as_u16() == 429 {
+                                return Err(backoff::Error::Transient { err: error, retry_after });
                             }
-                        })
-                        .await
+                            return Err(backoff::Error::Permanent(error));
+                        }
+
+                        Ok(response)
                     }
-                }
+                })
+                .await
             }
-        };
+        }
+    }
+}
 
+/// Read a successful response body and decode it as JSON.
+async fn read_json<O: DeserializeOwned>(response: reqwest::Response) -> Result<O, AnthropicError> {
+    let bytes = response.bytes().await?;
+    serde_json::from_slice::<O>(&bytes).map_err(|err| {
         #[cfg(feature = "tracing")]
-        {
-            let attempts = attempt_counter.load(Ordering::SeqCst);
-            span.record("attempts", attempts);
-            span.record("duration_ms", overall_started.elapsed().as_millis() as u64);
-            match &result {
-                Ok(_) => {
-                    span.record("status", 200u16);
-                }
-                Err(AnthropicError::Api(api)) => {
-                    tracing::warn!(
-                        target: "anthropic::http",
-                        error_type = %api.error_type,
-                        message = %api.message,
-                        "anthropic api error"
-                    );
-                }
-                Err(AnthropicError::UnexpectedResponse { status, .. }) => {
-                    span.record("status", *status);
-                }
-                Err(_) => {}
-            }
-        }
+        tracing::warn!(target: "anthropic::http", error = %err, "anthropic response could not be decoded");
+        AnthropicError::Deserialize(err)
+    })
+}
+
+fn files_betas() -> Vec<String> {
+    vec![FILES_API_BETA.to_string()]
+}
 
-        result
+/// Turn a built request into a per-attempt request source.
+///
+/// The first call hands back the original; later calls return clones of it,
+/// failing if the body is a stream that cannot be cloned.
+fn replay(request: reqwest::Request) -> impl FnMut() -> Result<reqwest::Request, AnthropicError> + Send {
+    let mut template = Some(request);
+    move || {
+        let request =
+            template.take().ok_or_else(|| AnthropicError::InvalidRequest("request could not be cloned".into()))?;
+        template = request.try_clone();
+        Ok(request)
     }
 }
 
@@ -533,14 +823,13 @@ async fn execute_once(
     client: reqwest::Client,
     request: reqwest::Request,
     attempts: &AtomicU32,
-) -> Result<Vec<u8>, AnthropicError> {
+) -> Result<reqwest::Response, AnthropicError> {
     #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
     let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
     #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
     let started = Instant::now();
     let response = client.execute(request).await?;
     let status = response.status();
-    let bytes = response.bytes().await?;
 
     #[cfg(feature = "tracing")]
     tracing::debug!(
@@ -552,9 +841,10 @@ async fn execute_once(
     );
 
     if !status.is_success() {
+        let bytes = response.bytes().await?;
         return Err(parse_error(status.as_u16(), bytes.as_ref()));
     }
-    Ok(bytes.to_vec())
+    Ok(response)
 }
 
 /// Parse a `Retry-After` header value into a [`Duration`].
@@ -676,6 +966,18 @@ mod tests {
         let _cloned = client.clone();
     }
 
+    #[test]
+    fn headers_merge_client_and_request_betas() {
+        let client = Client::builder().api_key("k").beta("a-beta, b-beta").build().unwrap();
+        let headers = client.headers_with_betas(&["b-beta".into(), "c-beta".into()]).unwrap();
+        assert_eq!(headers.get(BETA_HEADER).unwrap(), "a-beta,b-beta,c-beta");
+
+        let client = Client::builder().api_key("k").build().unwrap();
+        assert!(client.headers().unwrap().get(BETA_HEADER).is_none());
+        let headers = client.headers_with_betas(&["c-beta".into()]).unwrap();
+        assert_eq!(headers.get(BETA_HEADER).unwrap(), "c-beta");
+    }
+
     #[test]
     fn parse_retry_after_handles_seconds() {
         let header = HeaderValue::from_static("30");
@@ -0,0 +1,551 @@
+//! Anthropic-defined client tools: computer use, bash, and the text editor.
+//!
+//! These tools have schemas built into the model, so the request only names
+//! a versioned tool type instead of sending a JSON schema. Unlike server tools
+//! they are executed by the caller: Claude emits ordinary `tool_use` blocks
+//! and the `input` of each one parses into a typed action
+//! ([`ComputerAction`], [`BashAction`], [`TextEditorCommand`]) that executors
+//! passed to [`run_tool_loop`](crate::tool_loop::run_tool_loop) can `match` on.
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::CacheControl;
+
+/// Any of the Anthropic-defined client tools.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum BuiltinTool {
+    Computer(ComputerTool),
+    Bash(BashTool),
+    TextEditor(TextEditorTool),
+}
+
+impl BuiltinTool {
+    /// Name the model uses to call this tool.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Computer(tool) => &tool.name,
+            Self::Bash(tool) => &tool.name,
+            Self::TextEditor(tool) => &tool.name,
+        }
+    }
+
+    /// `anthropic-beta` flag the API requires before it accepts this tool.
+    pub fn required_beta(&self) -> Option<&'static str> {
+        match self {
+            Self::Computer(tool) => Some(tool.version.beta()),
+            Self::Bash(tool) => tool.version.beta(),
+            Self::TextEditor(tool) => tool.version.beta(),
+        }
+    }
+}
+
+/// Versions of the computer use tool.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+pub enum ComputerToolVersion {
+    #[serde(rename = "computer_20241022")]
+    V20241022,
+    #[default]
+    #[serde(rename = "computer_20250124")]
+    V20250124,
+}
+
+impl ComputerToolVersion {
+    fn beta(self) -> &'static str {
+        match self {
+            Self::V20241022 => "computer-use-2024-10-22",
+            Self::V20250124 => "computer-use-2025-01-24",
+        }
+    }
+}
+
+/// Computer use tool: screenshots plus mouse and keyboard control of a
+/// display of the given size.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ComputerTool {
+    #[serde(rename = "type")]
+    pub version: ComputerToolVersion,
+    pub name: String,
+    pub display_width_px: u32,
+    pub display_height_px: u32,
+    /// X11 display number, for multi-display setups.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_number: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+impl ComputerTool {
+    /// Latest computer use tool for a display of `width` x `height` pixels.
+    pub fn new(display_width_px: u32, display_height_px: u32) -> Self {
+        Self {
+            version: ComputerToolVersion::default(),
+            name: "computer".into(),
+            display_width_px,
+            display_height_px,
+            display_number: None,
+            cache_control: None,
+        }
+    }
+
+    pub fn version(mut self, version: ComputerToolVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn display_number(mut self, display_number: u32) -> Self {
+        self.display_number = Some(display_number);
+        self
+    }
+
+    pub fn with_cache_control(mut self, cache: CacheControl) -> Self {
+        self.cache_control = Some(cache);
+        self
+    }
+}
+
+/// Versions of the bash tool.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+pub enum BashToolVersion {
+    #[serde(rename = "bash_20241022")]
+    V20241022,
+    #[default]
+    #[serde(rename = "bash_20250124")]
+    V20250124,
+}
+
+impl BashToolVersion {
+    fn beta(self) -> Option<&'static str> {
+        match self {
+            Self::V20241022 => Some("computer-use-2024-10-22"),
+            Self::V20250124 => None,
+        }
+    }
+}
+
+/// Bash tool: a persistent shell session driven by [`BashAction`]s.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BashTool {
+    #[serde(rename = "type")]
+    pub version: BashToolVersion,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+impl Default for BashTool {
+    fn default() -> Self {
+        Self { version: BashToolVersion::default(), name: "bash".into(), cache_control: None }
+    }
+}
+
+impl BashTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn version(mut self, version: BashToolVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn with_cache_control(mut self, cache: CacheControl) -> Self {
+        self.cache_control = Some(cache);
+        self
+    }
+}
+
+/// Versions of the text editor tool. The tool name changed with
+/// `text_editor_20250429`, and `undo_edit` is only available before it.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+pub enum TextEditorToolVersion {
+    #[serde(rename = "text_editor_20241022")]
+    V20241022,
+    #[serde(rename = "text_editor_20250124")]
+    V20250124,
+    #[serde(rename = "text_editor_20250429")]
+    V20250429,
+    #[default]
+    #[serde(rename = "text_editor_20250728")]
+    V20250728,
+}
+
+impl TextEditorToolVersion {
+    /// Name the model uses for this version of the tool.
+    pub fn tool_name(self) -> &'static str {
+        match self {
+            Self::V20241022 | Self::V20250124 => "str_replace_editor",
+            Self::V20250429 | Self::V20250728 => "str_replace_based_edit_tool",
+        }
+    }
+
+    fn beta(self) -> Option<&'static str> {
+        match self {
+            Self::V20241022 => Some("computer-use-2024-10-22"),
+            _ => None,
+        }
+    }
+}
+
+/// Text editor tool: view and edit files through [`TextEditorCommand`]s.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TextEditorTool {
+    #[serde(rename = "type")]
+    pub version: TextEditorToolVersion,
+    pub name: String,
+    /// Truncate `view` output after this many characters
+    /// (`text_editor_20250728` only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_characters: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+impl Default for TextEditorTool {
+    fn default() -> Self {
+        Self::with_version(TextEditorToolVersion::default())
+    }
+}
+
+impl TextEditorTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Text editor tool pinned to `version`, with the matching tool name.
+    pub fn with_version(version: TextEditorToolVersion) -> Self {
+        Self { version, name: version.tool_name().into(), max_characters: None, cache_control: None }
+    }
+
+    pub fn max_characters(mut self, max_characters: u32) -> Self {
+        self.max_characters = Some(max_characters);
+        self
+    }
+
+    pub fn with_cache_control(mut self, cache: CacheControl) -> Self {
+        self.cache_control = Some(cache);
+        self
+    }
+}
+
+/// `[x, y]` pixel position on the display.
+pub type Coordinate = [u32; 2];
+
+/// Direction of a [`ComputerAction::Scroll`].
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Input of a computer use `tool_use` block.
+///
+/// Click actions take an optional `text` holding modifier keys to press
+/// during the click (e.g. `"shift"`).
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "action")]
+pub enum ComputerAction {
+    Screenshot,
+    CursorPosition,
+    MouseMove {
+        coordinate: Coordinate,
+    },
+    LeftClick {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        coordinate: Option<Coordinate>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
+    RightClick {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        coordinate: Option<Coordinate>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
+    MiddleClick {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        coordinate: Option<Coordinate>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
+    DoubleClick {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        coordinate: Option<Coordinate>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
+    TripleClick {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        coordinate: Option<Coordinate>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
+    /// Drag from `start_coordinate` (or the current cursor position) to
+    /// `coordinate`.
+    LeftClickDrag {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        start_coordinate: Option<Coordinate>,
+        coordinate: Coordinate,
+    },
+    LeftMouseDown {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        coordinate: Option<Coordinate>,
+    },
+    LeftMouseUp {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        coordinate: Option<Coordinate>,
+    },
+    Scroll {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        coordinate: Option<Coordinate>,
+        scroll_direction: ScrollDirection,
+        scroll_amount: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
+    /// Type a string of text.
+    Type {
+        text: String,
+    },
+    /// Press a key or key combination in xdotool syntax (e.g. `"ctrl+s"`).
+    Key {
+        text: String,
+    },
+    /// Hold a key for `duration` seconds.
+    HoldKey {
+        text: String,
+        duration: f64,
+    },
+    /// Wait for `duration` seconds.
+    Wait {
+        duration: f64,
+    },
+}
+
+impl ComputerAction {
+    /// Parse the `input` of a computer use `tool_use` block.
+    pub fn from_input(input: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        Self::deserialize(input)
+    }
+}
+
+/// Input of a bash `tool_use` block.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(try_from = "RawBashAction", into = "RawBashAction")]
+pub enum BashAction {
+    /// Run a command in the persistent shell.
+    Command { command: String },
+    /// Restart the shell session.
+    Restart,
+}
+
+impl BashAction {
+    /// Parse the `input` of a bash `tool_use` block.
+    pub fn from_input(input: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        Self::deserialize(input)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawBashAction {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    restart: bool,
+}
+
+impl TryFrom<RawBashAction> for BashAction {
+    type Error = String;
+
+    fn try_from(raw: RawBashAction) -> Result<Self, Self::Error> {
+        match (raw.restart, raw.command) {
+            (true, _) => Ok(Self::Restart),
+            (false, Some(command)) => Ok(Self::Command { command }),
+            (false, None) => Err("bash input needs either `command` or `restart: true`".into()),
+        }
+    }
+}
+
+impl From<BashAction> for RawBashAction {
+    fn from(action: BashAction) -> Self {
+        match action {
+            BashAction::Command { command } => Self { command: Some(command), restart: false },
+            BashAction::Restart => Self { command: None, restart: true },
+        }
+    }
+}
+
+/// Input of a text editor `tool_use` block.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "command")]
+pub enum TextEditorCommand {
+    /// Show a file (optionally only the 1-indexed `[start, end]` lines; an
+    /// `end` of `-1` means end of file) or list a directory.
+    View {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        view_range: Option<[i64; 2]>,
+    },
+    /// Create a new file, or overwrite an existing one.
+    Create { path: String, file_text: String },
+    /// Replace the single exact occurrence of `old_str` with `new_str`.
+    StrReplace {
+        path: String,
+        old_str: String,
+        #[serde(default)]
+        new_str: String,
+    },
+    /// Insert `new_str` after line `insert_line` (0 inserts at the top).
+    Insert {
+        path: String,
+        insert_line: u32,
+        #[serde(alias = "insert_text")]
+        new_str: String,
+    },
+    /// Revert the last edit made to `path`. Removed in `text_editor_20250429`.
+    UndoEdit { path: String },
+}
+
+impl TextEditorCommand {
+    /// Parse the `input` of a text editor `tool_use` block.
+    pub fn from_input(input: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        Self::deserialize(input)
+    }
+
+    /// Path the command operates on.
+    pub fn path(&self) -> &str {
+        match self {
+            Self::View { path, .. }
+            | Self::Create { path, .. }
+            | Self::StrReplace { path, .. }
+            | Self::Insert { path, .. }
+            | Self::UndoEdit { path } => path,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ToolDefinition;
+    use serde_json::json;
+
+    #[test]
+    fn tool_definitions_serialize_with_versioned_types() {
+        let computer: ToolDefinition = ComputerTool::new(1024, 768).display_number(1).into();
+        assert_eq!(
+            serde_json::to_value(&computer).unwrap(),
+            json!({
+                "type": "computer_20250124",
+                "name": "computer",
+                "display_width_px": 1024,
+                "display_height_px": 768,
+                "display_number": 1
+            })
+        );
+        assert_eq!(computer.required_beta(), Some("computer-use-2025-01-24"));
+        assert!(!computer.is_server_tool());
+
+        let bash: ToolDefinition = BashTool::new().into();
+        assert_eq!(serde_json::to_value(&bash).unwrap(), json!({"type": "bash_20250124", "name": "bash"}));
+        assert_eq!(bash.required_beta(), None);
+
+        let editor: ToolDefinition = TextEditorTool::new().max_characters(10_000).into();
+        assert_eq!(
+            serde_json::to_value(&editor).unwrap(),
+            json!({"type": "text_editor_20250728", "name": "str_replace_based_edit_tool", "max_characters": 10000})
+        );
+        let old = TextEditorTool::with_version(TextEditorToolVersion::V20250124);
+        assert_eq!(old.name, "str_replace_editor");
+    }
+
+    #[test]
+    fn tool_definitions_deserialize_into_builtin_variant() {
+        let tools: Vec<ToolDefinition> = serde_json::from_value(json!([
+            {"type": "bash_20241022", "name": "bash"},
+            {"type": "computer_20241022", "name": "computer", "display_width_px": 800, "display_height_px": 600},
+            {"name": "custom", "description": "d", "input_schema": {"type": "object"}}
+        ]))
+        .unwrap();
+        assert_eq!(
+            tools[0],
+            ToolDefinition::Builtin(BuiltinTool::Bash(BashTool::new().version(BashToolVersion::V20241022)))
+        );
+        assert_eq!(tools[0].required_beta(), Some("computer-use-2024-10-22"));
+        assert!(matches!(&tools[1], ToolDefinition::Builtin(BuiltinTool::Computer(c)) if c.display_width_px == 800));
+        assert!(tools[2].as_custom().is_some());
+    }
+
+    #[test]
+    fn parses_computer_actions() {
+        assert_eq!(ComputerAction::from_input(&json!({"action": "screenshot"})).unwrap(), ComputerAction::Screenshot);
+        assert_eq!(
+            ComputerAction::from_input(&json!({"action": "left_click", "coordinate": [10, 20]})).unwrap(),
+            ComputerAction::LeftClick { coordinate: Some([10, 20]), text: None }
+        );
+        assert_eq!(
+            ComputerAction::from_input(&json!({"action": "type", "text": "hello"})).unwrap(),
+            ComputerAction::Type { text: "hello".into() }
+        );
+        assert_eq!(
+            ComputerAction::from_input(&json!({
+                "action": "scroll",
+                "coordinate": [5, 5],
+                "scroll_direction": "down",
+                "scroll_amount": 3
+            }))
+            .unwrap(),
+            ComputerAction::Scroll {
+                coordinate: Some([5, 5]),
+                scroll_direction: ScrollDirection::Down,
+                scroll_amount: 3,
+                text: None
+            }
+        );
+        assert!(ComputerAction::from_input(&json!({"action": "teleport"})).is_err());
+    }
+
+    #[test]
+    fn parses_bash_actions() {
+        assert_eq!(
+            BashAction::from_input(&json!({"command": "ls -la"})).unwrap(),
+            BashAction::Command { command: "ls -la".into() }
+        );
+        assert_eq!(BashAction::from_input(&json!({"restart": true})).unwrap(), BashAction::Restart);
+        assert!(BashAction::from_input(&json!({})).is_err());
+        assert_eq!(serde_json::to_value(BashAction::Restart).unwrap(), json!({"restart": true}));
+    }
+
+    #[test]
+    fn parses_text_editor_commands() {
+        let cmd = TextEditorCommand::from_input(&json!({
+            "command": "str_replace",
+            "path": "src/main.rs",
+            "old_str": "foo",
+            "new_str": "bar"
+        }))
+        .unwrap();
+        assert_eq!(
+            cmd,
+            TextEditorCommand::StrReplace { path: "src/main.rs".into(), old_str: "foo".into(), new_str: "bar".into() }
+        );
+        assert_eq!(cmd.path(), "src/main.rs");
+        assert_eq!(
+            TextEditorCommand::from_input(&json!({"command": "view", "path": "a.txt", "view_range": [1, -1]})).unwrap(),
+            TextEditorCommand::View { path: "a.txt".into(), view_range: Some([1, -1]) }
+        );
+        assert_eq!(
+            TextEditorCommand::from_input(&json!({
+                "command": "insert",
+                "path": "a.txt",
+                "insert_line": 2,
+                "insert_text": "x"
+            }))
+            .unwrap(),
+            TextEditorCommand::Insert { path: "a.txt".into(), insert_line: 2, new_str: "x".into() }
+        );
+    }
+}
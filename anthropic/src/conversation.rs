@@ -0,0 +1,144 @@
+//! Stateful multi-turn conversations.
+//!
+//! [`Conversation`] keeps the running transcript for a request template and
+//! appends each assistant reply after a successful call. It also remembers
+//! the code execution container returned by the API and sends its id on every
+//! following turn, so files and interpreter state created by
+//! [`CodeExecutionTool`](crate::types::CodeExecutionTool) stay available.
+//!
+//! Assistant replies are recorded verbatim, so `thinking` and
+//! `redacted_thinking` blocks keep their signatures and are sent back
+//! unchanged, as extended thinking with tool use requires.
+
+use crate::client::Client;
+use crate::error::AnthropicError;
+use crate::types::{Message, MessagesRequest, MessagesResponse, Role};
+
+/// A multi-turn conversation built on top of a [`MessagesRequest`] template.
+///
+/// Everything except `messages` and `container` (model, system prompt,
+/// tools, sampling parameters, betas, retry policy) is taken from the
+/// template unchanged on every turn.
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    request: MessagesRequest,
+}
+
+impl Conversation {
+    /// Start a conversation from `request`. Its messages become the opening
+    /// transcript and its `container`, if set, is reused.
+    pub fn new(request: MessagesRequest) -> Self {
+        Self { request }
+    }
+
+    /// The transcript so far.
+    pub fn messages(&self) -> &[Message] {
+        &self.request.messages
+    }
+
+    /// The request that the next [`Conversation::send`] will issue.
+    pub fn request(&self) -> &MessagesRequest {
+        &self.request
+    }
+
+    /// Id of the code execution container reused by this conversation.
+    pub fn container(&self) -> Option<&str> {
+        self.request.container.as_deref()
+    }
+
+    /// Append a message to the transcript without sending it.
+    pub fn push(&mut self, message: Message) {
+        self.request.messages.push(message);
+    }
+
+    /// Send the transcript and append the assistant reply to it.
+    ///
+    /// On error the transcript is left untouched so the call can be retried.
+    /// An incompatible thinking budget is rejected before sending (see
+    /// [`MessagesRequest::validate_thinking`]).
+    pub async fn send(&mut self, client: &Client) -> Result<MessagesResponse, AnthropicError> {
+        self.request.validate_thinking()?;
+        let response = client.messages(self.request.clone()).await?;
+        self.record(&response);
+        Ok(response)
+    }
+
+    /// Append `message` and [`send`](Conversation::send) the transcript.
+    ///
+    /// If the call fails the message stays in the transcript.
+    pub async fn send_message(
+        &mut self,
+        client: &Client,
+        message: Message,
+    ) -> Result<MessagesResponse, AnthropicError> {
+        self.push(message);
+        self.send(client).await
+    }
+
+    /// Consume the conversation and return the final request state.
+    pub fn into_request(self) -> MessagesRequest {
+        self.request
+    }
+
+    fn record(&mut self, response: &MessagesResponse) {
+        if let Some(container) = &response.container {
+            self.request.container = Some(container.id.clone());
+        }
+        self.request.messages.push(Message::new(Role::Assistant, response.content.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Container, ContentBlock, MessagesRequestBuilder, StopReason, Usage};
+
+    fn response(text: &str, container: Option<&str>) -> MessagesResponse {
+        MessagesResponse {
+            id: "msg_1".into(),
+            message_type: "message".into(),
+            role: Role::Assistant,
+            content: vec![ContentBlock::text(text)],
+            model: "claude".into(),
+            stop_reason: Some(StopReason::EndTurn),
+            stop_sequence: None,
+            usage: Usage::default(),
+            container: container.map(|id| Container { id: id.into(), expires_at: "2025-05-23T00:00:00Z".into() }),
+        }
+    }
+
+    #[test]
+    fn record_appends_reply_and_remembers_container() {
+        let request = MessagesRequestBuilder::new("m", vec![Message::user("hi")], 10).build().unwrap();
+        let mut conversation = Conversation::new(request);
+        assert_eq!(conversation.container(), None);
+
+        conversation.record(&response("hello", Some("container_1")));
+        assert_eq!(conversation.messages().len(), 2);
+        assert_eq!(conversation.messages()[1].role, Role::Assistant);
+        assert_eq!(conversation.container(), Some("container_1"));
+
+        // A response without a container keeps the one already in use.
+        conversation.push(Message::user("again"));
+        conversation.record(&response("ok", None));
+        assert_eq!(conversation.messages().len(), 4);
+        assert_eq!(conversation.into_request().container.as_deref(), Some("container_1"));
+    }
+
+    #[test]
+    fn record_keeps_thinking_blocks_verbatim() {
+        let request = MessagesRequestBuilder::new("m", vec![Message::user("hi")], 2048)
+            .thinking(crate::types::ThinkingConfig::enabled(1024))
+            .build()
+            .unwrap();
+        let mut conversation = Conversation::new(request);
+        let mut reply = response("done", None);
+        reply
+            .content
+            .insert(0, ContentBlock::Thinking { thinking: "let me see".into(), signature: Some("sig_1".into()) });
+        reply.content.insert(1, ContentBlock::RedactedThinking { data: "opaque".into() });
+
+        conversation.record(&reply);
+        assert_eq!(conversation.messages()[1].content, reply.content);
+    }
+}
@@ -7,7 +7,11 @@ use backoff::ExponentialBackoff;
 use serde::{Deserialize, Serialize};
 
 use crate::error::AnthropicError;
-use crate::types::{Message, MessagesRequest, RetryPolicy, SystemPrompt, ThinkingConfig, Tool, ToolChoice};
+use crate::files::FILES_API_BETA;
+use crate::types::{
+    collect_betas, references_files, Message, MessagesRequest, RetryPolicy, SystemPrompt, ThinkingConfig, ToolChoice,
+    ToolDefinition,
+};
 
 /// Request payload for `POST /v1/messages/count_tokens`.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
@@ -17,11 +21,14 @@ pub struct CountTokensRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system: Option<SystemPrompt>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tools: Option<Vec<Tool>>,
+    pub tools: Option<Vec<ToolDefinition>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<ToolChoice>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub thinking: Option<ThinkingConfig>,
+    /// Extra `anthropic-beta` flags. Carried in memory only; never serialized.
+    #[serde(skip, default)]
+    pub betas: Vec<String>,
     /// Per-request retry policy. Carried in memory only; never serialized.
     #[serde(skip, default)]
     pub retry_policy: RetryPolicy,
@@ -31,8 +38,8 @@ impl CountTokensRequest {
     /// Build a count-tokens request from an existing [`MessagesRequest`].
     ///
     /// Fields that don't affect token counting (max_tokens, temperature, etc.)
-    /// are dropped. The source request's retry policy is propagated so the
-    /// count-tokens call inherits the same retry behavior.
+    /// are dropped. The source request's betas and retry policy are
+    /// propagated so the count-tokens call inherits the same behavior.
     pub fn from_messages_request(request: &MessagesRequest) -> Self {
         Self {
             model: request.model.clone(),
@@ -41,9 +48,17 @@ impl CountTokensRequest {
             tools: request.tools.clone(),
             tool_choice: request.tool_choice.clone(),
             thinking: request.thinking.clone(),
+            betas: request.betas.clone(),
             retry_policy: request.retry_policy.clone(),
         }
     }
+
+    /// Every `anthropic-beta` flag this request needs. See
+    /// [`MessagesRequest::required_betas`].
+    pub fn required_betas(&self) -> Vec<String> {
+        let files = references_files(&self.messages);
+        collect_betas(&self.betas, self.tools.as_deref(), files.then_some(FILES_API_BETA))
+    }
 }
 
 /// Builder for [`CountTokensRequest`].
@@ -52,9 +67,10 @@ pub struct CountTokensRequestBuilder {
     model: Option<String>,
     messages: Option<Vec<Message>>,
     system: Option<SystemPrompt>,
-    tools: Option<Vec<Tool>>,
+    tools: Option<Vec<ToolDefinition>>,
     tool_choice: Option<ToolChoice>,
     thinking: Option<ThinkingConfig>,
+    betas: Vec<String>,
     retry_policy: RetryPolicy,
 }
 
@@ -68,8 +84,12 @@ impl CountTokensRequestBuilder {
         self
     }
 
-    pub fn tools(mut self, tools: Vec<Tool>) -> Self {
-        self.tools = Some(tools);
+    pub fn tools<I, T>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<ToolDefinition>,
+    {
+        self.tools = Some(tools.into_iter().map(Into::into).collect());
         self
     }
 
@@ -83,6 +103,12 @@ impl CountTokensRequestBuilder {
         self
     }
 
+    /// Send an extra `anthropic-beta` flag with this request.
+    pub fn beta(mut self, beta: impl Into<String>) -> Self {
+        self.betas.push(beta.into());
+        self
+    }
+
     /// Override the retry policy for this request. See [`RetryPolicy`].
     pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
         self.retry_policy = policy;
@@ -117,6 +143,7 @@ impl CountTokensRequestBuilder {
             tools: self.tools,
             tool_choice: self.tool_choice,
             thinking: self.thinking,
+            betas: self.betas,
             retry_policy: self.retry_policy,
         })
     }
@@ -131,7 +158,7 @@ pub struct CountTokensResponse {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::MessagesRequestBuilder;
+    use crate::types::{MessagesRequestBuilder, Tool};
     use serde_json::json;
 
     #[test]
@@ -139,7 +166,7 @@ mod tests {
         let req = MessagesRequestBuilder::new("claude", vec![Message::user("hi")], 100)
             .system("be nice")
             .tools(vec![Tool::new("t", "d", json!({}))])
-            .tool_choice(ToolChoice::Auto)
+            .tool_choice(ToolChoice::auto().disable_parallel_tool_use())
             .thinking(ThinkingConfig::enabled(256))
             .build()
             .unwrap();
@@ -149,7 +176,7 @@ mod tests {
         assert_eq!(ct.messages.len(), 1);
         assert!(ct.system.is_some());
         assert!(ct.tools.is_some());
-        assert_eq!(ct.tool_choice, Some(ToolChoice::Auto));
+        assert_eq!(ct.tool_choice, Some(ToolChoice::auto().disable_parallel_tool_use()));
         assert_eq!(ct.thinking, Some(ThinkingConfig::enabled(256)));
     }
 
@@ -0,0 +1,323 @@
+//! Helpers for building document content blocks.
+//!
+//! - [`ContentBlock::pdf_from_path`](crate::types::ContentBlock::pdf_from_path)
+//!   / [`pdf_from_bytes`](crate::types::ContentBlock::pdf_from_bytes) check
+//!   the API's PDF limits ([`MAX_PDF_BYTES`], [`MAX_PDF_PAGES`]) locally
+//!   before base64-encoding.
+//! - [`chunk_text`] splits a long corpus on paragraph boundaries so it can be
+//!   sent as a custom-content document via
+//!   [`ContentBlock::document_chunks`](crate::types::ContentBlock::document_chunks);
+//!   citations then point at whole chunks (`content_block_location`).
+//! - [`search_results`] turns ranked passages from a retrieval pipeline into
+//!   `search_result` blocks, which the model cites with
+//!   `search_result_location` citations.
+//! - [`DocumentSet`] attaches the same documents to many requests with a
+//!   cache breakpoint after the last one, so every request shares a single
+//!   cached prefix.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::error::AnthropicError;
+use crate::types::{CacheControl, ContentBlock, Message, Role};
+
+/// Largest PDF, in bytes, accepted by the API.
+pub const MAX_PDF_BYTES: usize = 32 * 1024 * 1024;
+
+/// Largest number of pages accepted in a single PDF.
+pub const MAX_PDF_PAGES: usize = 100;
+
+/// Validate `bytes` against the PDF limits and return the base64 data.
+pub(crate) fn encode_pdf(bytes: &[u8]) -> Result<String, AnthropicError> {
+    if !bytes.starts_with(b"%PDF-") {
+        return Err(AnthropicError::InvalidRequest("not a PDF file (missing %PDF- header)".into()));
+    }
+    if bytes.len() > MAX_PDF_BYTES {
+        return Err(AnthropicError::InvalidRequest(format!(
+            "PDF is {} bytes; the maximum is {MAX_PDF_BYTES}",
+            bytes.len()
+        )));
+    }
+    if let Some(pages) = pdf_page_count(bytes) {
+        if pages > MAX_PDF_PAGES {
+            return Err(AnthropicError::InvalidRequest(format!(
+                "PDF has {pages} pages; the maximum is {MAX_PDF_PAGES}"
+            )));
+        }
+    }
+    Ok(STANDARD.encode(bytes))
+}
+
+/// Best-effort page count: the number of `/Type /Page` objects in the file.
+///
+/// Returns `None` when no page objects are visible, for example when they
+/// live in compressed object streams. The API still enforces the limit in
+/// that case.
+pub fn pdf_page_count(bytes: &[u8]) -> Option<usize> {
+    let mut pages = 0;
+    let mut rest = bytes;
+    while let Some(at) = find(rest, b"/Type") {
+        rest = &rest[at + b"/Type".len()..];
+        let value = trim_start(rest);
+        if value.starts_with(b"/Page") && !value.get(5).is_some_and(|b| b.is_ascii_alphanumeric()) {
+            pages += 1;
+        }
+    }
+    (pages > 0).then_some(pages)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn trim_start(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// Split `text` into chunks of at most `max_chars` characters.
+///
+/// Paragraphs (separated by blank lines) are packed greedily into chunks.
+/// A paragraph longer than `max_chars` is split at the last whitespace
+/// before the limit, or mid-word if it has none. Chunks are trimmed and
+/// never empty.
+pub fn chunk_text(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_chars = 0;
+
+    let paragraphs = text.split("\n\n").map(str::trim).filter(|p| !p.is_empty());
+    for piece in paragraphs.flat_map(|p| split_long(p, max_chars)) {
+        let piece_chars = piece.chars().count();
+        if !current.is_empty() && current_chars + 2 + piece_chars > max_chars {
+            chunks.push(std::mem::take(&mut current));
+            current_chars = 0;
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+            current_chars += 2;
+        }
+        current.push_str(piece);
+        current_chars += piece_chars;
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn split_long(mut paragraph: &str, max_chars: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    while let Some((limit, _)) = paragraph.char_indices().nth(max_chars) {
+        let cut = paragraph[..limit].rfind(char::is_whitespace).filter(|&at| at > 0).unwrap_or(limit);
+        pieces.push(paragraph[..cut].trim_end());
+        paragraph = paragraph[cut..].trim_start();
+    }
+    if !paragraph.is_empty() {
+        pieces.push(paragraph);
+    }
+    pieces
+}
+
+/// A passage returned by a retrieval pipeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Passage {
+    /// URL or other identifier of the source the passage came from.
+    pub source: String,
+    pub title: String,
+    pub text: String,
+}
+
+impl Passage {
+    pub fn new(source: impl Into<String>, title: impl Into<String>, text: impl Into<String>) -> Self {
+        Self { source: source.into(), title: title.into(), text: text.into() }
+    }
+}
+
+/// Convert a ranked list of passages into citable `search_result` blocks.
+///
+/// Passages sharing a `source` are merged into a single block (one text
+/// block per passage, in rank order) so the model sees each source once.
+/// Blocks are ordered by the rank of their best passage and keep the title
+/// of that passage. Send them in a user message or return them from a tool
+/// with [`ToolOutput::blocks`](crate::ToolOutput::blocks).
+pub fn search_results<I>(passages: I) -> Vec<ContentBlock>
+where
+    I: IntoIterator<Item = Passage>,
+{
+    let mut grouped: Vec<(String, String, Vec<String>)> = Vec::new();
+    for passage in passages {
+        match grouped.iter_mut().find(|(source, ..)| *source == passage.source) {
+            Some((.., texts)) => texts.push(passage.text),
+            None => grouped.push((passage.source, passage.title, vec![passage.text])),
+        }
+    }
+    grouped.into_iter().map(|(source, title, texts)| ContentBlock::search_result(source, title, texts)).collect()
+}
+
+/// A reusable set of documents sharing one cached prompt prefix.
+///
+/// [`DocumentSet::blocks`] returns the documents with a `cache_control`
+/// breakpoint on the last one. Put them first in the first user message
+/// (as [`DocumentSet::user_message`] does) and every request built from the
+/// same set reads the documents from the prompt cache after the first one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentSet {
+    documents: Vec<ContentBlock>,
+    cache_control: CacheControl,
+}
+
+impl Default for DocumentSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DocumentSet {
+    /// Empty set using the default 5-minute ephemeral cache.
+    pub fn new() -> Self {
+        Self { documents: Vec::new(), cache_control: CacheControl::ephemeral() }
+    }
+
+    /// Append a document block.
+    pub fn document(mut self, document: ContentBlock) -> Self {
+        self.documents.push(document);
+        self
+    }
+
+    /// Append a document block in place.
+    pub fn push(&mut self, document: ContentBlock) {
+        self.documents.push(document);
+    }
+
+    /// Override the cache marker placed after the last document (e.g.
+    /// `CacheControl::ephemeral_ttl("1h")`).
+    pub fn cache_control(mut self, cache: CacheControl) -> Self {
+        self.cache_control = cache;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// The documents, with the cache breakpoint on the last one.
+    pub fn blocks(&self) -> Vec<ContentBlock> {
+        let mut blocks = self.documents.clone();
+        if let Some(last) = blocks.pop() {
+            blocks.push(last.with_cache_control(self.cache_control.clone()));
+        }
+        blocks
+    }
+
+    /// A user message containing the documents followed by `prompt`.
+    pub fn user_message(&self, prompt: impl Into<String>) -> Message {
+        let mut content = self.blocks();
+        content.push(ContentBlock::text(prompt));
+        Message::new(Role::User, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DocumentSource;
+
+    fn pdf_with_pages(pages: usize) -> Vec<u8> {
+        let mut pdf = b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n".to_vec();
+        pdf.extend_from_slice(format!("2 0 obj\n<< /Type /Pages /Count {pages} >>\nendobj\n").as_bytes());
+        for i in 0..pages {
+            pdf.extend_from_slice(format!("{} 0 obj\n<</Type/Page/Parent 2 0 R>>\nendobj\n", i + 3).as_bytes());
+        }
+        pdf.extend_from_slice(b"%%EOF\n");
+        pdf
+    }
+
+    #[test]
+    fn counts_pdf_pages() {
+        assert_eq!(pdf_page_count(&pdf_with_pages(3)), Some(3));
+        assert_eq!(pdf_page_count(b"%PDF-1.7\n<< /Type /ObjStm >>"), None);
+    }
+
+    #[test]
+    fn validates_pdfs() {
+        assert!(encode_pdf(&pdf_with_pages(2)).is_ok());
+        let err = encode_pdf(&pdf_with_pages(MAX_PDF_PAGES + 1)).unwrap_err();
+        assert!(matches!(err, AnthropicError::InvalidRequest(msg) if msg.contains("101 pages")));
+        let err = encode_pdf(b"PK\x03\x04").unwrap_err();
+        assert!(matches!(err, AnthropicError::InvalidRequest(msg) if msg.contains("not a PDF")));
+    }
+
+    #[test]
+    fn chunks_on_paragraph_boundaries() {
+        let text = "First paragraph.\n\nSecond one.\n\n\n\nThird paragraph is here.";
+        assert_eq!(chunk_text(text, 30), vec!["First paragraph.\n\nSecond one.", "Third paragraph is here."]);
+        assert_eq!(chunk_text(text, 1000), vec!["First paragraph.\n\nSecond one.\n\nThird paragraph is here."]);
+        assert!(chunk_text("  \n\n ", 10).is_empty());
+    }
+
+    #[test]
+    fn splits_long_paragraphs_at_whitespace() {
+        assert_eq!(chunk_text("alpha beta gamma delta", 11), vec!["alpha beta", "gamma delta"]);
+        assert_eq!(chunk_text("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(chunk_text("héllo wörld", 5), vec!["héllo", "wörld"]);
+    }
+
+    #[test]
+    fn search_results_group_passages_by_source_in_rank_order() {
+        let blocks = search_results([
+            Passage::new("https://a.example", "A", "best"),
+            Passage::new("https://b.example", "B", "second"),
+            Passage::new("https://a.example", "A (dup)", "third"),
+        ]);
+        assert_eq!(
+            blocks,
+            vec![
+                ContentBlock::search_result("https://a.example", "A", ["best", "third"]),
+                ContentBlock::search_result("https://b.example", "B", ["second"]),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&blocks[1]).unwrap(),
+            serde_json::json!({
+                "type": "search_result",
+                "source": "https://b.example",
+                "title": "B",
+                "content": [{"type": "text", "text": "second"}],
+                "citations": {"enabled": true}
+            })
+        );
+    }
+
+    #[test]
+    fn document_set_caches_the_last_document() {
+        let set = DocumentSet::new()
+            .document(ContentBlock::document_text("one").with_title("One"))
+            .document(ContentBlock::document_chunks(chunk_text("a\n\nb", 1)).with_citations(true));
+        let message = set.user_message("Compare them.");
+        assert_eq!(message.content.len(), 3);
+        match &message.content[0] {
+            ContentBlock::Document { title, cache_control, .. } => {
+                assert_eq!(title.as_deref(), Some("One"));
+                assert!(cache_control.is_none());
+            }
+            other => panic!("unexpected block {other:?}"),
+        }
+        match &message.content[1] {
+            ContentBlock::Document {
+                source: DocumentSource::Content { content }, citations, cache_control, ..
+            } => {
+                assert_eq!(content, &vec![ContentBlock::text("a"), ContentBlock::text("b")]);
+                assert!(citations.as_ref().unwrap().enabled);
+                assert_eq!(cache_control, &Some(CacheControl::ephemeral()));
+            }
+            other => panic!("unexpected block {other:?}"),
+        }
+        assert_eq!(message.content[2], ContentBlock::text("Compare them."));
+    }
+}
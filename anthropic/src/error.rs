@@ -34,6 +34,24 @@ pub enum AnthropicError {
     /// Unexpected response payload.
     #[error("unexpected response (status {status}): {body}")]
     UnexpectedResponse { status: u16, body: String },
+    /// MCP transport or protocol failure (see the `mcp` feature).
+    #[error("mcp error: {0}")]
+    Mcp(String),
+    /// JSON-RPC error returned by an MCP server.
+    #[error("mcp server error {code}: {message}")]
+    McpRpc { code: i64, message: String },
+    /// Structured output extraction failed: the model did not call the
+    /// extraction tool or its input never matched the target type.
+    #[error("extraction failed: {0}")]
+    Extraction(String),
+    /// The operation was stopped through a
+    /// [`CancellationToken`](crate::CancellationToken).
+    #[error("operation cancelled")]
+    Cancelled,
+    /// The tool loop deadline passed before the first model response
+    /// arrived, so there is no outcome to return.
+    #[error("tool loop deadline exceeded")]
+    DeadlineExceeded,
 }
 
 /// Anthropic API error payload.
@@ -0,0 +1,762 @@
+//! Ready-made executors for the Anthropic-defined text editor and bash tools.
+//!
+//! [`TextEditorExecutor`] applies [`TextEditorCommand`]s to files under a
+//! root directory and refuses any path that resolves outside of it.
+//! [`BashExecutor`] (Unix only) runs [`BashAction`]s in a persistent shell
+//! session with a per-command timeout and bounded output.
+//!
+//! Both report failures as [`ToolOutput::error`] so Claude sees them and can
+//! recover, and plug straight into
+//! [`run_tool_loop`](crate::tool_loop::run_tool_loop):
+//!
+//! ```no_run
+//! # async fn demo(client: &anthropic::Client, request: anthropic::types::MessagesRequest) -> Result<(), anthropic::AnthropicError> {
+//! use anthropic::executors::{BashExecutor, TextEditorExecutor};
+//! use anthropic::tool_loop::{run_tool_loop, ToolLoopConfig, ToolOutput};
+//!
+//! let editor = TextEditorExecutor::new("./workspace").expect("workspace exists");
+//! let bash = BashExecutor::new("./workspace");
+//! let outcome = run_tool_loop(
+//!     client,
+//!     request,
+//!     |name, input| {
+//!         let (editor, bash) = (&editor, &bash);
+//!         async move {
+//!             Ok(match name.as_str() {
+//!                 "str_replace_based_edit_tool" => editor.call(&input),
+//!                 "bash" => bash.call(&input).await,
+//!                 other => ToolOutput::error(format!("unknown tool {other}")),
+//!             })
+//!         }
+//!     },
+//!     ToolLoopConfig::default(),
+//! )
+//! .await?;
+//! # Ok(()) }
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+#[cfg(unix)]
+use crate::computer_use::BashAction;
+use crate::computer_use::TextEditorCommand;
+use crate::tool_loop::ToolOutput;
+
+/// Maximum directory depth listed by a `view` of a directory.
+const VIEW_DIR_DEPTH: usize = 2;
+
+/// Executes text editor commands against files under a root directory.
+///
+/// Relative paths are resolved against the root; absolute paths are accepted
+/// only if they point inside it. `..` components and symlinks that would
+/// escape the root are rejected. Every `create`, `str_replace`, and `insert`
+/// records the previous file contents so `undo_edit` can restore them.
+#[derive(Debug)]
+pub struct TextEditorExecutor {
+    root: PathBuf,
+    max_characters: Option<usize>,
+    history: Mutex<HashMap<PathBuf, Vec<Option<String>>>>,
+}
+
+impl TextEditorExecutor {
+    /// Confine the editor to `root`, which must be an existing directory.
+    pub fn new(root: impl AsRef<Path>) -> io::Result<Self> {
+        let root = fs::canonicalize(root)?;
+        if !root.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a directory", root.display())));
+        }
+        Ok(Self { root, max_characters: None, history: Mutex::new(HashMap::new()) })
+    }
+
+    /// Truncate `view` output after this many characters. Mirrors
+    /// [`TextEditorTool::max_characters`](crate::computer_use::TextEditorTool::max_characters).
+    pub fn max_characters(mut self, max_characters: usize) -> Self {
+        self.max_characters = Some(max_characters);
+        self
+    }
+
+    /// The canonical root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Parse a `tool_use` input and execute it.
+    pub fn call(&self, input: &serde_json::Value) -> ToolOutput {
+        match TextEditorCommand::from_input(input) {
+            Ok(command) => self.execute(command),
+            Err(err) => ToolOutput::error(format!("invalid text editor input: {err}")),
+        }
+    }
+
+    /// Execute a single command.
+    pub fn execute(&self, command: TextEditorCommand) -> ToolOutput {
+        let result = match command {
+            TextEditorCommand::View { path, view_range } => self.view(&path, view_range),
+            TextEditorCommand::Create { path, file_text } => self.create(&path, &file_text),
+            TextEditorCommand::StrReplace { path, old_str, new_str } => self.str_replace(&path, &old_str, &new_str),
+            TextEditorCommand::Insert { path, insert_line, new_str } => self.insert(&path, insert_line, &new_str),
+            TextEditorCommand::UndoEdit { path } => self.undo_edit(&path),
+        };
+        match result {
+            Ok(output) => ToolOutput::ok(output),
+            Err(message) => ToolOutput::error(message),
+        }
+    }
+
+    /// Map a model-supplied path to a location under the root.
+    fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let requested = Path::new(path);
+        let joined = if requested.is_absolute() { requested.to_path_buf() } else { self.root.join(requested) };
+
+        let mut normalized = PathBuf::new();
+        for component in joined.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !normalized.pop() {
+                        return Err(format!("{path} is outside the workspace"));
+                    }
+                }
+                other => normalized.push(other),
+            }
+        }
+        if !normalized.starts_with(&self.root) {
+            return Err(format!("{path} is outside the workspace"));
+        }
+
+        // Follow symlinks on the deepest entry that exists on disk so a link
+        // inside the root cannot point the editor somewhere else.
+        // `symlink_metadata` does not follow links, so a dangling link counts
+        // as existing rather than being skipped over.
+        let mut existing = normalized.as_path();
+        while fs::symlink_metadata(existing).is_err() {
+            existing = match existing.parent() {
+                Some(parent) => parent,
+                None => break,
+            };
+        }
+        let canonical = match fs::canonicalize(existing) {
+            Ok(canonical) => canonical,
+            // A dangling link: writing through it would create its target,
+            // which may be anywhere.
+            Err(_) if fs::symlink_metadata(existing).is_ok_and(|meta| meta.file_type().is_symlink()) => {
+                return Err(format!("{path} goes through a symlink whose target does not exist"));
+            }
+            Err(err) => return Err(format!("{path}: {err}")),
+        };
+        if !canonical.starts_with(&self.root) {
+            return Err(format!("{path} is outside the workspace"));
+        }
+        Ok(normalized)
+    }
+
+    fn read(&self, path: &str, resolved: &Path) -> Result<String, String> {
+        if !resolved.is_file() {
+            return Err(format!("{path} does not exist or is not a file"));
+        }
+        fs::read_to_string(resolved).map_err(|err| format!("failed to read {path}: {err}"))
+    }
+
+    fn write(&self, path: &str, resolved: &Path, previous: Option<String>, contents: &str) -> Result<(), String> {
+        if let Some(parent) = resolved.parent() {
+            fs::create_dir_all(parent).map_err(|err| format!("failed to create parent of {path}: {err}"))?;
+        }
+        fs::write(resolved, contents).map_err(|err| format!("failed to write {path}: {err}"))?;
+        self.history.lock().expect("history lock poisoned").entry(resolved.to_path_buf()).or_default().push(previous);
+        Ok(())
+    }
+
+    fn view(&self, path: &str, view_range: Option<[i64; 2]>) -> Result<String, String> {
+        let resolved = self.resolve(path)?;
+        if resolved.is_dir() {
+            if view_range.is_some() {
+                return Err("view_range is not allowed when viewing a directory".into());
+            }
+            let mut entries = Vec::new();
+            list_dir(&resolved, Path::new(""), VIEW_DIR_DEPTH, &mut entries)
+                .map_err(|err| format!("failed to list {path}: {err}"))?;
+            let listing = format!(
+                "Files and directories up to {VIEW_DIR_DEPTH} levels deep in {path}, excluding hidden items:\n{}",
+                entries.join("\n")
+            );
+            return Ok(self.truncate(listing));
+        }
+
+        let contents = self.read(path, &resolved)?;
+        let lines: Vec<&str> = contents.lines().collect();
+        let (start, end) = match view_range {
+            None => (1, lines.len()),
+            Some([start, end]) => {
+                let len = lines.len() as i64;
+                if start < 1 || start > len.max(1) {
+                    return Err(format!("invalid view_range: start {start} must be within 1..={len}"));
+                }
+                let end = if end == -1 { len } else { end };
+                if end < start || end > len {
+                    return Err(format!("invalid view_range: end {end} must be -1 or within {start}..={len}"));
+                }
+                (start as usize, end as usize)
+            }
+        };
+        let numbered: Vec<String> = lines
+            .iter()
+            .enumerate()
+            .skip(start - 1)
+            .take(end + 1 - start)
+            .map(|(i, line)| format!("{:>6}\t{line}", i + 1))
+            .collect();
+        Ok(self.truncate(numbered.join("\n")))
+    }
+
+    fn create(&self, path: &str, file_text: &str) -> Result<String, String> {
+        let resolved = self.resolve(path)?;
+        if resolved.is_dir() {
+            return Err(format!("{path} is a directory"));
+        }
+        let previous = if resolved.exists() { Some(self.read(path, &resolved)?) } else { None };
+        self.write(path, &resolved, previous, file_text)?;
+        Ok(format!("File created successfully at: {path}"))
+    }
+
+    fn str_replace(&self, path: &str, old_str: &str, new_str: &str) -> Result<String, String> {
+        let resolved = self.resolve(path)?;
+        let contents = self.read(path, &resolved)?;
+        if old_str.is_empty() {
+            return Err("old_str must not be empty".into());
+        }
+        match contents.matches(old_str).count() {
+            0 => Err(format!("No replacement was performed: old_str did not appear verbatim in {path}.")),
+            1 => {
+                let updated = contents.replacen(old_str, new_str, 1);
+                self.write(path, &resolved, Some(contents), &updated)?;
+                Ok(format!("The file {path} has been edited."))
+            }
+            n => Err(format!(
+                "No replacement was performed: old_str appears {n} times in {path}. Include more context to make it unique."
+            )),
+        }
+    }
+
+    fn insert(&self, path: &str, insert_line: u32, new_str: &str) -> Result<String, String> {
+        let resolved = self.resolve(path)?;
+        let contents = self.read(path, &resolved)?;
+        let mut lines: Vec<&str> = contents.lines().collect();
+        let at = insert_line as usize;
+        if at > lines.len() {
+            return Err(format!("invalid insert_line {insert_line}: {path} has {} lines", lines.len()));
+        }
+        lines.splice(at..at, new_str.lines());
+        let mut updated = lines.join("\n");
+        if contents.ends_with('\n') || contents.is_empty() {
+            updated.push('\n');
+        }
+        self.write(path, &resolved, Some(contents.clone()), &updated)?;
+        Ok(format!("The file {path} has been edited."))
+    }
+
+    fn undo_edit(&self, path: &str) -> Result<String, String> {
+        let resolved = self.resolve(path)?;
+        let previous = self
+            .history
+            .lock()
+            .expect("history lock poisoned")
+            .get_mut(&resolved)
+            .and_then(|edits| edits.pop())
+            .ok_or_else(|| format!("No edit history found for {path}."))?;
+        match previous {
+            Some(contents) => fs::write(&resolved, contents).map_err(|err| format!("failed to write {path}: {err}"))?,
+            None => fs::remove_file(&resolved).map_err(|err| format!("failed to remove {path}: {err}"))?,
+        }
+        Ok(format!("Last edit to {path} undone successfully."))
+    }
+
+    fn truncate(&self, mut output: String) -> String {
+        if let Some(max) = self.max_characters {
+            if let Some((cut, _)) = output.char_indices().nth(max) {
+                output.truncate(cut);
+                output.push_str("\n[output truncated]");
+            }
+        }
+        output
+    }
+}
+
+fn list_dir(dir: &Path, prefix: &Path, depth: usize, out: &mut Vec<String>) -> io::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let relative = prefix.join(&name);
+        if entry.file_type()?.is_dir() {
+            out.push(format!("{}/", relative.display()));
+            if depth > 1 {
+                list_dir(&entry.path(), &relative, depth - 1, out)?;
+            }
+        } else {
+            out.push(relative.display().to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+pub use bash::BashExecutor;
+
+#[cfg(unix)]
+mod bash {
+    use std::ffi::OsString;
+    use std::fmt;
+    use std::path::PathBuf;
+    use std::process::Stdio;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+    use tokio::sync::Mutex;
+
+    use super::BashAction;
+    use crate::tool_loop::ToolOutput;
+
+    static SESSION_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    /// Runs bash commands in a persistent shell session.
+    ///
+    /// Working directory, environment variables, and shell state carry over
+    /// between commands, as the bash tool expects. stderr is merged into
+    /// stdout. A command that exceeds the timeout kills the session, along
+    /// with every process it started; the next command starts a fresh one.
+    ///
+    /// The shell does not inherit the parent's environment, so secrets such
+    /// as `ANTHROPIC_API_KEY` stay out of reach. Only
+    /// [`BashExecutor::PASSED_ENV`] is passed through; add more with
+    /// [`BashExecutor::env`].
+    pub struct BashExecutor {
+        cwd: PathBuf,
+        timeout: Duration,
+        max_output_bytes: usize,
+        env: Vec<(OsString, OsString)>,
+        session: Mutex<Option<Session>>,
+    }
+
+    // Values set through `env` may be secrets; only their names are printed.
+    impl fmt::Debug for BashExecutor {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("BashExecutor")
+                .field("cwd", &self.cwd)
+                .field("timeout", &self.timeout)
+                .field("max_output_bytes", &self.max_output_bytes)
+                .field("env", &self.env.iter().map(|(key, _)| key).collect::<Vec<_>>())
+                .finish_non_exhaustive()
+        }
+    }
+
+    #[derive(Debug)]
+    struct Session {
+        child: Child,
+        stdin: ChildStdin,
+        stdout: BufReader<ChildStdout>,
+        sentinel: String,
+    }
+
+    impl Drop for Session {
+        /// The shell leads its own process group; kill the whole group so
+        /// background jobs and stuck children do not outlive the session.
+        fn drop(&mut self) {
+            if let Some(pid) = self.child.id() {
+                // SAFETY: `kill` has no memory-safety preconditions. The
+                // group id is the shell's pid, set by `process_group(0)`.
+                unsafe {
+                    libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+                }
+            }
+        }
+    }
+
+    impl BashExecutor {
+        /// Variables copied from the parent environment into every shell.
+        pub const PASSED_ENV: &'static [&'static str] = &["PATH", "HOME", "LANG"];
+
+        /// Shell sessions start in `cwd`. Defaults to a 120 second timeout
+        /// and 30 KB of output per command.
+        pub fn new(cwd: impl Into<PathBuf>) -> Self {
+            Self {
+                cwd: cwd.into(),
+                timeout: Duration::from_secs(120),
+                max_output_bytes: 30_000,
+                env: Vec::new(),
+                session: Mutex::new(None),
+            }
+        }
+
+        /// Set an environment variable for the shell, on top of
+        /// [`Self::PASSED_ENV`]. Takes effect for sessions started afterwards.
+        pub fn env(mut self, key: impl Into<OsString>, value: impl Into<OsString>) -> Self {
+            self.env.push((key.into(), value.into()));
+            self
+        }
+
+        /// Per-command timeout.
+        pub fn timeout(mut self, timeout: Duration) -> Self {
+            self.timeout = timeout;
+            self
+        }
+
+        /// Keep at most this many bytes of output per command.
+        pub fn max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+            self.max_output_bytes = max_output_bytes;
+            self
+        }
+
+        /// Parse a `tool_use` input and execute it.
+        pub async fn call(&self, input: &serde_json::Value) -> ToolOutput {
+            match BashAction::from_input(input) {
+                Ok(action) => self.execute(action).await,
+                Err(err) => ToolOutput::error(format!("invalid bash input: {err}")),
+            }
+        }
+
+        /// Execute a single action.
+        pub async fn execute(&self, action: BashAction) -> ToolOutput {
+            let mut guard = self.session.lock().await;
+            let command = match action {
+                BashAction::Restart => {
+                    *guard = None;
+                    return match self.spawn() {
+                        Ok(session) => {
+                            *guard = Some(session);
+                            ToolOutput::ok("tool has been restarted.")
+                        }
+                        Err(err) => ToolOutput::error(format!("failed to start bash: {err}")),
+                    };
+                }
+                BashAction::Command { command } => command,
+            };
+
+            if guard.is_none() {
+                match self.spawn() {
+                    Ok(session) => *guard = Some(session),
+                    Err(err) => return ToolOutput::error(format!("failed to start bash: {err}")),
+                }
+            }
+            let session = guard.as_mut().expect("session was just started");
+
+            match tokio::time::timeout(self.timeout, self.run(session, &command)).await {
+                Ok(Ok((output, status))) => {
+                    let mut content = if output.is_empty() { "(no output)".to_string() } else { output };
+                    if status != 0 {
+                        content.push_str(&format!("\n[exit code: {status}]"));
+                    }
+                    ToolOutput::ok(content)
+                }
+                Ok(Err(err)) => {
+                    *guard = None;
+                    ToolOutput::error(format!("bash session failed: {err}; the shell will be restarted"))
+                }
+                Err(_) => {
+                    *guard = None;
+                    ToolOutput::error(format!(
+                        "command timed out after {}s; the shell will be restarted",
+                        self.timeout.as_secs_f64()
+                    ))
+                }
+            }
+        }
+
+        fn spawn(&self) -> std::io::Result<Session> {
+            let mut command = Command::new("bash");
+            command.args(["--noprofile", "--norc"]).current_dir(&self.cwd).env_clear();
+            for key in Self::PASSED_ENV {
+                if let Some(value) = std::env::var_os(key) {
+                    command.env(key, value);
+                }
+            }
+            let mut child = command
+                .envs(self.env.iter().map(|(key, value)| (key, value)))
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .process_group(0)
+                .kill_on_drop(true)
+                .spawn()?;
+            let stdin = child.stdin.take().expect("stdin is piped");
+            let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+            let sentinel = format!(
+                "__ANTHROPIC_BASH_DONE_{}_{}_{nanos}__",
+                std::process::id(),
+                SESSION_COUNTER.fetch_add(1, Ordering::Relaxed)
+            );
+            Ok(Session { child, stdin, stdout, sentinel })
+        }
+
+        /// Run one command and read its output up to the sentinel line,
+        /// returning the (possibly truncated) output and the exit status.
+        async fn run(&self, session: &mut Session, command: &str) -> std::io::Result<(String, i32)> {
+            let script = format!("{{\n{command}\n}} 2>&1 </dev/null; echo \"{}$?\"\n", session.sentinel);
+            session.stdin.write_all(script.as_bytes()).await?;
+            session.stdin.flush().await?;
+
+            // Output is read in chunks and only the current line is buffered.
+            // Once a line outgrows `keep` bytes, everything but its tail is
+            // handed to `append`, which keeps at most `max_output_bytes` and
+            // counts the rest, so memory stays bounded however much a command
+            // prints. The tail is enough to spot the sentinel and exit status.
+            let sentinel = session.sentinel.as_bytes();
+            let keep = sentinel.len() + 16;
+            let mut output = Vec::new();
+            let mut omitted = 0usize;
+            let mut line = Vec::new();
+            loop {
+                let buf = session.stdout.fill_buf().await?;
+                if buf.is_empty() {
+                    let status = session.child.wait().await?;
+                    return Err(std::io::Error::other(format!("shell exited ({status})")));
+                }
+                let (len, line_done) = match buf.iter().position(|&byte| byte == b'\n') {
+                    Some(newline) => (newline + 1, true),
+                    None => (buf.len(), false),
+                };
+                line.extend_from_slice(&buf[..len]);
+                session.stdout.consume(len);
+
+                if !line_done {
+                    if line.len() > keep {
+                        let flushed = line.len() - keep;
+                        self.append(&mut output, &mut omitted, &line[..flushed]);
+                        line.drain(..flushed);
+                    }
+                    continue;
+                }
+                if let Some(pos) = line.windows(sentinel.len()).position(|window| window == sentinel) {
+                    let status = String::from_utf8_lossy(&line[pos + sentinel.len()..]).trim().parse().unwrap_or(-1);
+                    self.append(&mut output, &mut omitted, &line[..pos]);
+                    let mut output = String::from_utf8_lossy(&output).into_owned();
+                    while output.ends_with('\n') {
+                        output.pop();
+                    }
+                    if omitted > 0 {
+                        output.push_str(&format!("\n[output truncated: {omitted} bytes omitted]"));
+                    }
+                    return Ok((output, status));
+                }
+                self.append(&mut output, &mut omitted, &line);
+                line.clear();
+            }
+        }
+
+        fn append(&self, output: &mut Vec<u8>, omitted: &mut usize, bytes: &[u8]) {
+            let room = self.max_output_bytes.saturating_sub(output.len());
+            let take = room.min(bytes.len());
+            output.extend_from_slice(&bytes[..take]);
+            *omitted += bytes.len() - take;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn editor() -> (tempfile::TempDir, TextEditorExecutor) {
+        let dir = tempfile::tempdir().unwrap();
+        let editor = TextEditorExecutor::new(dir.path()).unwrap();
+        (dir, editor)
+    }
+
+    fn ok(output: ToolOutput) -> String {
+        assert!(!output.is_error, "unexpected tool error: {}", output.text());
+        output.text()
+    }
+
+    fn err(output: ToolOutput) -> String {
+        assert!(output.is_error, "expected a tool error, got: {}", output.text());
+        output.text()
+    }
+
+    #[test]
+    fn create_view_and_undo() {
+        let (dir, editor) = editor();
+        ok(editor.call(&json!({"command": "create", "path": "notes/a.txt", "file_text": "one\ntwo\nthree\n"})));
+        assert_eq!(fs::read_to_string(dir.path().join("notes/a.txt")).unwrap(), "one\ntwo\nthree\n");
+
+        let view = ok(editor.call(&json!({"command": "view", "path": "notes/a.txt", "view_range": [2, -1]})));
+        assert_eq!(view, "     2\ttwo\n     3\tthree");
+
+        let listing = ok(editor.call(&json!({"command": "view", "path": "."})));
+        assert!(listing.ends_with("notes/\nnotes/a.txt"), "{listing}");
+
+        ok(editor.call(&json!({"command": "create", "path": "notes/a.txt", "file_text": "replaced"})));
+        ok(editor.call(&json!({"command": "undo_edit", "path": "notes/a.txt"})));
+        assert_eq!(fs::read_to_string(dir.path().join("notes/a.txt")).unwrap(), "one\ntwo\nthree\n");
+        ok(editor.call(&json!({"command": "undo_edit", "path": "notes/a.txt"})));
+        assert!(!dir.path().join("notes/a.txt").exists());
+        err(editor.call(&json!({"command": "undo_edit", "path": "notes/a.txt"})));
+    }
+
+    #[test]
+    fn str_replace_requires_a_unique_match() {
+        let (dir, editor) = editor();
+        fs::write(dir.path().join("main.rs"), "fn a() {}\nfn b() {}\nfn a() {}\n").unwrap();
+
+        let message = err(editor.call(&json!({"command": "str_replace", "path": "main.rs", "old_str": "fn a() {}"})));
+        assert!(message.contains("2 times"), "{message}");
+        err(editor.call(&json!({"command": "str_replace", "path": "main.rs", "old_str": "fn c() {}"})));
+
+        ok(editor.call(&json!({
+            "command": "str_replace",
+            "path": "main.rs",
+            "old_str": "fn b() {}",
+            "new_str": "fn b() { todo!() }"
+        })));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("main.rs")).unwrap(),
+            "fn a() {}\nfn b() { todo!() }\nfn a() {}\n"
+        );
+    }
+
+    #[test]
+    fn insert_after_line() {
+        let (dir, editor) = editor();
+        fs::write(dir.path().join("list.txt"), "a\nc\n").unwrap();
+        ok(editor.call(&json!({"command": "insert", "path": "list.txt", "insert_line": 1, "new_str": "b"})));
+        ok(editor.call(&json!({"command": "insert", "path": "list.txt", "insert_line": 0, "new_str": "start"})));
+        assert_eq!(fs::read_to_string(dir.path().join("list.txt")).unwrap(), "start\na\nb\nc\n");
+        err(editor.call(&json!({"command": "insert", "path": "list.txt", "insert_line": 9, "new_str": "x"})));
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_root() {
+        let (dir, editor) = editor();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+
+        err(editor.call(&json!({"command": "view", "path": "../secret.txt"})));
+        err(editor.call(&json!({"command": "create", "path": "a/../../escape.txt", "file_text": "x"})));
+        err(editor.call(&json!({"command": "view", "path": outside.path().join("secret.txt")})));
+
+        // An absolute path inside the root is fine.
+        fs::write(dir.path().join("inside.txt"), "hi").unwrap();
+        ok(editor.call(&json!({"command": "view", "path": editor.root().join("inside.txt")})));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+            err(editor.call(&json!({"command": "view", "path": "link/secret.txt"})));
+            err(editor.call(&json!({"command": "create", "path": "link/new.txt", "file_text": "x"})));
+            assert!(!outside.path().join("new.txt").exists());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_dangling_symlinks() {
+        let (dir, editor) = editor();
+        let outside = tempfile::tempdir().unwrap();
+        let target = outside.path().join("new.txt");
+        std::os::unix::fs::symlink(&target, dir.path().join("link")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("missing"), dir.path().join("dir_link")).unwrap();
+
+        err(editor.call(&json!({"command": "create", "path": "link", "file_text": "x"})));
+        err(editor.call(&json!({"command": "create", "path": "dir_link/new.txt", "file_text": "x"})));
+        assert!(!target.exists());
+        assert!(!outside.path().join("missing").exists());
+    }
+
+    #[test]
+    fn view_truncates_to_max_characters() {
+        let (dir, editor) = editor();
+        let editor = editor.max_characters(10);
+        fs::write(dir.path().join("long.txt"), "abcdefghijklmnopqrstuvwxyz").unwrap();
+        let view = ok(editor.call(&json!({"command": "view", "path": "long.txt"})));
+        assert_eq!(view, "     1\tabc\n[output truncated]");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn bash_session_persists_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let bash = BashExecutor::new(dir.path());
+
+        assert_eq!(ok(bash.call(&json!({"command": "export GREETING=hi; mkdir sub && cd sub"})).await), "(no output)");
+        assert_eq!(ok(bash.call(&json!({"command": "echo $GREETING from $(basename $PWD)"})).await), "hi from sub");
+        assert_eq!(ok(bash.call(&json!({"command": "printf 'no newline'"})).await), "no newline");
+        assert_eq!(
+            ok(bash.call(&json!({"command": "echo oops >&2; exit_code() { return 3; }; exit_code"})).await),
+            "oops\n[exit code: 3]"
+        );
+
+        ok(bash.call(&json!({"restart": true})).await);
+        assert_eq!(ok(bash.call(&json!({"command": "echo ${GREETING:-unset}"})).await), "unset");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn bash_truncates_output_and_times_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let bash = BashExecutor::new(dir.path()).max_output_bytes(8).timeout(std::time::Duration::from_millis(300));
+
+        let output = ok(bash.call(&json!({"command": "seq 1 100"})).await);
+        assert!(output.starts_with("1\n2\n3\n4\n"), "{output}");
+        assert!(output.contains("bytes omitted"), "{output}");
+
+        let message = err(bash.call(&json!({"command": "sleep 5"})).await);
+        assert!(message.contains("timed out"), "{message}");
+        // The session is restarted transparently after a timeout.
+        assert_eq!(ok(bash.call(&json!({"command": "echo back"})).await), "back");
+
+        // A single huge line is truncated without being buffered whole.
+        let output = ok(bash.call(&json!({"command": "head -c 1000000 /dev/zero | tr '\\0' a"})).await);
+        assert_eq!(output, "aaaaaaaa\n[output truncated: 999992 bytes omitted]");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn bash_does_not_inherit_the_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let bash = BashExecutor::new(dir.path()).env("EXTRA_SETTING", "on");
+
+        assert_eq!(ok(bash.call(&json!({"command": "echo $EXTRA_SETTING"})).await), "on");
+        let names = ok(bash.call(&json!({"command": "compgen -e"})).await);
+        // Bash exports these itself.
+        let own = ["PWD", "OLDPWD", "SHLVL", "_"];
+        for (name, _) in std::env::vars_os() {
+            let name = name.to_string_lossy();
+            if !BashExecutor::PASSED_ENV.contains(&name.as_ref()) && !own.contains(&name.as_ref()) {
+                assert!(!names.lines().any(|line| line == name), "{name} leaked into the shell");
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn bash_timeout_kills_background_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let bash = BashExecutor::new(dir.path()).timeout(std::time::Duration::from_millis(300));
+
+        let pid = ok(bash.call(&json!({"command": "sleep 1000 >/dev/null & echo $!"})).await);
+        err(bash.call(&json!({"command": "sleep 5"})).await);
+
+        // Killed processes may linger as zombies until reaped; either way
+        // they are no longer running.
+        let running = || {
+            fs::read_to_string(format!("/proc/{pid}/stat"))
+                .is_ok_and(|stat| stat.rsplit(')').next().is_some_and(|rest| !rest.trim_start().starts_with('Z')))
+        };
+        for _ in 0..50 {
+            if !running() {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("background job {pid} survived the timeout");
+    }
+}
@@ -0,0 +1,345 @@
+//! Structured output extraction (`schemars` feature).
+//!
+//! [`Client::extract`] turns any `T: DeserializeOwned + JsonSchema` into a
+//! single forced tool: the request gets a tool whose `input_schema` is
+//! generated from `T`, `tool_choice` is pinned to it, and the model's
+//! `tool_use` input is deserialized into `T`. When the input does not match,
+//! [`ExtractConfig::max_retries`] controls how many times the model is
+//! re-asked with the error as a `tool_result` error. With the `jsonschema`
+//! feature the input is also validated against the schema, so constraints
+//! serde does not enforce (`minimum`, `pattern`, ...) are re-asked too.
+//!
+//! ```no_run
+//! use anthropic::types::{Message, MessagesRequestBuilder};
+//! use schemars::JsonSchema;
+//! use serde::Deserialize;
+//!
+//! /// Contact details found in the text.
+//! #[derive(Deserialize, JsonSchema)]
+//! struct Contact {
+//!     name: String,
+//!     email: Option<String>,
+//! }
+//!
+//! # async fn run(client: anthropic::Client) -> Result<(), anthropic::AnthropicError> {
+//! let request = MessagesRequestBuilder::new(
+//!     "claude-3-5-sonnet-20240620",
+//!     vec![Message::user("Reach Ada Lovelace at ada@example.com.")],
+//!     512,
+//! )
+//! .build()?;
+//! let contact: Contact = client.extract(request).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+
+use crate::client::Client;
+use crate::error::AnthropicError;
+use crate::typed_tool::input_schema;
+use crate::types::{ContentBlock, Message, MessagesRequest, MessagesResponse, Role, Tool, ToolChoice, ToolDefinition};
+
+/// Default name of the synthesized extraction tool.
+pub const DEFAULT_TOOL_NAME: &str = "structured_output";
+
+const DEFAULT_DESCRIPTION: &str = "Record the requested information. Always respond by calling this tool.";
+
+/// Options for [`Client::extract_with`] and [`Client::extract_streaming`].
+#[derive(Debug, Clone)]
+pub struct ExtractConfig {
+    /// Name of the synthesized tool. Must not clash with other tools on the
+    /// request.
+    pub tool_name: String,
+    /// Tool description. The schema's own description (the doc comment on
+    /// `T`) is appended when present.
+    pub description: String,
+    /// How many times to re-ask the model when its output does not
+    /// deserialize into `T` (or, with the `jsonschema` feature, violates
+    /// `T`'s schema). `0` (the default) fails on the first mismatch.
+    pub max_retries: u32,
+}
+
+impl Default for ExtractConfig {
+    fn default() -> Self {
+        Self { tool_name: DEFAULT_TOOL_NAME.into(), description: DEFAULT_DESCRIPTION.into(), max_retries: 0 }
+    }
+}
+
+impl ExtractConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tool_name(mut self, tool_name: impl Into<String>) -> Self {
+        self.tool_name = tool_name.into();
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+}
+
+/// The extraction tool for `T`: its schema plus the configured name and
+/// description.
+pub fn extraction_tool<T: JsonSchema>(config: &ExtractConfig) -> Tool {
+    let schema = input_schema::<T>();
+    let description = match schema.get("description").and_then(|d| d.as_str()) {
+        Some(doc) if !doc.is_empty() => format!("{}\n\n{doc}", config.description),
+        _ => config.description.clone(),
+    };
+    Tool::new(config.tool_name.clone(), description, schema)
+}
+
+impl Client {
+    /// Extract a `T` from the model's answer to `request` using
+    /// [`ExtractConfig::default`]. See the [`extract`](crate::extract) module.
+    pub async fn extract<T>(&self, request: MessagesRequest) -> Result<T, AnthropicError>
+    where
+        T: DeserializeOwned + JsonSchema,
+    {
+        self.extract_with(request, ExtractConfig::default()).await
+    }
+
+    /// Like [`Client::extract`] with explicit options.
+    pub async fn extract_with<T>(&self, request: MessagesRequest, config: ExtractConfig) -> Result<T, AnthropicError>
+    where
+        T: DeserializeOwned + JsonSchema,
+    {
+        run_extraction(request, &config, |request| self.messages(request)).await
+    }
+
+    /// Like [`Client::extract_with`] but sends each attempt through
+    /// [`Client::messages_stream`] and folds the events into the final
+    /// message, which avoids idle-connection timeouts on large outputs.
+    pub async fn extract_streaming<T>(
+        &self,
+        request: MessagesRequest,
+        config: ExtractConfig,
+    ) -> Result<T, AnthropicError>
+    where
+        T: DeserializeOwned + JsonSchema,
+    {
+        run_extraction(request, &config, |request| async move {
+            crate::stream::collect(self.messages_stream(request).await?).await
+        })
+        .await
+    }
+}
+
+async fn run_extraction<T, F, Fut>(
+    mut request: MessagesRequest,
+    config: &ExtractConfig,
+    mut send: F,
+) -> Result<T, AnthropicError>
+where
+    T: DeserializeOwned + JsonSchema,
+    F: FnMut(MessagesRequest) -> Fut,
+    Fut: Future<Output = Result<MessagesResponse, AnthropicError>>,
+{
+    let tools = request.tools.get_or_insert_with(Vec::new);
+    if tools.iter().any(|tool| tool.name() == config.tool_name) {
+        return Err(AnthropicError::InvalidRequest(format!(
+            "request already has a tool named `{}`; choose another ExtractConfig::tool_name",
+            config.tool_name
+        )));
+    }
+    tools.push(ToolDefinition::from(extraction_tool::<T>(config)));
+    request.tool_choice = Some(ToolChoice::tool(config.tool_name.clone()));
+
+    #[cfg(feature = "jsonschema")]
+    let validators = crate::tool_loop::InputValidators::new(&request);
+
+    let mut attempt = 0;
+    loop {
+        let response = send(request.clone()).await?;
+        let Some((id, input)) = response
+            .tool_uses()
+            .find(|(_, name, _)| *name == config.tool_name)
+            .map(|(id, _, input)| (id.to_string(), input.clone()))
+        else {
+            return Err(AnthropicError::Extraction(format!(
+                "model did not call `{}` (stop reason: {})",
+                config.tool_name,
+                response.stop_reason.map_or_else(|| "none".to_string(), |reason| format!("{reason:?}"))
+            )));
+        };
+
+        // Schema constraints serde does not enforce (`minimum`, `pattern`,
+        // ...) are only checked once the input deserializes.
+        #[cfg(feature = "jsonschema")]
+        let violations = validators.check(&config.tool_name, &input);
+        #[cfg(not(feature = "jsonschema"))]
+        let violations: Option<String> = None;
+        let problem = match serde_json::from_value::<T>(input) {
+            Ok(value) => match violations {
+                None => return Ok(value),
+                Some(violations) => violations,
+            },
+            Err(err) => err.to_string(),
+        };
+        if attempt == config.max_retries {
+            return Err(AnthropicError::Extraction(format!(
+                "output did not match the schema after {} attempt(s): {problem}",
+                attempt + 1
+            )));
+        }
+        attempt += 1;
+
+        // Every `tool_use` in the turn needs a `tool_result`, including calls
+        // to the request's other tools, which are not run here.
+        let results = response
+            .tool_uses()
+            .map(|(tool_id, name, _)| {
+                let message = if tool_id == id {
+                    format!("{problem}. Call `{}` again with input that matches its schema.", config.tool_name)
+                } else {
+                    format!("`{name}` was not run. Call `{}` to answer.", config.tool_name)
+                };
+                ContentBlock::tool_result_error(tool_id, message)
+            })
+            .collect();
+        request.messages.push(Message::new(Role::Assistant, response.content));
+        request.messages.push(Message::new(Role::User, results));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    /// A person.
+    #[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+    struct Person {
+        name: String,
+        age: u32,
+    }
+
+    fn request() -> MessagesRequest {
+        crate::types::MessagesRequestBuilder::new("claude-3-5-sonnet-20240620", vec![Message::user("Ada, 36")], 256)
+            .build()
+            .unwrap()
+    }
+
+    fn response(input: serde_json::Value) -> MessagesResponse {
+        serde_json::from_value(json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "tool_use", "id": "tu_1", "name": DEFAULT_TOOL_NAME, "input": input}],
+            "model": "claude-3-5-sonnet-20240620",
+            "stop_reason": "tool_use",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 1, "output_tokens": 1}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn tool_description_includes_schema_doc() {
+        let tool = extraction_tool::<Person>(&ExtractConfig::new().tool_name("person"));
+        assert_eq!(tool.name, "person");
+        assert!(tool.description.ends_with("\n\nA person."), "{}", tool.description);
+        assert_eq!(tool.input_schema["required"], json!(["name", "age"]));
+    }
+
+    #[tokio::test]
+    async fn retries_with_the_deserialization_error() {
+        let mut sent = Vec::new();
+        let mut replies = vec![response(json!({"name": "Ada", "age": 36})), response(json!({"name": "Ada"}))];
+        let person: Person = run_extraction(request(), &ExtractConfig::new().max_retries(1), |request| {
+            sent.push(request);
+            let reply = replies.pop().unwrap();
+            async move { Ok(reply) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(person, Person { name: "Ada".into(), age: 36 });
+
+        assert_eq!(sent[0].tool_choice, Some(ToolChoice::tool(DEFAULT_TOOL_NAME)));
+        assert_eq!(sent[1].messages.len(), 3);
+        let retry = serde_json::to_value(&sent[1].messages[2]).unwrap();
+        assert_eq!(retry["content"][0]["is_error"], true);
+        assert!(retry["content"][0]["content"].as_str().unwrap().contains("missing field `age`"));
+    }
+
+    #[tokio::test]
+    async fn answers_every_tool_use_when_retrying() {
+        let mut first = response(json!({"name": "Ada"}));
+        first.content.insert(
+            0,
+            serde_json::from_value(json!({"type": "tool_use", "id": "tu_0", "name": "lookup", "input": {}})).unwrap(),
+        );
+        let mut sent = Vec::new();
+        let mut replies = vec![response(json!({"name": "Ada", "age": 36})), first];
+        let person: Person = run_extraction(request(), &ExtractConfig::new().max_retries(1), |request| {
+            sent.push(request);
+            let reply = replies.pop().unwrap();
+            async move { Ok(reply) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(person.age, 36);
+
+        let retry = serde_json::to_value(&sent[1].messages[2]).unwrap();
+        assert_eq!(retry["content"][0]["tool_use_id"], "tu_0");
+        assert_eq!(retry["content"][0]["is_error"], true);
+        assert_eq!(retry["content"][1]["tool_use_id"], "tu_1");
+    }
+
+    #[cfg(feature = "jsonschema")]
+    #[tokio::test]
+    async fn retries_on_schema_constraints_serde_does_not_check() {
+        #[derive(Debug, Deserialize, JsonSchema)]
+        struct Rating {
+            #[schemars(range(min = 1, max = 5))]
+            stars: u8,
+        }
+
+        let mut sent = Vec::new();
+        let mut replies = vec![response(json!({"stars": 4})), response(json!({"stars": 9}))];
+        let rating: Rating = run_extraction(request(), &ExtractConfig::new().max_retries(1), |request| {
+            sent.push(request);
+            let reply = replies.pop().unwrap();
+            async move { Ok(reply) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(rating.stars, 4);
+
+        let retry = serde_json::to_value(&sent[1].messages[2]).unwrap();
+        let message = retry["content"][0]["content"].as_str().unwrap();
+        assert!(message.contains("at `/stars`"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn fails_without_retries_and_on_name_clashes() {
+        let err = run_extraction::<Person, _, _>(request(), &ExtractConfig::new(), |_| async {
+            Ok(response(json!({"name": "Ada"})))
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AnthropicError::Extraction(ref msg) if msg.contains("after 1 attempt(s)")), "{err}");
+
+        let mut clashing = request();
+        clashing.tools = Some(vec![Tool::new(DEFAULT_TOOL_NAME, "mine", json!({"type": "object"})).into()]);
+        let err = run_extraction::<Person, _, _>(clashing, &ExtractConfig::new(), |_| async {
+            unreachable!("no request is sent")
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AnthropicError::InvalidRequest(_)));
+    }
+}
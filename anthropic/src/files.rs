@@ -0,0 +1,157 @@
+//! Types for the `/v1/files` endpoints (Files API).
+//!
+//! Upload a file once with [`Client::upload_file`](crate::Client::upload_file)
+//! and reference it from any number of requests with
+//! [`ContentBlock::document_file`](crate::types::ContentBlock::document_file)
+//! or [`ContentBlock::image_file`](crate::types::ContentBlock::image_file)
+//! instead of re-sending base64 data. The `files-api-2025-04-14` beta is added
+//! automatically, both on the files endpoints and on requests that use file
+//! sources.
+
+use std::path::Path;
+use std::pin::Pin;
+
+use serde::{Deserialize, Serialize};
+use tokio_stream::Stream;
+
+use crate::error::AnthropicError;
+
+/// Beta flag required by the Files API and by requests that reference files.
+pub const FILES_API_BETA: &str = "files-api-2025-04-14";
+
+/// Metadata of an uploaded file.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FileMetadata {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub file_type: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub size_bytes: u64,
+    pub created_at: String,
+    /// Whether the file can be downloaded. Only files produced by tools (for
+    /// example code execution outputs) are downloadable.
+    #[serde(default)]
+    pub downloadable: bool,
+}
+
+/// Paginated list response returned by `GET /v1/files`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FileList {
+    pub data: Vec<FileMetadata>,
+    #[serde(default)]
+    pub has_more: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_id: Option<String>,
+}
+
+/// Response returned by `DELETE /v1/files/{file_id}`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FileDeleted {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub deleted_type: String,
+}
+
+/// Query parameters for paginating through uploaded files.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ListFilesParams {
+    pub before_id: Option<String>,
+    pub after_id: Option<String>,
+    pub limit: Option<u32>,
+}
+
+impl ListFilesParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn before_id(mut self, before_id: impl Into<String>) -> Self {
+        self.before_id = Some(before_id.into());
+        self
+    }
+
+    pub fn after_id(mut self, after_id: impl Into<String>) -> Self {
+        self.after_id = Some(after_id.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub(crate) fn as_query(&self) -> Vec<(&'static str, String)> {
+        let mut out = Vec::new();
+        if let Some(before) = &self.before_id {
+            out.push(("before_id", before.clone()));
+        }
+        if let Some(after) = &self.after_id {
+            out.push(("after_id", after.clone()));
+        }
+        if let Some(limit) = self.limit {
+            out.push(("limit", limit.to_string()));
+        }
+        out
+    }
+}
+
+/// Byte stream returned by [`Client::download_file`](crate::Client::download_file).
+pub type FileDownloadStream = Pin<Box<dyn Stream<Item = Result<bytes::Bytes, AnthropicError>> + Send>>;
+
+/// Best-effort MIME type for an upload, based on the file extension.
+pub(crate) fn guess_mime_type(path: &Path) -> &'static str {
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("pdf") => "application/pdf",
+        Some("txt") => "text/plain",
+        Some("md") => "text/markdown",
+        Some("csv") => "text/csv",
+        Some("html" | "htm") => "text/html",
+        Some("json") => "application/json",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn file_metadata_deserializes() {
+        let file: FileMetadata = serde_json::from_value(json!({
+            "id": "file_011",
+            "type": "file",
+            "filename": "report.pdf",
+            "mime_type": "application/pdf",
+            "size_bytes": 1024,
+            "created_at": "2025-04-14T00:00:00Z"
+        }))
+        .unwrap();
+        assert_eq!(file.id, "file_011");
+        assert_eq!(file.size_bytes, 1024);
+        assert!(!file.downloadable);
+    }
+
+    #[test]
+    fn list_files_params_builds_query() {
+        let query = ListFilesParams::new().before_id("file_9").limit(20).as_query();
+        assert_eq!(query, vec![("before_id", "file_9".to_string()), ("limit", "20".to_string())]);
+        assert!(ListFilesParams::new().as_query().is_empty());
+    }
+
+    #[test]
+    fn guesses_mime_type_from_extension() {
+        assert_eq!(guess_mime_type(Path::new("docs/Report.PDF")), "application/pdf");
+        assert_eq!(guess_mime_type(Path::new("photo.jpeg")), "image/jpeg");
+        assert_eq!(guess_mime_type(Path::new("archive.tar.gz")), "application/octet-stream");
+        assert_eq!(guess_mime_type(Path::new("README")), "application/octet-stream");
+    }
+}
@@ -0,0 +1,316 @@
+//! Helpers for building image content blocks from raw bytes.
+//!
+//! [`ContentBlock::image_from_bytes`](crate::types::ContentBlock::image_from_bytes)
+//! and [`ContentBlock::image_from_path`](crate::types::ContentBlock::image_from_path)
+//! detect the media type from the file's magic bytes, check the API's size
+//! and dimension limits locally, and base64-encode the data. With the
+//! optional `image` Cargo feature, images whose long edge exceeds
+//! [`RECOMMENDED_LONG_EDGE`] (or whose size exceeds [`MAX_IMAGE_BYTES`]) are
+//! downscaled before encoding instead of being sent as-is or rejected.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::error::AnthropicError;
+
+/// Largest image, in bytes, accepted by the API.
+pub const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Largest width or height, in pixels, accepted by the API.
+pub const MAX_IMAGE_DIMENSION: u32 = 8000;
+
+/// Long edge, in pixels, above which the API downscales images anyway.
+/// Sending larger images only adds latency.
+pub const RECOMMENDED_LONG_EDGE: u32 = 1568;
+
+/// Image formats supported by the API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImageMediaType {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+}
+
+impl ImageMediaType {
+    /// Detect the format from the leading magic bytes.
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else {
+            None
+        }
+    }
+
+    /// The MIME type sent as the image source's `media_type`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+        }
+    }
+}
+
+/// Read the `(width, height)` of a JPEG, PNG, GIF, or WebP image from its
+/// header, without decoding the pixels. Returns `None` for unknown or
+/// truncated data.
+pub fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    match ImageMediaType::sniff(bytes)? {
+        ImageMediaType::Png => Some((be_u32(bytes, 16)?, be_u32(bytes, 20)?)),
+        ImageMediaType::Gif => Some((le_u16(bytes, 6)? as u32, le_u16(bytes, 8)? as u32)),
+        ImageMediaType::Webp => webp_dimensions(bytes),
+        ImageMediaType::Jpeg => jpeg_dimensions(bytes),
+    }
+}
+
+/// Validate `bytes` against the API limits (downscaling first when the
+/// `image` feature is enabled) and return the media type and base64 data.
+pub(crate) fn encode_image(bytes: &[u8]) -> Result<(ImageMediaType, String), AnthropicError> {
+    let media_type = ImageMediaType::sniff(bytes).ok_or_else(|| {
+        AnthropicError::InvalidRequest("unsupported image format (expected JPEG, PNG, GIF, or WebP)".into())
+    })?;
+    let (width, height) = image_dimensions(bytes)
+        .ok_or_else(|| AnthropicError::InvalidRequest(format!("could not read {} dimensions", media_type.as_str())))?;
+
+    // Checked before decoding so a crafted header can't make `downscale`
+    // allocate a huge pixel buffer.
+    if width > MAX_IMAGE_DIMENSION || height > MAX_IMAGE_DIMENSION {
+        return Err(AnthropicError::InvalidRequest(format!(
+            "image is {width}x{height}px; the maximum is {MAX_IMAGE_DIMENSION}px per side"
+        )));
+    }
+
+    #[cfg(feature = "image")]
+    if width.max(height) > RECOMMENDED_LONG_EDGE || bytes.len() > MAX_IMAGE_BYTES {
+        let (media_type, resized) = downscale(bytes, media_type)?;
+        check_size(resized.len())?;
+        return Ok((media_type, STANDARD.encode(resized)));
+    }
+
+    check_size(bytes.len())?;
+    Ok((media_type, STANDARD.encode(bytes)))
+}
+
+fn check_size(len: usize) -> Result<(), AnthropicError> {
+    if len > MAX_IMAGE_BYTES {
+        return Err(AnthropicError::InvalidRequest(format!("image is {len} bytes; the maximum is {MAX_IMAGE_BYTES}")));
+    }
+    Ok(())
+}
+
+/// Upper bound on decoder allocations in [`downscale`]: room for an RGBA8
+/// image at [`MAX_IMAGE_DIMENSION`] on both sides.
+#[cfg(feature = "image")]
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Resize so the long edge is at most [`RECOMMENDED_LONG_EDGE`], keeping the
+/// aspect ratio. JPEGs stay JPEG; everything else is re-encoded as PNG (GIF
+/// animations keep only their first frame).
+///
+/// Decoding is capped at [`MAX_IMAGE_DIMENSION`] per side and
+/// [`MAX_DECODE_ALLOC`] bytes, so the header check in [`encode_image`] is
+/// backed by the decoder itself.
+#[cfg(feature = "image")]
+fn downscale(bytes: &[u8], media_type: ImageMediaType) -> Result<(ImageMediaType, Vec<u8>), AnthropicError> {
+    use image::imageops::FilterType;
+    use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+
+    let decode_error =
+        |err: image::ImageError| AnthropicError::InvalidRequest(format!("failed to decode image: {err}"));
+    let format = match media_type {
+        ImageMediaType::Jpeg => ImageFormat::Jpeg,
+        ImageMediaType::Png => ImageFormat::Png,
+        ImageMediaType::Gif => ImageFormat::Gif,
+        ImageMediaType::Webp => ImageFormat::WebP,
+    };
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    let mut reader = ImageReader::with_format(std::io::Cursor::new(bytes), format);
+    reader.limits(limits);
+    let image = reader.decode().map_err(decode_error)?;
+    let image = if image.width().max(image.height()) > RECOMMENDED_LONG_EDGE {
+        image.resize(RECOMMENDED_LONG_EDGE, RECOMMENDED_LONG_EDGE, FilterType::Lanczos3)
+    } else {
+        image
+    };
+
+    let mut out = std::io::Cursor::new(Vec::new());
+    let encode_error =
+        |err: image::ImageError| AnthropicError::InvalidRequest(format!("failed to encode image: {err}"));
+    let media_type = match media_type {
+        ImageMediaType::Jpeg => {
+            DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut out, ImageFormat::Jpeg).map_err(encode_error)?;
+            ImageMediaType::Jpeg
+        }
+        _ => {
+            image.write_to(&mut out, ImageFormat::Png).map_err(encode_error)?;
+            ImageMediaType::Png
+        }
+    };
+    Ok((media_type, out.into_inner()))
+}
+
+fn be_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le_u24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+fn webp_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        // Lossy: 14-bit sizes after the frame tag and start code.
+        b"VP8 " => Some(((le_u16(bytes, 26)? & 0x3FFF) as u32, (le_u16(bytes, 28)? & 0x3FFF) as u32)),
+        // Lossless: two 14-bit (size - 1) fields packed after the signature byte.
+        b"VP8L" => {
+            let b = bytes.get(21..25)?;
+            let width = 1 + (b[0] as u32 | (b[1] as u32 & 0x3F) << 8);
+            let height = 1 + (b[1] as u32 >> 6 | (b[2] as u32) << 2 | (b[3] as u32 & 0x0F) << 10);
+            Some((width, height))
+        }
+        // Extended: 24-bit (size - 1) canvas fields.
+        b"VP8X" => Some((1 + le_u24(bytes, 24)?, 1 + le_u24(bytes, 27)?)),
+        _ => None,
+    }
+}
+
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    loop {
+        if *bytes.get(at)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(at + 1)?;
+        match marker {
+            // Fill bytes before a marker.
+            0xFF => at += 1,
+            // Standalone markers carry no length.
+            0x01 | 0xD0..=0xD7 => at += 2,
+            // Start-of-frame markers (excluding DHT, JPG, and DAC).
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let height = be_u16(bytes, at + 5)? as u32;
+                let width = be_u16(bytes, at + 7)? as u32;
+                return Some((width, height));
+            }
+            _ => at += 2 + be_u16(bytes, at + 2)? as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[8, 6, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn sniffs_supported_formats() {
+        assert_eq!(ImageMediaType::sniff(&png_header(1, 1)), Some(ImageMediaType::Png));
+        assert_eq!(ImageMediaType::sniff(b"\xFF\xD8\xFF\xE0"), Some(ImageMediaType::Jpeg));
+        assert_eq!(ImageMediaType::sniff(b"GIF89a\x01\x00"), Some(ImageMediaType::Gif));
+        assert_eq!(ImageMediaType::sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some(ImageMediaType::Webp));
+        assert_eq!(ImageMediaType::sniff(b"%PDF-1.7"), None);
+        assert_eq!(ImageMediaType::Webp.as_str(), "image/webp");
+    }
+
+    #[test]
+    fn reads_dimensions_from_headers() {
+        assert_eq!(image_dimensions(&png_header(640, 480)), Some((640, 480)));
+        assert_eq!(image_dimensions(b"GIF89a\x20\x03\x58\x02"), Some((800, 600)));
+
+        // APP0 segment, then SOF0 with height 300 and width 500.
+        let jpeg =
+            [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0x2C, 0x01, 0xF4];
+        assert_eq!(image_dimensions(&jpeg), Some((500, 300)));
+
+        let mut vp8x = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0\0\0\0\0".to_vec();
+        vp8x.extend_from_slice(&[0x1F, 0x03, 0x00, 0x57, 0x02, 0x00]);
+        assert_eq!(image_dimensions(&vp8x), Some((800, 600)));
+
+        // 2x3 lossless: (w-1)=1 and (h-1)=2 packed as 14-bit fields.
+        let vp8l = b"RIFF\0\0\0\0WEBPVP8L\0\0\0\0\x2f\x01\x80\x00\x00";
+        assert_eq!(image_dimensions(vp8l), Some((2, 3)));
+
+        assert_eq!(image_dimensions(&png_header(1, 1)[..18]), None);
+    }
+
+    #[test]
+    fn encodes_small_images() {
+        let bytes = png_header(64, 32);
+        let (media_type, data) = encode_image(&bytes).unwrap();
+        assert_eq!(media_type, ImageMediaType::Png);
+        assert_eq!(STANDARD.decode(data).unwrap(), bytes);
+    }
+
+    #[test]
+    fn rejects_unknown_formats() {
+        let err = encode_image(b"not an image").unwrap_err();
+        assert!(matches!(err, AnthropicError::InvalidRequest(msg) if msg.contains("unsupported image format")));
+    }
+
+    #[cfg(not(feature = "image"))]
+    #[test]
+    fn enforces_limits_without_resizing() {
+        let err = encode_image(&png_header(9000, 10)).unwrap_err();
+        assert!(matches!(err, AnthropicError::InvalidRequest(msg) if msg.contains("8000px")));
+
+        let mut big = png_header(100, 100);
+        big.resize(MAX_IMAGE_BYTES + 1, 0);
+        let err = encode_image(&big).unwrap_err();
+        assert!(matches!(err, AnthropicError::InvalidRequest(msg) if msg.contains("maximum is 5242880")));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn rejects_oversized_dimensions_before_decoding() {
+        // Header-only PNG: decoding would fail, so the error proves the
+        // dimension check ran first.
+        let err = encode_image(&png_header(9000, 10)).unwrap_err();
+        assert!(matches!(err, AnthropicError::InvalidRequest(msg) if msg.contains("8000px")));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn downscales_oversized_images() {
+        let source = image::RgbImage::from_pixel(2000, 1000, image::Rgb([200, 40, 40]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        source.write_to(&mut png, image::ImageFormat::Png).unwrap();
+
+        let (media_type, data) = encode_image(png.get_ref()).unwrap();
+        assert_eq!(media_type, ImageMediaType::Png);
+        let resized = STANDARD.decode(data).unwrap();
+        assert_eq!(image_dimensions(&resized), Some((RECOMMENDED_LONG_EDGE, RECOMMENDED_LONG_EDGE / 2)));
+
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        source.write_to(&mut jpeg, image::ImageFormat::Jpeg).unwrap();
+        let (media_type, data) = encode_image(jpeg.get_ref()).unwrap();
+        assert_eq!(media_type, ImageMediaType::Jpeg);
+        assert_eq!(image_dimensions(&STANDARD.decode(data).unwrap()), Some((1568, 784)));
+    }
+}
@@ -36,12 +36,48 @@
 //!   [`Client::cancel_batch`](client::Client::cancel_batch),
 //!   [`Client::delete_batch`](client::Client::delete_batch), and
 //!   [`Client::get_batch_results`](client::Client::get_batch_results) (JSONL-aware).
+//! - Files API: [`Client::upload_file`](client::Client::upload_file),
+//!   [`Client::list_files`](client::Client::list_files),
+//!   [`Client::download_file`](client::Client::download_file), and friends;
+//!   uploaded files can be referenced as image or document sources.
+//! - [`documents`] helpers: size- and page-checked PDFs
+//!   (`ContentBlock::pdf_from_path`), a paragraph-aware text chunker for
+//!   citable custom-content documents, [`documents::search_results`] for RAG
+//!   passages, and [`documents::DocumentSet`] to share one cached document
+//!   prefix across requests.
+//! - [`images`] helpers behind `ContentBlock::image_from_path` /
+//!   `image_from_bytes`: media type sniffing, API limit checks, and (with the
+//!   optional `image` feature) downscaling of oversized images.
 //! - [`StreamAccumulator`] / [`collect_stream`] to fold a live SSE stream
 //!   into a fully materialized [`types::MessagesResponse`].
-//! - [`run_tool_loop`] to drive a tool-use conversation end-to-end.
+//! - [`run_tool_loop`] to drive a tool-use conversation end-to-end, with
+//!   [`ToolRegistry`] to dispatch calls to per-tool async handlers that
+//!   share an `Arc` context. Per-tool timeouts, a loop deadline, and a
+//!   token or dollar [`ToolLoopBudget`] keep runaway agents in check.
+//!   [`run_tool_loop_streaming`] streams each turn and reports text,
+//!   thinking, and tool progress through a [`ToolLoopEvent`] callback.
+//! - Cancellation: a [`CancellationToken`] stops [`run_tool_loop`] (keeping
+//!   the partial transcript), `Client::messages_with_cancellation`,
+//!   `messages_stream_with_cancellation`, and
+//!   `wait_for_batch_with_cancellation`, and drops the in-flight HTTP
+//!   request.
+//! - Typed computer use, bash, and text editor tool definitions in
+//!   [`computer_use`], with action enums that parse incoming `tool_use`
+//!   inputs, plus ready-made sandboxed [`executors`] for the text editor
+//!   and bash tools.
+//! - [`Conversation`] for multi-turn chats that keep their transcript and
+//!   reuse the code execution container between turns.
+//! - Citations: typed [`types::Citation`] locations on text blocks (streamed
+//!   via `citations_delta`) and
+//!   [`MessagesRequest::resolve_citation`](types::MessagesRequest::resolve_citation)
+//!   to map each one back to the cited document and span.
 //! - Prompt-caching (`CacheControl`), extended thinking (`ThinkingConfig`),
 //!   service tier, image / document blocks, and all other modern request
 //!   fields are supported on [`types::MessagesRequestBuilder`].
+//! - Extended thinking with tools: [`run_tool_loop`] and [`Conversation`]
+//!   send `thinking` / `redacted_thinking` blocks back with their signatures,
+//!   thinking budgets are validated against `max_tokens`, and the
+//!   interleaved-thinking beta is one builder call away.
 //! - Per-call retry override via [`RetryPolicy`] —
 //!   `MessagesRequestBuilder::backoff`, `no_retries`, and `retry_policy`
 //!   let individual calls opt out of retries on interactive paths or
@@ -51,28 +87,80 @@
 //!   critical path, with `method`, `path`, `status`, `attempts`, and
 //!   `duration_ms` fields plus per-attempt events. The feature compiles
 //!   out entirely when disabled.
+//! - Optional `mcp` Cargo feature — `mcp::McpClient` connects to local MCP
+//!   servers over stdio or streamable HTTP, turns their `tools/list` into
+//!   tool definitions, and provides a `run_tool_loop` executor that
+//!   forwards calls as `tools/call`.
+//! - Optional `schemars` Cargo feature — `typed_tool::TypedTool` derives a
+//!   tool's `input_schema` from a Rust type and deserializes `tool_use`
+//!   inputs before calling the handler, reporting bad input to the model.
+//!   With it, [`Client::extract`](client::Client::extract) pulls a typed value
+//!   out of a response by forcing a synthesized tool and deserializing its
+//!   input, optionally re-asking the model when the input does not match.
+//! - Optional `jsonschema` Cargo feature — `ToolLoopConfig::validate_inputs`
+//!   checks each `tool_use` input against the tool's `input_schema` and
+//!   answers violations with a `tool_result` error instead of dispatching.
+//! - Optional `macros` Cargo feature — the [`tool`](macro@tool) attribute turns an
+//!   `async fn` into a `TypedTool`, with its doc comments as the tool and
+//!   parameter descriptions.
 
 pub mod batches;
+pub mod citations;
 pub mod client;
+pub mod computer_use;
+pub mod conversation;
 pub mod count_tokens;
+pub mod documents;
 pub mod error;
+pub mod executors;
+#[cfg(feature = "schemars")]
+pub mod extract;
+pub mod files;
+pub mod images;
+#[cfg(feature = "mcp")]
+pub mod mcp;
 pub mod models;
 pub mod stream;
 pub mod tool_loop;
+pub mod tool_registry;
+#[cfg(feature = "schemars")]
+pub mod typed_tool;
 pub mod types;
 
 pub use batches::{
     BatchProcessingStatus, BatchRequest, BatchRequestCounts, BatchRequestResult, BatchResultItem, CreateBatchRequest,
     ListBatchesParams, MessageBatch, MessageBatchList,
 };
+pub use citations::{CitationSpan, CitedSource};
 pub use client::{Client, ClientBuilder, ExponentialBackoff};
+pub use conversation::Conversation;
 pub use count_tokens::{CountTokensRequest, CountTokensRequestBuilder, CountTokensResponse};
 pub use error::{AnthropicError, ApiError};
+pub use files::{FileDeleted, FileList, FileMetadata, ListFilesParams};
 pub use models::{ListModelsParams, Model, ModelList};
 pub use stream::{collect, collect_stream, StreamAccumulator};
-pub use tool_loop::{run_tool_loop, ToolLoopConfig, ToolOutput};
+pub use tokio_util::sync::CancellationToken;
+pub use tool_loop::{
+    run_tool_loop, run_tool_loop_streaming, ToolCancelPolicy, ToolLoopBudget, ToolLoopConfig, ToolLoopEvent,
+    ToolLoopIteration, ToolLoopOutcome, ToolLoopStopReason, ToolOutput,
+};
+pub use tool_registry::ToolRegistry;
 pub use types::RetryPolicy;
 
+#[cfg(feature = "macros")]
+pub use anthropic_macros::tool;
+
+/// Re-exports used by code generated by the `#[tool]` macro. Not part of the
+/// public API.
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod __private {
+    pub use futures_util::future::BoxFuture;
+    pub use schemars;
+    pub use serde;
+    pub use serde_json;
+}
+
 /// Fuzzing entry points for harnesses under `fuzz/`.
 ///
 /// These functions wrap internal parsers that run on attacker-controllable
@@ -84,7 +172,7 @@ pub use types::RetryPolicy;
 #[doc(hidden)]
 pub mod __fuzz {
     /// Feed arbitrary bytes through the internal error-body parser the way
-    /// `execute_bytes` does on a non-success response. The function must
+    /// the transport does on a non-success response. The function must
     /// never panic and must always produce an `AnthropicError`.
     pub fn parse_error(status: u16, bytes: &[u8]) -> crate::AnthropicError {
         crate::client::parse_error(status, bytes)
@@ -0,0 +1,561 @@
+//! Local MCP client bridge (requires the `mcp` Cargo feature).
+//!
+//! [`McpClient`] speaks the Model Context Protocol to a server the caller
+//! runs, either as a child process over stdio or at a streamable HTTP
+//! endpoint. It exposes the server's `tools/list` as [`Tool`] definitions
+//! and forwards `tool_use` calls as `tools/call`, so MCP tools can be used
+//! with [`run_tool_loop`](crate::tool_loop::run_tool_loop) without any glue:
+//!
+//! ```no_run
+//! # async fn demo(client: &anthropic::Client) -> Result<(), anthropic::AnthropicError> {
+//! use anthropic::mcp::McpClient;
+//! use anthropic::tool_loop::{run_tool_loop, ToolLoopConfig};
+//! use anthropic::types::{Message, MessagesRequestBuilder};
+//!
+//! let mut command = tokio::process::Command::new("my-mcp-server");
+//! command.arg("--stdio");
+//! let mcp = McpClient::spawn(command).await?;
+//!
+//! let request = MessagesRequestBuilder::new("claude-3-5-sonnet-20240620", vec![Message::user("hi")], 1024)
+//!     .tools(mcp.list_tools().await?)
+//!     .build()?;
+//! let outcome = run_tool_loop(client, request, mcp.executor(), ToolLoopConfig::default()).await?;
+//! # Ok(()) }
+//! ```
+//!
+//! This is unrelated to the MCP *connector*
+//! ([`McpServer`](crate::types::McpServer)), where the API itself connects to
+//! a remote server.
+
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+
+use crate::error::AnthropicError;
+use crate::tool_loop::ToolOutput;
+use crate::types::{ContentBlock, Tool};
+
+/// MCP protocol revision requested during the handshake.
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+const SESSION_HEADER: &str = "mcp-session-id";
+/// Sent on every streamable-HTTP request after `initialize`.
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// Client for a single MCP server connection.
+///
+/// Cheap to clone; clones share the connection. Requests on a stdio
+/// connection are sent one at a time.
+#[derive(Clone)]
+pub struct McpClient {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    transport: Transport,
+    next_id: AtomicU64,
+    server_info: Value,
+}
+
+enum Transport {
+    Io(Mutex<IoTransport>),
+    Http(HttpTransport),
+}
+
+type BoxReader = BufReader<Pin<Box<dyn AsyncRead + Send>>>;
+type BoxWriter = Pin<Box<dyn AsyncWrite + Send>>;
+
+struct IoTransport {
+    reader: BoxReader,
+    writer: BoxWriter,
+    /// Keeps a spawned server alive (and kills it on drop).
+    _child: Option<Child>,
+}
+
+struct HttpTransport {
+    http: reqwest::Client,
+    url: String,
+    headers: HeaderMap,
+    session_id: std::sync::Mutex<Option<String>>,
+    /// The version negotiated by `initialize`; `None` until then.
+    protocol_version: std::sync::Mutex<Option<String>>,
+}
+
+impl std::fmt::Debug for McpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let transport = match self.inner.transport {
+            Transport::Io(_) => "io",
+            Transport::Http(_) => "http",
+        };
+        f.debug_struct("McpClient")
+            .field("transport", &transport)
+            .field("server_info", &self.inner.server_info)
+            .finish()
+    }
+}
+
+impl McpClient {
+    /// Spawn `command` and talk to it over its stdin / stdout. The process is
+    /// killed when the last clone of the client is dropped.
+    pub async fn spawn(mut command: Command) -> Result<Self, AnthropicError> {
+        command.stdin(Stdio::piped()).stdout(Stdio::piped()).kill_on_drop(true);
+        let mut child = command.spawn().map_err(|err| AnthropicError::Mcp(format!("failed to spawn server: {err}")))?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Self::connect_io(stdout, stdin, Some(child)).await
+    }
+
+    /// Use an already-connected byte stream, such as a socket or an in-memory
+    /// pipe, with newline-delimited JSON-RPC framing.
+    pub async fn from_io<R, W>(reader: R, writer: W) -> Result<Self, AnthropicError>
+    where
+        R: AsyncRead + Send + 'static,
+        W: AsyncWrite + Send + 'static,
+    {
+        Self::connect_io(reader, writer, None).await
+    }
+
+    /// Connect to a streamable HTTP endpoint.
+    pub async fn connect_http(url: impl Into<String>) -> Result<Self, AnthropicError> {
+        Self::connect_http_with_headers(url, HeaderMap::new()).await
+    }
+
+    /// Connect to a streamable HTTP endpoint, sending `headers` (for example
+    /// `Authorization`) with every request.
+    pub async fn connect_http_with_headers(url: impl Into<String>, headers: HeaderMap) -> Result<Self, AnthropicError> {
+        let transport = HttpTransport {
+            http: reqwest::Client::new(),
+            url: url.into(),
+            headers,
+            session_id: std::sync::Mutex::new(None),
+            protocol_version: std::sync::Mutex::new(None),
+        };
+        Self::initialize(Transport::Http(transport)).await
+    }
+
+    async fn connect_io<R, W>(reader: R, writer: W, child: Option<Child>) -> Result<Self, AnthropicError>
+    where
+        R: AsyncRead + Send + 'static,
+        W: AsyncWrite + Send + 'static,
+    {
+        let reader: Pin<Box<dyn AsyncRead + Send>> = Box::pin(reader);
+        let transport = IoTransport { reader: BufReader::new(reader), writer: Box::pin(writer), _child: child };
+        Self::initialize(Transport::Io(Mutex::new(transport))).await
+    }
+
+    async fn initialize(transport: Transport) -> Result<Self, AnthropicError> {
+        let mut client =
+            Self { inner: Arc::new(Inner { transport, next_id: AtomicU64::new(1), server_info: Value::Null }) };
+        let server_info = client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {"name": "anthropic-rs", "version": env!("CARGO_PKG_VERSION")}
+                }),
+            )
+            .await?;
+        if let Transport::Http(http) = &client.inner.transport {
+            let version = server_info.get("protocolVersion").and_then(Value::as_str).unwrap_or(PROTOCOL_VERSION);
+            *http.protocol_version.lock().expect("protocol version lock poisoned") = Some(version.to_string());
+        }
+        client.notify("notifications/initialized").await?;
+        Arc::get_mut(&mut client.inner).expect("client is not shared yet").server_info = server_info;
+        Ok(client)
+    }
+
+    /// The server's `initialize` result (protocol version, capabilities,
+    /// server info).
+    pub fn server_info(&self) -> &Value {
+        &self.inner.server_info
+    }
+
+    /// Fetch every tool the server offers, following pagination, as tool
+    /// definitions ready for [`MessagesRequestBuilder::tools`](crate::types::MessagesRequestBuilder::tools).
+    pub async fn list_tools(&self) -> Result<Vec<Tool>, AnthropicError> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({"cursor": cursor}),
+                None => json!({}),
+            };
+            let page: ListToolsPage = serde_json::from_value(self.request("tools/list", params).await?)?;
+            tools.extend(
+                page.tools
+                    .into_iter()
+                    .map(|tool| Tool::new(tool.name, tool.description.unwrap_or_default(), tool.input_schema)),
+            );
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(tools),
+            }
+        }
+    }
+
+    /// Call a tool on the server.
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, AnthropicError> {
+        let result = self.request("tools/call", json!({"name": name, "arguments": arguments})).await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Executor for [`run_tool_loop`](crate::tool_loop::run_tool_loop) that
+    /// forwards every call to this server.
+    ///
+    /// Tool failures and JSON-RPC errors (such as an unknown tool) become
+    /// [`ToolOutput::error`] so the model can react; transport failures abort
+    /// the loop.
+    pub fn executor(&self) -> impl FnMut(String, Value) -> BoxFuture<'static, Result<ToolOutput, AnthropicError>> {
+        let client = self.clone();
+        move |name, input| {
+            let client = client.clone();
+            Box::pin(async move {
+                match client.call_tool(&name, input).await {
+                    Ok(result) => Ok(result.into_tool_output()),
+                    Err(AnthropicError::McpRpc { message, .. }) => Ok(ToolOutput::error(message)),
+                    Err(err) => Err(err),
+                }
+            })
+        }
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, AnthropicError> {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        let response = match &self.inner.transport {
+            Transport::Io(io) => io.lock().await.request(id, &message).await?,
+            Transport::Http(http) => http.request(id, &message).await?,
+        };
+        into_result(response)
+    }
+
+    async fn notify(&self, method: &str) -> Result<(), AnthropicError> {
+        let message = json!({"jsonrpc": "2.0", "method": method});
+        match &self.inner.transport {
+            Transport::Io(io) => io.lock().await.send(&message).await,
+            Transport::Http(http) => http.notify(&message).await,
+        }
+    }
+}
+
+/// Split a JSON-RPC response into its result or error.
+fn into_result(mut response: Value) -> Result<Value, AnthropicError> {
+    if let Some(error) = response.get("error") {
+        return Err(AnthropicError::McpRpc {
+            code: error.get("code").and_then(Value::as_i64).unwrap_or_default(),
+            message: error.get("message").and_then(Value::as_str).unwrap_or("unknown error").to_string(),
+        });
+    }
+    Ok(response.get_mut("result").map(Value::take).unwrap_or(Value::Null))
+}
+
+/// Returns `true` if `message` is the response to request `id`.
+fn is_response_to(message: &Value, id: u64) -> bool {
+    message.get("id").and_then(Value::as_u64) == Some(id)
+        && (message.get("result").is_some() || message.get("error").is_some())
+}
+
+impl IoTransport {
+    async fn send(&mut self, message: &Value) -> Result<(), AnthropicError> {
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        self.writer.write_all(&line).await.map_err(io_error)?;
+        self.writer.flush().await.map_err(io_error)
+    }
+
+    async fn request(&mut self, id: u64, message: &Value) -> Result<Value, AnthropicError> {
+        self.send(message).await?;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line).await.map_err(io_error)? == 0 {
+                return Err(AnthropicError::Mcp("server closed the connection".into()));
+            }
+            let Ok(incoming) = serde_json::from_str::<Value>(line.trim()) else {
+                // Servers may log non-protocol lines; skip them.
+                continue;
+            };
+            if is_response_to(&incoming, id) {
+                return Ok(incoming);
+            }
+            // Server-initiated request: answer pings, decline everything else.
+            if let (Some(request_id), Some(method)) = (incoming.get("id"), incoming.get("method")) {
+                let reply = if method == "ping" {
+                    json!({"jsonrpc": "2.0", "id": request_id, "result": {}})
+                } else {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": request_id,
+                        "error": {"code": -32601, "message": format!("method {method} not supported by client")}
+                    })
+                };
+                self.send(&reply).await?;
+            }
+        }
+    }
+}
+
+impl HttpTransport {
+    fn post(&self, message: &Value) -> reqwest::RequestBuilder {
+        let mut request = self
+            .http
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .header(ACCEPT, HeaderValue::from_static("application/json, text/event-stream"))
+            .json(message);
+        if let Some(session) = self.session_id.lock().expect("session lock poisoned").as_deref() {
+            request = request.header(SESSION_HEADER, session);
+        }
+        if let Some(version) = self.protocol_version.lock().expect("protocol version lock poisoned").as_deref() {
+            request = request.header(PROTOCOL_VERSION_HEADER, version);
+        }
+        request
+    }
+
+    async fn send(&self, message: &Value) -> Result<reqwest::Response, AnthropicError> {
+        let response = self.post(message).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(AnthropicError::UnexpectedResponse { status: status.as_u16(), body });
+        }
+        if let Some(session) = response.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+            *self.session_id.lock().expect("session lock poisoned") = Some(session.to_string());
+        }
+        Ok(response)
+    }
+
+    async fn notify(&self, message: &Value) -> Result<(), AnthropicError> {
+        self.send(message).await.map(drop)
+    }
+
+    async fn request(&self, id: u64, message: &Value) -> Result<Value, AnthropicError> {
+        let response = self.send(message).await?;
+        let is_sse = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        if !is_sse {
+            return Ok(response.json().await?);
+        }
+
+        // Read SSE events until the one carrying our response.
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+        let mut data = String::new();
+        while let Some(chunk) = stream.next().await {
+            buffer.push_str(&String::from_utf8_lossy(&chunk?));
+            while let Some(newline) = buffer.find('\n') {
+                let line: String = buffer.drain(..=newline).collect();
+                let line = line.trim_end_matches(['\r', '\n']);
+                if let Some(payload) = line.strip_prefix("data:") {
+                    data.push_str(payload.strip_prefix(' ').unwrap_or(payload));
+                } else if line.is_empty() && !data.is_empty() {
+                    let event: Value = serde_json::from_str(&data)?;
+                    data.clear();
+                    if is_response_to(&event, id) {
+                        return Ok(event);
+                    }
+                }
+            }
+        }
+        if !data.is_empty() {
+            let event: Value = serde_json::from_str(&data)?;
+            if is_response_to(&event, id) {
+                return Ok(event);
+            }
+        }
+        Err(AnthropicError::Mcp(format!("event stream ended without a response to request {id}")))
+    }
+}
+
+fn io_error(err: std::io::Error) -> AnthropicError {
+    AnthropicError::Mcp(format!("transport error: {err}"))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListToolsPage {
+    tools: Vec<McpTool>,
+    #[serde(default)]
+    next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct McpTool {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    input_schema: Value,
+}
+
+/// Result of a `tools/call` request.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResult {
+    #[serde(default)]
+    pub content: Vec<McpContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+impl CallToolResult {
+    /// Convert the result into content blocks for a `tool_result`.
+    ///
+    /// Text and images map directly. Embedded text resources become text;
+    /// content Claude cannot read in a tool result (audio, binary resources,
+    /// resource links) is replaced by a short text note. When a server only
+    /// returns `structuredContent`, it is rendered as JSON text.
+    pub fn to_content_blocks(&self) -> Vec<ContentBlock> {
+        let mut blocks: Vec<ContentBlock> = self.content.iter().map(McpContent::to_content_block).collect();
+        if blocks.is_empty() {
+            if let Some(structured) = &self.structured_content {
+                blocks.push(ContentBlock::text(structured.to_string()));
+            }
+        }
+        blocks
+    }
+
+    /// Convert the result into a [`ToolOutput`] for the tool loop. Text-only
+    /// results are flattened into a single string.
+    pub fn into_tool_output(self) -> ToolOutput {
+        let blocks = self.to_content_blocks();
+        let mut output = if blocks.iter().all(|b| b.as_text().is_some()) {
+            ToolOutput::ok(blocks.iter().filter_map(|b| b.as_text()).collect::<Vec<_>>().join("\n"))
+        } else {
+            ToolOutput::blocks(blocks)
+        };
+        output.is_error = self.is_error;
+        output
+    }
+}
+
+/// One item of MCP tool result content.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum McpContent {
+    Text {
+        text: String,
+    },
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Audio {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Resource {
+        resource: McpResourceContents,
+    },
+    ResourceLink {
+        uri: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    /// Content type this client does not know about.
+    #[serde(other)]
+    Unknown,
+}
+
+impl McpContent {
+    fn to_content_block(&self) -> ContentBlock {
+        match self {
+            Self::Text { text } => ContentBlock::text(text.clone()),
+            Self::Image { data, mime_type } => ContentBlock::image_base64(mime_type.clone(), data.clone()),
+            Self::Audio { mime_type, .. } => ContentBlock::text(format!("[audio content ({mime_type}) omitted]")),
+            Self::Resource { resource } => match &resource.text {
+                Some(text) => ContentBlock::text(text.clone()),
+                None => ContentBlock::text(format!("[binary resource {} omitted]", resource.uri)),
+            },
+            Self::ResourceLink { uri, .. } => ContentBlock::text(format!("[resource: {uri}]")),
+            Self::Unknown => ContentBlock::text("[unsupported content omitted]"),
+        }
+    }
+}
+
+/// Resource embedded in a tool result.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResourceContents {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ToolResultContent;
+
+    #[test]
+    fn converts_call_results_into_tool_outputs() {
+        let result: CallToolResult = serde_json::from_value(json!({
+            "content": [
+                {"type": "text", "text": "line 1"},
+                {"type": "resource", "resource": {"uri": "file:///a.txt", "text": "line 2"}}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(result.clone().into_tool_output(), ToolOutput::ok("line 1\nline 2"));
+
+        let result: CallToolResult = serde_json::from_value(json!({
+            "content": [
+                {"type": "text", "text": "screenshot:"},
+                {"type": "image", "data": "iVBORw0KGgo=", "mimeType": "image/png"},
+                {"type": "video", "url": "x"}
+            ],
+            "isError": true
+        }))
+        .unwrap();
+        let output = result.into_tool_output();
+        assert!(output.is_error);
+        assert_eq!(
+            output.content,
+            ToolResultContent::Blocks(vec![
+                ContentBlock::text("screenshot:"),
+                ContentBlock::image_base64("image/png", "iVBORw0KGgo="),
+                ContentBlock::text("[unsupported content omitted]"),
+            ])
+        );
+    }
+
+    #[test]
+    fn structured_content_is_used_when_content_is_empty() {
+        let result: CallToolResult =
+            serde_json::from_value(json!({"content": [], "structuredContent": {"sum": 3}})).unwrap();
+        assert_eq!(result.into_tool_output(), ToolOutput::ok(r#"{"sum":3}"#));
+    }
+
+    #[test]
+    fn json_rpc_errors_are_surfaced() {
+        let err = into_result(json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32602, "message": "no such tool"}}))
+            .unwrap_err();
+        assert!(matches!(err, AnthropicError::McpRpc { code: -32602, ref message } if message == "no such tool"));
+        assert_eq!(
+            into_result(json!({"jsonrpc": "2.0", "id": 1, "result": {"ok": true}})).unwrap(),
+            json!({"ok": true})
+        );
+    }
+}
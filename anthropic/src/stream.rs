@@ -4,10 +4,13 @@
 //! Messages SSE stream into a complete [`MessagesResponse`], handling:
 //!
 //! - text deltas (`text_delta` on `text` blocks)
-//! - tool-use input deltas (`input_json_delta` on `tool_use` blocks — the
-//!   partial JSON chunks are concatenated and re-parsed on the terminal event)
+//! - tool-use input deltas (`input_json_delta` on `tool_use`,
+//!   `server_tool_use`, and `mcp_tool_use` blocks — the partial JSON chunks
+//!   are concatenated and re-parsed on the terminal event)
 //! - extended-thinking deltas (`thinking_delta` and `signature_delta` on
 //!   `thinking` blocks)
+//! - citation deltas (`citations_delta` on `text` blocks — each citation is
+//!   appended to the block's `citations` list)
 //! - `message_delta` events carrying stop reasons and usage updates
 //!
 //! It is designed so that callers can either stream one event at a time and
@@ -96,7 +99,15 @@ impl StreamAccumulator {
                     (ContentBlock::Text { text, .. }, ContentBlockDelta::TextDelta { text: delta }) => {
                         text.push_str(&delta);
                     }
-                    (ContentBlock::ToolUse { .. }, ContentBlockDelta::InputJsonDelta { partial_json }) => {
+                    (ContentBlock::Text { citations, .. }, ContentBlockDelta::CitationsDelta { citation }) => {
+                        citations.get_or_insert_with(Vec::new).push(citation);
+                    }
+                    (
+                        ContentBlock::ToolUse { .. }
+                        | ContentBlock::ServerToolUse { .. }
+                        | ContentBlock::McpToolUse { .. },
+                        ContentBlockDelta::InputJsonDelta { partial_json },
+                    ) => {
                         self.partial_json[index].push_str(&partial_json);
                     }
                     (ContentBlock::Thinking { thinking, .. }, ContentBlockDelta::ThinkingDelta { thinking: delta }) => {
@@ -122,7 +133,12 @@ impl StreamAccumulator {
                     .message
                     .as_mut()
                     .ok_or_else(|| AnthropicError::InvalidRequest("stream event before message_start".into()))?;
-                if let Some(ContentBlock::ToolUse { input, .. }) = message.content.get_mut(index) {
+                if let Some(
+                    ContentBlock::ToolUse { input, .. }
+                    | ContentBlock::ServerToolUse { input, .. }
+                    | ContentBlock::McpToolUse { input, .. },
+                ) = message.content.get_mut(index)
+                {
                     if let Some(buffer) = buffer {
                         if !buffer.is_empty() {
                             *input = serde_json::from_str(&buffer).map_err(AnthropicError::Deserialize)?;
@@ -144,6 +160,9 @@ impl StreamAccumulator {
                 if delta.stop_sequence.is_some() {
                     message.stop_sequence = delta.stop_sequence.clone();
                 }
+                if delta.container.is_some() {
+                    message.container = delta.container.clone();
+                }
                 merge_usage(&mut message.usage, &usage);
                 self.final_delta = Some(delta);
             }
@@ -174,6 +193,9 @@ fn merge_usage(target: &mut Usage, delta: &MessageDeltaUsage) {
     if delta.cache_read_input_tokens.is_some() {
         target.cache_read_input_tokens = delta.cache_read_input_tokens;
     }
+    if delta.server_tool_use.is_some() {
+        target.server_tool_use = delta.server_tool_use.clone();
+    }
 }
 
 /// Drive a [`MessagesResponseStream`] (or any compatible [`Stream`]) to
@@ -198,7 +220,7 @@ pub async fn collect(stream: MessagesResponseStream) -> Result<MessagesResponse,
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{MessageDelta, MessageDeltaUsage, Role, StopReason};
+    use crate::types::{Citation, MessageDelta, MessageDeltaUsage, Role, ServerToolUsage, StopReason};
     use futures_util::stream;
     use serde_json::json;
 
@@ -213,6 +235,7 @@ mod tests {
                 stop_reason: None,
                 stop_sequence: None,
                 usage: Usage { input_tokens: 5, output_tokens: 0, ..Default::default() },
+                container: None,
             },
         }
     }
@@ -234,12 +257,13 @@ mod tests {
         acc.push(text_delta(0, "world!")).unwrap();
         acc.push(MessagesStreamEvent::ContentBlockStop { index: 0 }).unwrap();
         acc.push(MessagesStreamEvent::MessageDelta {
-            delta: MessageDelta { stop_reason: Some(StopReason::EndTurn), stop_sequence: None },
+            delta: MessageDelta { stop_reason: Some(StopReason::EndTurn), stop_sequence: None, container: None },
             usage: MessageDeltaUsage {
                 output_tokens: 12,
                 input_tokens: None,
                 cache_creation_input_tokens: None,
                 cache_read_input_tokens: None,
+                server_tool_use: None,
             },
         })
         .unwrap();
@@ -281,6 +305,89 @@ mod tests {
         assert_eq!(tool_uses[0].2, &json!({"city": "Paris"}));
     }
 
+    #[test]
+    fn accumulates_server_tool_use_and_results() {
+        let mut acc = StreamAccumulator::new();
+        acc.push(message_start()).unwrap();
+        acc.push(MessagesStreamEvent::ContentBlockStart {
+            index: 0,
+            content_block: ContentBlock::ServerToolUse {
+                id: "srvtoolu_1".into(),
+                name: "web_search".into(),
+                input: json!({}),
+                cache_control: None,
+            },
+        })
+        .unwrap();
+        acc.push(MessagesStreamEvent::ContentBlockDelta {
+            index: 0,
+            delta: ContentBlockDelta::InputJsonDelta { partial_json: "{\"query\": \"rust\"}".into() },
+        })
+        .unwrap();
+        acc.push(MessagesStreamEvent::ContentBlockStop { index: 0 }).unwrap();
+        let result: ContentBlock = serde_json::from_value(json!({
+            "type": "web_search_tool_result",
+            "tool_use_id": "srvtoolu_1",
+            "content": [{
+                "type": "web_search_result",
+                "url": "https://www.rust-lang.org",
+                "title": "Rust",
+                "encrypted_content": "enc"
+            }]
+        }))
+        .unwrap();
+        acc.push(MessagesStreamEvent::ContentBlockStart { index: 1, content_block: result.clone() }).unwrap();
+        acc.push(MessagesStreamEvent::ContentBlockStop { index: 1 }).unwrap();
+        acc.push(MessagesStreamEvent::MessageDelta {
+            delta: MessageDelta { stop_reason: Some(StopReason::EndTurn), stop_sequence: None, container: None },
+            usage: MessageDeltaUsage {
+                output_tokens: 3,
+                input_tokens: None,
+                cache_creation_input_tokens: None,
+                cache_read_input_tokens: None,
+                server_tool_use: Some(ServerToolUsage { web_search_requests: 1, web_fetch_requests: 0 }),
+            },
+        })
+        .unwrap();
+
+        let response = acc.finish().unwrap();
+        let calls: Vec<_> = response.server_tool_uses().collect();
+        assert_eq!(calls, vec![("srvtoolu_1", "web_search", &json!({"query": "rust"}))]);
+        assert!(!response.has_tool_use());
+        assert_eq!(response.content[1], result);
+        assert_eq!(response.usage.server_tool_use.unwrap().web_search_requests, 1);
+    }
+
+    #[test]
+    fn accumulates_mcp_tool_use_input() {
+        let mut acc = StreamAccumulator::new();
+        acc.push(message_start()).unwrap();
+        acc.push(MessagesStreamEvent::ContentBlockStart {
+            index: 0,
+            content_block: ContentBlock::McpToolUse {
+                id: "mcptoolu_1".into(),
+                name: "search_issues".into(),
+                server_name: "tracker".into(),
+                input: json!({}),
+                cache_control: None,
+            },
+        })
+        .unwrap();
+        for chunk in ["{\"query\": ", "\"bug\"}"] {
+            acc.push(MessagesStreamEvent::ContentBlockDelta {
+                index: 0,
+                delta: ContentBlockDelta::InputJsonDelta { partial_json: chunk.into() },
+            })
+            .unwrap();
+        }
+        acc.push(MessagesStreamEvent::ContentBlockStop { index: 0 }).unwrap();
+
+        let response = acc.finish().unwrap();
+        let calls: Vec<_> = response.mcp_tool_uses().collect();
+        assert_eq!(calls, vec![("mcptoolu_1", "tracker", "search_issues", &json!({"query": "bug"}))]);
+        assert!(!response.has_tool_use());
+    }
+
     #[test]
     fn accumulates_thinking_and_signature_deltas() {
         let mut acc = StreamAccumulator::new();
@@ -315,6 +422,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn accumulates_citations_deltas_on_text_blocks() {
+        let citation = Citation::CharLocation {
+            cited_text: "The sky is blue.".into(),
+            document_index: 0,
+            document_title: Some("Facts".into()),
+            start_char_index: 20,
+            end_char_index: 36,
+        };
+        let mut acc = StreamAccumulator::new();
+        acc.push(message_start()).unwrap();
+        acc.push(block_start_text(0)).unwrap();
+        acc.push(MessagesStreamEvent::ContentBlockDelta {
+            index: 0,
+            delta: ContentBlockDelta::CitationsDelta { citation: citation.clone() },
+        })
+        .unwrap();
+        acc.push(text_delta(0, "the sky is blue")).unwrap();
+        acc.push(MessagesStreamEvent::ContentBlockStop { index: 0 }).unwrap();
+        acc.push(MessagesStreamEvent::MessageStop).unwrap();
+
+        let response = acc.finish().unwrap();
+        assert_eq!(response.text(), "the sky is blue");
+        assert_eq!(response.citations().collect::<Vec<_>>(), vec![&citation]);
+    }
+
     #[test]
     fn rejects_delta_before_message_start() {
         let mut acc = StreamAccumulator::new();
@@ -360,12 +493,17 @@ mod tests {
         acc.push(text_delta(0, "hi")).unwrap();
         acc.push(MessagesStreamEvent::ContentBlockStop { index: 0 }).unwrap();
         acc.push(MessagesStreamEvent::MessageDelta {
-            delta: MessageDelta { stop_reason: Some(StopReason::StopSequence), stop_sequence: Some("STOP".into()) },
+            delta: MessageDelta {
+                stop_reason: Some(StopReason::StopSequence),
+                stop_sequence: Some("STOP".into()),
+                container: None,
+            },
             usage: MessageDeltaUsage {
                 output_tokens: 7,
                 input_tokens: Some(11),
                 cache_creation_input_tokens: Some(3),
                 cache_read_input_tokens: Some(4),
+                server_tool_use: None,
             },
         })
         .unwrap();
@@ -8,28 +8,181 @@
 //! [`run_tool_loop`] captures that pattern behind a minimal callback-based API
 //! so callers only have to provide the tool executor.
 
+use std::collections::BTreeMap;
+use std::fmt;
 use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::future::{self, BoxFuture, Either};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use tokio_util::sync::CancellationToken;
 
 use crate::client::Client;
 use crate::error::AnthropicError;
-use crate::types::{ContentBlock, Message, MessagesRequest, MessagesResponse, Role};
+use crate::stream::StreamAccumulator;
+use crate::types::{
+    ContentBlock, ContentBlockDelta, Message, MessagesRequest, MessagesResponse, MessagesStreamEvent, ModelPricing,
+    Role, StopReason, ToolResultContent, Usage,
+};
 
 /// Result of executing a single tool call.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ToolOutput {
-    pub content: String,
+    pub content: ToolResultContent,
     pub is_error: bool,
 }
 
 impl ToolOutput {
     /// Successful tool result.
     pub fn ok(content: impl Into<String>) -> Self {
-        Self { content: content.into(), is_error: false }
+        Self { content: ToolResultContent::Text(content.into()), is_error: false }
     }
 
     /// Tool-level error reported back to the model.
     pub fn error(content: impl Into<String>) -> Self {
-        Self { content: content.into(), is_error: true }
+        Self { content: ToolResultContent::Text(content.into()), is_error: true }
+    }
+
+    /// Successful tool result made of content blocks. Tool results may hold
+    /// text, image, document, and search result blocks.
+    pub fn blocks(blocks: Vec<ContentBlock>) -> Self {
+        Self { content: ToolResultContent::Blocks(blocks), is_error: false }
+    }
+
+    /// Successful tool result holding `value` serialized as compact JSON text.
+    pub fn json(value: serde_json::Value) -> Self {
+        Self::ok(value.to_string())
+    }
+
+    /// Successful tool result holding a base64-encoded image, e.g. a
+    /// screenshot.
+    pub fn image_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        Self::blocks(vec![ContentBlock::image_base64(media_type, data)])
+    }
+
+    /// Successful tool result holding an image from raw bytes; see
+    /// [`ContentBlock::image_from_bytes`] for format detection and limits.
+    pub fn image_from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self, AnthropicError> {
+        Ok(Self::blocks(vec![ContentBlock::image_from_bytes(bytes)?]))
+    }
+
+    /// Successful tool result holding a base64-encoded document.
+    pub fn document_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        Self::blocks(vec![ContentBlock::document_base64(media_type, data)])
+    }
+
+    /// Successful tool result holding a PDF from raw bytes; see
+    /// [`ContentBlock::pdf_from_bytes`] for the limits checked.
+    pub fn pdf_from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self, AnthropicError> {
+        Ok(Self::blocks(vec![ContentBlock::pdf_from_bytes(bytes)?]))
+    }
+
+    /// Successful tool result holding a plain-text document, which the model
+    /// can cite.
+    pub fn document_text(text: impl Into<String>) -> Self {
+        Self::blocks(vec![ContentBlock::document_text(text)])
+    }
+
+    /// Concatenated text of the result, ignoring non-text blocks.
+    pub fn text(&self) -> String {
+        match &self.content {
+            ToolResultContent::Text(text) => text.clone(),
+            ToolResultContent::Blocks(blocks) => blocks.iter().filter_map(|b| b.as_text()).collect(),
+        }
+    }
+
+    fn into_tool_result(self, tool_use_id: String) -> ContentBlock {
+        ContentBlock::ToolResult {
+            tool_use_id,
+            is_error: self.is_error.then_some(true),
+            content: self.content,
+            cache_control: None,
+        }
+    }
+}
+
+/// What [`ToolLoopHooks::before_tool`] decides about a tool call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolDecision {
+    /// Run the call as requested.
+    Approve,
+    /// Skip the call and report `reason` to the model as an error
+    /// `tool_result`.
+    Deny(String),
+    /// Run the call with this input instead.
+    Modify(serde_json::Value),
+    /// Stop the loop without running any more tools
+    /// ([`ToolLoopStopReason::Aborted`]).
+    Abort,
+}
+
+/// Lifecycle hooks for [`run_tool_loop`], e.g. to have a person approve
+/// destructive tool calls or to record progress. Every method has a no-op
+/// default.
+///
+/// ```no_run
+/// use anthropic::tool_loop::{ToolDecision, ToolLoopConfig, ToolLoopHooks};
+/// use futures_util::future::BoxFuture;
+/// use serde_json::Value;
+///
+/// struct ConfirmDeletes;
+///
+/// impl ToolLoopHooks for ConfirmDeletes {
+///     fn before_tool<'a>(&'a self, name: &'a str, input: &'a Value) -> BoxFuture<'a, ToolDecision> {
+///         Box::pin(async move {
+///             if name != "delete_file" {
+///                 return ToolDecision::Approve;
+///             }
+///             println!("Allow deleting {}? [y/N]", input["path"]);
+///             let mut answer = String::new();
+///             std::io::stdin().read_line(&mut answer).ok();
+///             match answer.trim() {
+///                 "y" => ToolDecision::Approve,
+///                 _ => ToolDecision::Deny("the user declined".into()),
+///             }
+///         })
+///     }
+/// }
+///
+/// let config = ToolLoopConfig::default().hooks(ConfirmDeletes);
+/// ```
+pub trait ToolLoopHooks: Send + Sync {
+    /// Called before every request to the model.
+    fn before_model_call<'a>(&'a self, request: &'a MessagesRequest) -> BoxFuture<'a, ()> {
+        let _ = request;
+        Box::pin(future::ready(()))
+    }
+
+    /// Called before each tool call is dispatched, in `tool_use` order.
+    /// Calls rejected by input validation never get here.
+    fn before_tool<'a>(&'a self, name: &'a str, input: &'a serde_json::Value) -> BoxFuture<'a, ToolDecision> {
+        let _ = (name, input);
+        Box::pin(future::ready(ToolDecision::Approve))
+    }
+
+    /// Called after each executed tool call, in `tool_use` order, with the
+    /// input it ran with. The output may be edited before it is sent.
+    fn after_tool<'a>(
+        &'a self,
+        name: &'a str,
+        input: &'a serde_json::Value,
+        output: &'a mut ToolOutput,
+    ) -> BoxFuture<'a, ()> {
+        let _ = (name, input, output);
+        Box::pin(future::ready(()))
+    }
+
+    /// Called when an iteration completes, with its zero-based index.
+    fn on_iteration<'a>(&'a self, index: usize, iteration: &'a ToolLoopIteration) -> BoxFuture<'a, ()> {
+        let _ = (index, iteration);
+        Box::pin(future::ready(()))
+    }
+}
+
+impl fmt::Debug for dyn ToolLoopHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ToolLoopHooks")
     }
 }
 
@@ -38,23 +191,316 @@ impl ToolOutput {
 pub struct ToolLoopConfig {
     /// Maximum number of model round-trips before the loop bails out.
     pub max_iterations: usize,
+    /// How many tool calls from one assistant turn may run concurrently.
+    /// `1` (the default) awaits them one after another. Results are always
+    /// sent back in the original `tool_use` order.
+    pub max_parallel_tools: usize,
+    /// Validate every `tool_use` input against the matching tool's
+    /// `input_schema` before calling the executor (`jsonschema` feature).
+    /// Invalid inputs are answered with a `tool_result` error listing each
+    /// violation and never reach the executor.
+    #[cfg(feature = "jsonschema")]
+    pub validate_inputs: bool,
+    /// Lifecycle hooks, including per-call approval.
+    pub hooks: Option<Arc<dyn ToolLoopHooks>>,
+    /// Largest `max_tokens` the loop may raise the request to. When a
+    /// response stops on `max_tokens` in the middle of a `tool_use` block,
+    /// the turn is retried with twice the budget, up to this cap. A request
+    /// that already starts at or above the cap still gets one retry with
+    /// twice its own budget.
+    pub max_tokens_cap: u32,
+    /// Longest a single tool call may run. A call that takes longer is
+    /// dropped and answered with an error `tool_result` so the model can
+    /// react.
+    pub tool_timeout: Option<Duration>,
+    /// Wall-clock limit for the whole loop. It bounds in-flight model
+    /// requests as well as running tool calls; a model request still waiting
+    /// at the deadline is dropped.
+    pub deadline: Option<Duration>,
+    /// Cumulative token and cost limits.
+    pub budget: ToolLoopBudget,
+    /// Stops the loop when cancelled, e.g. because the user went away. An
+    /// in-flight model request is dropped; running tool calls are handled
+    /// according to `on_cancel`.
+    pub cancellation: Option<CancellationToken>,
+    /// What happens to tool calls that are running when `cancellation`
+    /// fires.
+    pub on_cancel: ToolCancelPolicy,
+}
+
+/// How [`run_tool_loop`] treats running tool calls when
+/// [`ToolLoopConfig::cancellation`] fires. Either way, calls that have not
+/// started yet are skipped and answered with an error.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToolCancelPolicy {
+    /// Let running calls finish and keep their results (the default). Use
+    /// this when dropping a call halfway could leave things inconsistent.
+    #[default]
+    FinishRunning,
+    /// Drop running calls immediately and answer them with an error.
+    AbortRunning,
 }
 
 impl Default for ToolLoopConfig {
     fn default() -> Self {
-        Self { max_iterations: 8 }
+        Self::new(8)
     }
 }
 
 impl ToolLoopConfig {
     pub fn new(max_iterations: usize) -> Self {
-        Self { max_iterations }
+        Self {
+            max_iterations,
+            max_parallel_tools: 1,
+            #[cfg(feature = "jsonschema")]
+            validate_inputs: false,
+            hooks: None,
+            max_tokens_cap: 16_384,
+            tool_timeout: None,
+            deadline: None,
+            budget: ToolLoopBudget::default(),
+            cancellation: None,
+            on_cancel: ToolCancelPolicy::default(),
+        }
+    }
+
+    /// Stop the loop when `token` is cancelled.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Choose what happens to running tool calls on cancellation.
+    pub fn on_cancel(mut self, policy: ToolCancelPolicy) -> Self {
+        self.on_cancel = policy;
+        self
+    }
+
+    /// Answer tool calls that run longer than `tool_timeout` with an error.
+    pub fn tool_timeout(mut self, tool_timeout: Duration) -> Self {
+        self.tool_timeout = Some(tool_timeout);
+        self
+    }
+
+    /// Stop the loop once `deadline` has elapsed since it started.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stop the loop once `budget` is used up.
+    pub fn budget(mut self, budget: ToolLoopBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Run up to `max_parallel_tools` tool calls from the same turn
+    /// concurrently.
+    pub fn max_parallel_tools(mut self, max_parallel_tools: usize) -> Self {
+        self.max_parallel_tools = max_parallel_tools;
+        self
+    }
+
+    /// Cap for the `max_tokens` retries of truncated tool calls.
+    pub fn max_tokens_cap(mut self, max_tokens_cap: u32) -> Self {
+        self.max_tokens_cap = max_tokens_cap;
+        self
+    }
+
+    /// Install lifecycle hooks.
+    pub fn hooks(mut self, hooks: impl ToolLoopHooks + 'static) -> Self {
+        self.hooks = Some(Arc::new(hooks));
+        self
+    }
+
+    /// Turn `input_schema` validation of tool inputs on or off.
+    #[cfg(feature = "jsonschema")]
+    pub fn validate_inputs(mut self, validate_inputs: bool) -> Self {
+        self.validate_inputs = validate_inputs;
+        self
+    }
+}
+
+/// Cumulative usage limits for [`run_tool_loop`]. Usage is summed over all
+/// iterations and checked before every model request, so the request that
+/// crosses a limit still completes; the loop then stops with
+/// [`ToolLoopStopReason::BudgetExceeded`]. Every limit is off by default.
+///
+/// ```
+/// use anthropic::tool_loop::ToolLoopBudget;
+/// use anthropic::types::ModelPricing;
+///
+/// let budget = ToolLoopBudget::default().max_output_tokens(20_000).max_cost(0.50, ModelPricing::new(3.0, 15.0));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ToolLoopBudget {
+    /// Limit on input tokens, counting cache writes and reads (see
+    /// [`Usage::total_input_tokens`]).
+    pub max_input_tokens: Option<u32>,
+    /// Limit on output tokens, thinking included.
+    pub max_output_tokens: Option<u32>,
+    /// Limit on spend in US dollars, priced at `pricing`.
+    pub max_cost_usd: Option<f64>,
+    /// Prices for `max_cost_usd`. The cost limit is ignored without them.
+    pub pricing: Option<ModelPricing>,
+}
+
+impl ToolLoopBudget {
+    pub fn max_input_tokens(mut self, max_input_tokens: u32) -> Self {
+        self.max_input_tokens = Some(max_input_tokens);
+        self
+    }
+
+    pub fn max_output_tokens(mut self, max_output_tokens: u32) -> Self {
+        self.max_output_tokens = Some(max_output_tokens);
+        self
+    }
+
+    /// Limit spend to `max_cost_usd` dollars at `pricing`.
+    pub fn max_cost(mut self, max_cost_usd: f64, pricing: ModelPricing) -> Self {
+        self.max_cost_usd = Some(max_cost_usd);
+        self.pricing = Some(pricing);
+        self
+    }
+
+    /// Whether `usage` has reached any of the limits.
+    pub fn is_exhausted(&self, usage: &Usage) -> bool {
+        self.max_input_tokens.is_some_and(|max| usage.total_input_tokens() >= max)
+            || self.max_output_tokens.is_some_and(|max| usage.output_tokens >= max)
+            || self.max_cost_usd.zip(self.pricing).is_some_and(|(max, pricing)| usage.cost(&pricing) >= max)
+    }
+}
+
+/// Why [`run_tool_loop`] stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolLoopStopReason {
+    /// The model replied without calling any tools. Its own
+    /// [`StopReason`] is on the final response.
+    Finished,
+    /// [`ToolLoopConfig::max_iterations`] round-trips were used up while the
+    /// model was still calling tools. The last tool results are in
+    /// [`ToolLoopOutcome::messages`] but were never sent.
+    MaxIterations,
+    /// [`ToolLoopHooks::before_tool`] returned [`ToolDecision::Abort`]. No
+    /// tool of that turn ran, and [`ToolLoopOutcome::messages`] ends with
+    /// the unanswered assistant turn.
+    Aborted,
+    /// The model declined to respond (`stop_reason: "refusal"`). The refusal
+    /// is the final response and the last message of the transcript.
+    Refused,
+    /// A tool call was still truncated by `max_tokens` at
+    /// [`ToolLoopConfig::max_tokens_cap`] (or, for a request that started at
+    /// or above the cap, at twice its initial budget). The truncated
+    /// response is the final response but is not part of
+    /// [`ToolLoopOutcome::messages`].
+    MaxTokens,
+    /// [`ToolLoopConfig::budget`] was used up. Like
+    /// [`MaxIterations`](ToolLoopStopReason::MaxIterations), the last tool
+    /// results are in [`ToolLoopOutcome::messages`] but were never sent.
+    BudgetExceeded,
+    /// [`ToolLoopConfig::deadline`] passed. Tool calls still running at the
+    /// deadline were answered with errors, and a model request still in
+    /// flight was dropped; the last tool results are in
+    /// [`ToolLoopOutcome::messages`] but were never sent.
+    DeadlineExceeded,
+    /// [`ToolLoopConfig::cancellation`] fired. [`ToolLoopOutcome::messages`]
+    /// holds the transcript up to the last completed step, including the
+    /// results of tool calls that finished or were skipped.
+    Cancelled,
+}
+
+/// One model round-trip of the tool loop.
+#[derive(Debug, Clone)]
+pub struct ToolLoopIteration {
+    /// The model's response for this turn.
+    pub response: MessagesResponse,
+    /// Time spent waiting for the response.
+    pub model_duration: Duration,
+    /// Time spent running the turn's tool calls (zero for the final turn).
+    pub tools_duration: Duration,
+}
+
+impl ToolLoopIteration {
+    /// The turn's thinking text (see [`MessagesResponse::thinking`]).
+    pub fn thinking(&self) -> String {
+        self.response.thinking()
+    }
+}
+
+/// What [`run_tool_loop`] returns: the final response plus everything needed
+/// to persist the conversation and account for its cost.
+#[derive(Debug, Clone)]
+pub struct ToolLoopOutcome {
+    /// The last response received from the model.
+    pub response: MessagesResponse,
+    /// Why the loop stopped.
+    pub stop_reason: ToolLoopStopReason,
+    /// Full transcript: the request's messages followed by every assistant
+    /// turn and `tool_result` turn of the loop.
+    pub messages: Vec<Message>,
+    /// Every round-trip, in order.
+    pub iterations: Vec<ToolLoopIteration>,
+    /// Token usage summed over all iterations, including cache reads and
+    /// writes.
+    pub usage: Usage,
+    /// Number of `tool_use` calls the model made, per tool name.
+    pub tool_calls: BTreeMap<String, usize>,
+    /// Tool calls rejected by `input_schema` validation (see
+    /// `ToolLoopConfig::validate_inputs`) and answered with an error instead
+    /// of being dispatched.
+    pub validation_failures: usize,
+    /// Wall-clock time of the whole loop.
+    pub duration: Duration,
+}
+
+impl ToolLoopOutcome {
+    /// The thinking text of every iteration, in order, for audit logs. Turns
+    /// without thinking yield an empty string.
+    pub fn thinking(&self) -> Vec<String> {
+        self.iterations.iter().map(ToolLoopIteration::thinking).collect()
+    }
+}
+
+/// Compiled `input_schema`s of the request's custom tools.
+#[cfg(feature = "jsonschema")]
+pub(crate) struct InputValidators(std::collections::HashMap<String, jsonschema::Validator>);
+
+#[cfg(feature = "jsonschema")]
+impl InputValidators {
+    /// Schemas the validator cannot compile are skipped; the API is the
+    /// authority on those.
+    pub(crate) fn new(request: &MessagesRequest) -> Self {
+        let tools = request.tools.iter().flatten().filter_map(|tool| tool.as_custom());
+        Self(
+            tools
+                .filter_map(|tool| {
+                    jsonschema::validator_for(&tool.input_schema).ok().map(|validator| (tool.name.clone(), validator))
+                })
+                .collect(),
+        )
+    }
+
+    /// Error message for the model when `input` violates `name`'s schema.
+    pub(crate) fn check(&self, name: &str, input: &serde_json::Value) -> Option<String> {
+        let violations: Vec<String> = self
+            .0
+            .get(name)?
+            .iter_errors(input)
+            .map(|err| match err.instance_path.as_str() {
+                "" => format!("- {err}"),
+                path => format!("- at `{path}`: {err}"),
+            })
+            .collect();
+        (!violations.is_empty())
+            .then(|| format!("input for tool `{name}` does not match its input_schema:\n{}", violations.join("\n")))
     }
 }
 
 /// Drive the "call model → run tools → feed results back" loop until Claude
 /// returns a tool-free response, an error propagates, or the iteration budget
-/// is exhausted.
+/// is exhausted. The [`ToolLoopOutcome`] records which of the last two
+/// happened, along with the transcript, per-iteration responses and timing,
+/// and summed usage.
 ///
 /// `request` is cloned on every iteration with `messages` extended to contain
 /// the running transcript, so the original tool list, tool choice, system
@@ -63,58 +509,519 @@ impl ToolLoopConfig {
 /// The `executor` callback receives the tool name and input JSON and returns
 /// either a [`ToolOutput`] (success or tool-level error, both fed back to the
 /// model) or a propagated [`AnthropicError`] that aborts the loop.
+///
+/// With `ToolLoopConfig::validate_inputs` (`jsonschema` feature), inputs
+/// that violate the tool's `input_schema` are answered with a precise
+/// `tool_result` error so the model can correct itself, and counted in
+/// [`ToolLoopOutcome::validation_failures`].
+///
+/// Calls from the same assistant turn run concurrently, up to
+/// [`ToolLoopConfig::max_parallel_tools`] at a time, unless the request's
+/// [`ToolChoice`](crate::types::ToolChoice) disables parallel tool use. Their
+/// `tool_result` blocks keep the order of the `tool_use` blocks either way.
+///
+/// Turns paused by long-running server tools (`pause_turn`) are continued
+/// automatically, a `tool_use` truncated by `max_tokens` is retried with a
+/// larger budget (see [`ToolLoopConfig::max_tokens_cap`]), and refusals end
+/// the loop with [`ToolLoopStopReason::Refused`]. Each of these round-trips
+/// counts toward `max_iterations`.
+///
+/// [`ToolLoopConfig::tool_timeout`] answers stuck tool calls with an error
+/// `tool_result`, while [`ToolLoopConfig::deadline`] and
+/// [`ToolLoopConfig::budget`] stop the whole loop with
+/// [`ToolLoopStopReason::DeadlineExceeded`] or
+/// [`ToolLoopStopReason::BudgetExceeded`], keeping the transcript so far. A
+/// model request still in flight at the deadline is dropped; if that was the
+/// first request, the loop fails with [`AnthropicError::DeadlineExceeded`].
+///
+/// Cancelling [`ToolLoopConfig::cancellation`] drops any in-flight model
+/// request and stops with [`ToolLoopStopReason::Cancelled`]; running tool
+/// calls finish or are aborted per [`ToolLoopConfig::on_cancel`]. If the
+/// token fires before the first response arrives there is no outcome to
+/// return, and the loop fails with [`AnthropicError::Cancelled`].
+///
+/// [`ToolLoopConfig::hooks`] observe each model call, iteration, and tool
+/// call, and can approve, deny, rewrite, or abort calls before they run.
+/// Denied calls are answered with an error `tool_result`.
+///
+/// With extended thinking enabled, every assistant turn, including its
+/// `thinking` and `redacted_thinking` blocks and their signatures, is sent
+/// back unchanged as the API requires; the thinking budget is checked against
+/// `max_tokens` up front (see [`MessagesRequest::validate_thinking`]).
+///
+/// Only custom `tool_use` blocks are dispatched to the executor. Server tools
+/// (web search, web fetch, code execution) and MCP connector tools run on
+/// Anthropic's side: their `server_tool_use` / `mcp_tool_use` and result
+/// blocks are kept in the transcript verbatim and never reach the executor.
 pub async fn run_tool_loop<F, Fut>(
     client: &Client,
+    request: MessagesRequest,
+    executor: F,
+    config: ToolLoopConfig,
+) -> Result<ToolLoopOutcome, AnthropicError>
+where
+    F: FnMut(String, serde_json::Value) -> Fut,
+    Fut: Future<Output = Result<ToolOutput, AnthropicError>>,
+{
+    drive(request, executor, config, |request| client.messages(request), &|_event| {}).await
+}
+
+/// Like [`run_tool_loop`], but every model turn is streamed through
+/// [`Client::messages_stream`] and progress is reported to `on_event` as it
+/// happens: text, thinking, and tool-input deltas, plus the start and finish
+/// of each tool call. The returned [`ToolLoopOutcome`] is the same as the
+/// blocking loop's.
+///
+/// `on_event` runs inline, so it should return quickly. To consume events as
+/// a `Stream` instead, send them into a channel:
+///
+/// ```no_run
+/// # async fn run(client: anthropic::Client, request: anthropic::types::MessagesRequest) -> Result<(), anthropic::AnthropicError> {
+/// use anthropic::tool_loop::{run_tool_loop_streaming, ToolLoopConfig, ToolLoopEvent, ToolOutput};
+///
+/// let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+/// let ui = tokio::spawn(async move {
+///     while let Some(event) = received.recv().await {
+///         if let ToolLoopEvent::TextDelta { text, .. } = event {
+///             print!("{text}");
+///         }
+///     }
+/// });
+/// let outcome = run_tool_loop_streaming(
+///     &client,
+///     request,
+///     |_name, _input| async { Ok(ToolOutput::ok("22C and sunny")) },
+///     ToolLoopConfig::default(),
+///     move |event| {
+///         let _ = events.send(event);
+///     },
+/// )
+/// .await?;
+/// # ui.await.ok();
+/// # Ok(())
+/// # }
+/// ```
+pub async fn run_tool_loop_streaming<F, Fut, E>(
+    client: &Client,
+    request: MessagesRequest,
+    executor: F,
+    config: ToolLoopConfig,
+    on_event: E,
+) -> Result<ToolLoopOutcome, AnthropicError>
+where
+    F: FnMut(String, serde_json::Value) -> Fut,
+    Fut: Future<Output = Result<ToolOutput, AnthropicError>>,
+    E: Fn(ToolLoopEvent),
+{
+    let on_event = &on_event;
+    let mut model_calls = 0;
+    let send = |request| {
+        let iteration = model_calls;
+        model_calls += 1;
+        async move {
+            let mut stream = client.messages_stream(request).await?;
+            let mut accumulator = StreamAccumulator::new();
+            while let Some(event) = stream.next().await {
+                let event = event?;
+                if let Some(event) = ToolLoopEvent::from_stream_event(iteration, &event) {
+                    on_event(event);
+                }
+                accumulator.push(event)?;
+            }
+            accumulator.finish()
+        }
+    };
+    drive(request, executor, config, send, on_event).await
+}
+
+/// The loop shared by [`run_tool_loop`] and [`run_tool_loop_streaming`];
+/// `send` performs one model request.
+async fn drive<F, Fut, S, SFut, E>(
     mut request: MessagesRequest,
-    mut executor: F,
+    executor: F,
     config: ToolLoopConfig,
-) -> Result<MessagesResponse, AnthropicError>
+    mut send: S,
+    on_event: &E,
+) -> Result<ToolLoopOutcome, AnthropicError>
 where
     F: FnMut(String, serde_json::Value) -> Fut,
     Fut: Future<Output = Result<ToolOutput, AnthropicError>>,
+    S: FnMut(MessagesRequest) -> SFut,
+    SFut: Future<Output = Result<MessagesResponse, AnthropicError>>,
+    E: Fn(ToolLoopEvent),
 {
     if config.max_iterations == 0 {
         return Err(AnthropicError::InvalidRequest("tool loop max_iterations must be non-zero".into()));
     }
+    if config.max_parallel_tools == 0 {
+        return Err(AnthropicError::InvalidRequest("tool loop max_parallel_tools must be non-zero".into()));
+    }
+    request.validate_thinking()?;
+    // Calls are started from inside the buffered stream, several of which
+    // may hold a handle to the executor at once; the lock is only taken
+    // while the executor builds its future.
+    let executor = std::sync::Mutex::new(executor);
+    let parallelism = match &request.tool_choice {
+        Some(choice) if choice.parallel_tool_use_disabled() => 1,
+        _ => config.max_parallel_tools,
+    };
+    let max_tokens_cap = config.max_tokens_cap.max(request.max_tokens.saturating_mul(2));
+
+    #[cfg(feature = "jsonschema")]
+    let validators = config.validate_inputs.then(|| InputValidators::new(&request));
+    #[cfg_attr(not(feature = "jsonschema"), allow(unused_mut))]
+    let mut validation_failures = 0;
+    let started = Instant::now();
+    let deadline = config.deadline.map(|deadline| started + deadline);
+    let mut iterations: Vec<ToolLoopIteration> = Vec::new();
+    let mut usage = Usage::default();
+    let mut tool_calls: BTreeMap<String, usize> = BTreeMap::new();
+
+    let hooks = config.hooks.as_deref();
 
-    for _ in 0..config.max_iterations {
-        let response = client.messages(request.clone()).await?;
+    let token = config.cancellation.as_ref();
+
+    let stop_reason = loop {
+        if token.is_some_and(CancellationToken::is_cancelled) {
+            if iterations.is_empty() {
+                return Err(AnthropicError::Cancelled);
+            }
+            break ToolLoopStopReason::Cancelled;
+        }
+        if !iterations.is_empty() {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break ToolLoopStopReason::DeadlineExceeded;
+            }
+            if config.budget.is_exhausted(&usage) {
+                break ToolLoopStopReason::BudgetExceeded;
+            }
+        }
+        if let Some(hooks) = hooks {
+            hooks.before_model_call(&request).await;
+        }
+        let model_started = Instant::now();
+        let response = tokio::select! {
+            biased;
+            _ = cancelled(token) => {
+                if iterations.is_empty() {
+                    return Err(AnthropicError::Cancelled);
+                }
+                break ToolLoopStopReason::Cancelled;
+            }
+            _ = deadline_passed(deadline) => {
+                if iterations.is_empty() {
+                    return Err(AnthropicError::DeadlineExceeded);
+                }
+                break ToolLoopStopReason::DeadlineExceeded;
+            }
+            response = send(request.clone()) => response?,
+        };
+        let model_duration = model_started.elapsed();
+        usage += &response.usage;
 
-        if !response.has_tool_use() {
-            return Ok(response);
+        // Keep using the same code execution container on follow-up turns so
+        // files and interpreter state survive between iterations.
+        if let Some(container) = &response.container {
+            request.container = Some(container.id.clone());
         }
 
-        // Collect tool calls BEFORE mutating the transcript so we can run
-        // every tool even if one of them errors.
-        let mut pending: Vec<(String, String, serde_json::Value)> = Vec::new();
-        for block in &response.content {
-            if let ContentBlock::ToolUse { id, name, input, .. } = block {
-                pending.push((id.clone(), name.clone(), input.clone()));
+        // A tool call cut off by `max_tokens` has incomplete input: drop the
+        // turn and ask again with a larger budget.
+        if response.stop_reason == Some(StopReason::MaxTokens) && response.has_tool_use() {
+            record_iteration(
+                hooks,
+                &mut iterations,
+                ToolLoopIteration { response, model_duration, tools_duration: Duration::ZERO },
+            )
+            .await;
+            if request.max_tokens >= max_tokens_cap {
+                break ToolLoopStopReason::MaxTokens;
             }
+            request.max_tokens = request.max_tokens.saturating_mul(2).min(max_tokens_cap);
+            if iterations.len() == config.max_iterations {
+                break ToolLoopStopReason::MaxIterations;
+            }
+            continue;
         }
 
         // Append the assistant turn to the transcript so the next request
         // sends the full history back to Claude.
         request.messages.push(Message::new(Role::Assistant, response.content.clone()));
 
-        let mut tool_results: Vec<ContentBlock> = Vec::with_capacity(pending.len());
-        for (id, name, input) in pending {
-            let output = executor(name, input).await?;
-            let block = if output.is_error {
-                ContentBlock::tool_result_error(id, output.content)
-            } else {
-                ContentBlock::tool_result_text(id, output.content)
+        match response.stop_reason {
+            // A long-running server tool paused the turn; sending the
+            // transcript back as-is lets the model continue it.
+            Some(StopReason::PauseTurn) => {
+                record_iteration(
+                    hooks,
+                    &mut iterations,
+                    ToolLoopIteration { response, model_duration, tools_duration: Duration::ZERO },
+                )
+                .await;
+                if iterations.len() == config.max_iterations {
+                    break ToolLoopStopReason::MaxIterations;
+                }
+                continue;
+            }
+            Some(StopReason::Refusal) => {
+                record_iteration(
+                    hooks,
+                    &mut iterations,
+                    ToolLoopIteration { response, model_duration, tools_duration: Duration::ZERO },
+                )
+                .await;
+                break ToolLoopStopReason::Refused;
+            }
+            _ if !response.has_tool_use() => {
+                record_iteration(
+                    hooks,
+                    &mut iterations,
+                    ToolLoopIteration { response, model_duration, tools_duration: Duration::ZERO },
+                )
+                .await;
+                break ToolLoopStopReason::Finished;
+            }
+            _ => {}
+        }
+
+        // Decide on every tool call before running any of them.
+        // `server_tool_use` and `mcp_tool_use` blocks are deliberately
+        // skipped: the API has already executed them.
+        let mut ids = Vec::new();
+        let mut calls = Vec::new();
+        let mut aborted = false;
+        for (id, name, input) in response.tool_uses() {
+            *tool_calls.entry(name.to_string()).or_default() += 1;
+            ids.push(id.to_string());
+            #[cfg(feature = "jsonschema")]
+            if let Some(message) = validators.as_ref().and_then(|v| v.check(name, input)) {
+                validation_failures += 1;
+                calls.push(PendingCall::Answered(ToolOutput::error(message)));
+                continue;
+            }
+            let decision = match hooks {
+                Some(hooks) => hooks.before_tool(name, input).await,
+                None => ToolDecision::Approve,
             };
-            tool_results.push(block);
+            calls.push(match decision {
+                ToolDecision::Approve => PendingCall::Run(name.to_string(), input.clone()),
+                ToolDecision::Modify(input) => PendingCall::Run(name.to_string(), input),
+                ToolDecision::Deny(reason) => {
+                    PendingCall::Answered(ToolOutput::error(format!("tool call denied: {reason}")))
+                }
+                ToolDecision::Abort => {
+                    aborted = true;
+                    break;
+                }
+            });
+        }
+        if aborted {
+            record_iteration(
+                hooks,
+                &mut iterations,
+                ToolLoopIteration { response, model_duration, tools_duration: Duration::ZERO },
+            )
+            .await;
+            break ToolLoopStopReason::Aborted;
+        }
+
+        // `buffered` starts at most `parallelism` calls at a time and yields
+        // their outputs in call order.
+        let tools_started = Instant::now();
+        // The futures are built up front so no borrowing closure is held
+        // across the await, which would make the loop's future non-`Send`.
+        // They only call the executor once polled, so the limits and the
+        // cancellation check apply to the executor call itself.
+        let executor = &executor;
+        let pending: Vec<_> = ids
+            .iter()
+            .zip(&calls)
+            .map(|(id, call)| match call {
+                PendingCall::Run(name, input) => Either::Left(run_call(
+                    id,
+                    name,
+                    input,
+                    move || (executor.lock().expect("tool executor lock poisoned"))(name.clone(), input.clone()),
+                    ToolLimits { timeout: config.tool_timeout, deadline, token, on_cancel: config.on_cancel },
+                    on_event,
+                )),
+                PendingCall::Answered(output) => Either::Right(future::ready(Ok(output.clone()))),
+            })
+            .collect();
+        let mut outputs: Vec<ToolOutput> = stream::iter(pending).buffered(parallelism).try_collect().await?;
+        if let Some(hooks) = hooks {
+            for (call, output) in calls.iter().zip(&mut outputs) {
+                if let PendingCall::Run(name, input) = call {
+                    hooks.after_tool(name, input, output).await;
+                }
+            }
         }
+        let tools_duration = tools_started.elapsed();
 
+        let tool_results: Vec<ContentBlock> =
+            ids.into_iter().zip(outputs).map(|(id, output)| output.into_tool_result(id)).collect();
         request.messages.push(Message::new(Role::User, tool_results));
+        record_iteration(hooks, &mut iterations, ToolLoopIteration { response, model_duration, tools_duration }).await;
+
+        if iterations.len() == config.max_iterations {
+            break ToolLoopStopReason::MaxIterations;
+        }
+    };
+
+    Ok(ToolLoopOutcome {
+        response: iterations.last().expect("the loop runs at least once").response.clone(),
+        stop_reason,
+        messages: request.messages,
+        iterations,
+        usage,
+        tool_calls,
+        validation_failures,
+        duration: started.elapsed(),
+    })
+}
+
+/// Live progress reported by [`run_tool_loop_streaming`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolLoopEvent {
+    /// A model response started streaming. `iteration` is its zero-based
+    /// index in [`ToolLoopOutcome::iterations`].
+    MessageStarted { iteration: usize },
+    /// Text appended to the content block at `index`.
+    TextDelta { iteration: usize, index: usize, text: String },
+    /// Thinking text appended to the content block at `index`.
+    ThinkingDelta { iteration: usize, index: usize, thinking: String },
+    /// A chunk of partial JSON input for the tool-use block at `index`.
+    ToolInputDelta { iteration: usize, index: usize, partial_json: String },
+    /// The executor is about to run a tool call (after validation and
+    /// [`ToolLoopHooks::before_tool`]).
+    ToolStarted { id: String, name: String, input: serde_json::Value },
+    /// A tool call completed, with the output returned by the executor
+    /// (before [`ToolLoopHooks::after_tool`]). With parallel execution,
+    /// calls finish in completion order.
+    ToolFinished { id: String, name: String, output: ToolOutput },
+}
+
+impl ToolLoopEvent {
+    fn from_stream_event(iteration: usize, event: &MessagesStreamEvent) -> Option<Self> {
+        let (index, delta) = match event {
+            MessagesStreamEvent::MessageStart { .. } => return Some(Self::MessageStarted { iteration }),
+            MessagesStreamEvent::ContentBlockDelta { index, delta } => (*index, delta),
+            _ => return None,
+        };
+        match delta {
+            ContentBlockDelta::TextDelta { text } => Some(Self::TextDelta { iteration, index, text: text.clone() }),
+            ContentBlockDelta::ThinkingDelta { thinking } => {
+                Some(Self::ThinkingDelta { iteration, index, thinking: thinking.clone() })
+            }
+            ContentBlockDelta::InputJsonDelta { partial_json } => {
+                Some(Self::ToolInputDelta { iteration, index, partial_json: partial_json.clone() })
+            }
+            ContentBlockDelta::SignatureDelta { .. } | ContentBlockDelta::CitationsDelta { .. } => None,
+        }
+    }
+}
+
+/// A tool call after validation and approval.
+enum PendingCall {
+    Run(String, serde_json::Value),
+    /// Answered without running the executor (invalid input or denied).
+    Answered(ToolOutput),
+}
+
+/// Limits that apply to every tool call of the loop.
+struct ToolLimits<'a> {
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    token: Option<&'a CancellationToken>,
+    on_cancel: ToolCancelPolicy,
+}
+
+/// Run one executor call under the per-tool timeout, loop deadline, and
+/// cancellation policy, reporting its start and finish to `on_event`. The
+/// executor is only invoked (through `start`) once the call is first polled,
+/// and not at all if the loop was cancelled or its deadline passed by then.
+async fn run_call<Start, Fut, E>(
+    id: &str,
+    name: &str,
+    input: &serde_json::Value,
+    start: Start,
+    limits: ToolLimits<'_>,
+    on_event: &E,
+) -> Result<ToolOutput, AnthropicError>
+where
+    Start: FnOnce() -> Fut,
+    Fut: Future<Output = Result<ToolOutput, AnthropicError>>,
+    E: Fn(ToolLoopEvent),
+{
+    if limits.token.is_some_and(CancellationToken::is_cancelled) {
+        return Ok(ToolOutput::error(format!("tool `{name}` was not run: the tool loop was cancelled")));
+    }
+    if limits.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        return Ok(ToolOutput::error(format!("tool `{name}` was not run: the tool loop deadline passed")));
+    }
+    on_event(ToolLoopEvent::ToolStarted { id: id.into(), name: name.into(), input: input.clone() });
+    let call = start();
+    let call = async {
+        match time_limit(name, limits.timeout, limits.deadline) {
+            Some((limit, message)) => {
+                tokio::time::timeout(limit, call).await.unwrap_or_else(|_elapsed| Ok(ToolOutput::error(message)))
+            }
+            None => call.await,
+        }
+    };
+    let output = match limits.on_cancel {
+        ToolCancelPolicy::FinishRunning => call.await?,
+        ToolCancelPolicy::AbortRunning => tokio::select! {
+            biased;
+            _ = cancelled(limits.token) => ToolOutput::error(format!("tool `{name}` was aborted: the tool loop was cancelled")),
+            output = call => output?,
+        },
+    };
+    on_event(ToolLoopEvent::ToolFinished { id: id.into(), name: name.into(), output: output.clone() });
+    Ok(output)
+}
+
+/// How long a tool call may run and the error reported when it takes longer:
+/// the per-tool timeout or the time left until the loop deadline, whichever
+/// is shorter.
+fn time_limit(name: &str, tool_timeout: Option<Duration>, deadline: Option<Instant>) -> Option<(Duration, String)> {
+    let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+    match (tool_timeout, remaining) {
+        (Some(timeout), remaining) if remaining.is_none_or(|remaining| timeout <= remaining) => {
+            Some((timeout, format!("tool `{name}` timed out after {timeout:?}")))
+        }
+        (_, Some(remaining)) => {
+            Some((remaining, format!("tool `{name}` did not finish before the tool loop deadline")))
+        }
+        _ => None,
+    }
+}
+
+/// Resolves once `token` is cancelled; never resolves without a token.
+async fn cancelled(token: Option<&CancellationToken>) {
+    match token {
+        Some(token) => token.cancelled().await,
+        None => future::pending().await,
     }
+}
+
+/// Resolves once `deadline` has passed; never resolves without one.
+async fn deadline_passed(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => future::pending().await,
+    }
+}
 
-    Err(AnthropicError::InvalidRequest(format!(
-        "tool loop exceeded {} iterations without reaching a final response",
-        config.max_iterations
-    )))
+async fn record_iteration(
+    hooks: Option<&dyn ToolLoopHooks>,
+    iterations: &mut Vec<ToolLoopIteration>,
+    iteration: ToolLoopIteration,
+) {
+    iterations.push(iteration);
+    if let Some(hooks) = hooks {
+        hooks.on_iteration(iterations.len() - 1, &iterations[iterations.len() - 1]).await;
+    }
 }
 
 #[cfg(test)]
@@ -124,18 +1031,68 @@ mod tests {
     #[test]
     fn tool_output_helpers_set_is_error_correctly() {
         let ok = ToolOutput::ok("done");
-        assert_eq!(ok.content, "done");
+        assert_eq!(ok.content, ToolResultContent::Text("done".into()));
         assert!(!ok.is_error);
 
         let err = ToolOutput::error("nope");
-        assert_eq!(err.content, "nope");
+        assert_eq!(err.text(), "nope");
         assert!(err.is_error);
+
+        let blocks = ToolOutput::blocks(vec![ContentBlock::text("a"), ContentBlock::image_base64("image/png", "x")]);
+        assert_eq!(blocks.text(), "a");
+        assert_eq!(
+            blocks.into_tool_result("tu_1".into()),
+            ContentBlock::tool_result_blocks(
+                "tu_1",
+                vec![ContentBlock::text("a"), ContentBlock::image_base64("image/png", "x")]
+            )
+        );
+    }
+
+    #[test]
+    fn tool_output_rich_constructors_become_blocks() {
+        assert_eq!(
+            ToolOutput::json(serde_json::json!({"temp": 21, "unit": "C"})),
+            ToolOutput::ok(r#"{"temp":21,"unit":"C"}"#)
+        );
+        assert_eq!(
+            ToolOutput::image_base64("image/png", "iVBORw0K").into_tool_result("tu_1".into()),
+            ContentBlock::tool_result_blocks("tu_1", vec![ContentBlock::image_base64("image/png", "iVBORw0K")])
+        );
+        assert_eq!(
+            ToolOutput::document_text("notes").content,
+            ToolResultContent::Blocks(vec![ContentBlock::document_text("notes")])
+        );
+        assert_eq!(
+            ToolOutput::pdf_from_bytes(b"%PDF-1.4\n%%EOF").unwrap().content,
+            ToolResultContent::Blocks(vec![ContentBlock::document_base64("application/pdf", "JVBERi0xLjQKJSVFT0Y=")])
+        );
+        assert!(ToolOutput::image_from_bytes(b"not an image").is_err());
     }
 
     #[test]
     fn tool_loop_config_default_and_custom() {
         assert_eq!(ToolLoopConfig::default().max_iterations, 8);
         assert_eq!(ToolLoopConfig::new(3).max_iterations, 3);
+        assert_eq!(ToolLoopConfig::default().max_parallel_tools, 1);
+        assert_eq!(ToolLoopConfig::default().max_parallel_tools(4).max_parallel_tools, 4);
+    }
+
+    #[test]
+    fn tool_loop_budget_checks_every_limit() {
+        let usage =
+            Usage { input_tokens: 100, output_tokens: 50, cache_read_input_tokens: Some(900), ..Default::default() };
+        assert!(!ToolLoopBudget::default().is_exhausted(&usage));
+        assert!(ToolLoopBudget::default().max_input_tokens(1_000).is_exhausted(&usage));
+        assert!(!ToolLoopBudget::default().max_input_tokens(1_001).is_exhausted(&usage));
+        assert!(ToolLoopBudget::default().max_output_tokens(50).is_exhausted(&usage));
+
+        // 100 * $10 + 900 * $1 + 50 * $20 per million tokens = $0.0029.
+        let pricing = ModelPricing::new(10.0, 20.0);
+        assert!(ToolLoopBudget::default().max_cost(0.0029, pricing).is_exhausted(&usage));
+        assert!(!ToolLoopBudget::default().max_cost(0.003, pricing).is_exhausted(&usage));
+        let no_pricing = ToolLoopBudget { max_cost_usd: Some(0.0), ..Default::default() };
+        assert!(!no_pricing.is_exhausted(&usage));
     }
 
     #[tokio::test]
@@ -143,9 +1100,43 @@ mod tests {
         // Build a throwaway client that we expect to never be called.
         let client = Client::builder().api_key("x").api_base("http://127.0.0.1:1").build().unwrap();
         let request = crate::types::MessagesRequestBuilder::new("m", vec![Message::user("hi")], 10).build().unwrap();
-        let err = run_tool_loop(&client, request, |_n, _i| async { Ok(ToolOutput::ok("x")) }, ToolLoopConfig::new(0))
-            .await
-            .unwrap_err();
+        // Spawning also checks that the loop's future is `Send`.
+        let err = tokio::spawn(async move {
+            run_tool_loop(&client, request, |_n, _i| async { Ok(ToolOutput::ok("x")) }, ToolLoopConfig::new(0)).await
+        })
+        .await
+        .unwrap()
+        .unwrap_err();
         assert!(matches!(err, AnthropicError::InvalidRequest(_)));
     }
+
+    #[cfg(feature = "jsonschema")]
+    #[test]
+    fn input_validators_describe_each_violation() {
+        use serde_json::json;
+
+        let request = crate::types::MessagesRequestBuilder::new("m", vec![Message::user("hi")], 10)
+            .tool(crate::types::Tool::new(
+                "get_weather",
+                "Weather",
+                json!({
+                    "type": "object",
+                    "properties": {"city": {"type": "string"}, "unit": {"enum": ["c", "f"]}},
+                    "required": ["city"]
+                }),
+            ))
+            .build()
+            .unwrap();
+        let validators = InputValidators::new(&request);
+
+        assert_eq!(validators.check("get_weather", &json!({"city": "Paris", "unit": "c"})), None);
+        assert_eq!(validators.check("other_tool", &json!(42)), None);
+        let message = validators.check("get_weather", &json!({"unit": "k"})).unwrap();
+        assert_eq!(
+            message,
+            "input for tool `get_weather` does not match its input_schema:\n\
+             - at `/unit`: \"k\" is not one of [\"c\",\"f\"]\n\
+             - \"city\" is a required property"
+        );
+    }
 }
@@ -0,0 +1,250 @@
+//! Name-based dispatch of tool calls to async handlers.
+//!
+//! A [`ToolRegistry`] pairs each [`Tool`] definition with the handler that
+//! runs it. The same registry produces the request's `tools` list
+//! ([`ToolRegistry::tools`]) and serves as the [`run_tool_loop`](crate::run_tool_loop)
+//! executor ([`ToolRegistry::executor`]), so the two can never drift apart.
+//!
+//! Handlers receive an `Arc` of the registry's context (database pools, HTTP
+//! clients, configuration, ...) along with the raw input:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use anthropic::tool_registry::ToolRegistry;
+//! use anthropic::types::Tool;
+//! use anthropic::ToolOutput;
+//! use serde_json::json;
+//!
+//! struct AppState {
+//!     greeting: String,
+//! }
+//!
+//! let registry = ToolRegistry::with_context(AppState { greeting: "hello".into() }).register(
+//!     Tool::new("greet", "Greet someone", json!({"type": "object", "properties": {"name": {"type": "string"}}})),
+//!     |state: Arc<AppState>, input| async move {
+//!         Ok(ToolOutput::ok(format!("{} {}", state.greeting, input["name"].as_str().unwrap_or("there"))))
+//!     },
+//! );
+//! ```
+//!
+//! Calls to tools that are not registered produce a [`ToolOutput::error`]
+//! so the model can recover, rather than aborting the loop.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+
+use crate::error::AnthropicError;
+use crate::tool_loop::ToolOutput;
+use crate::types::Tool;
+
+type Handler<C> = Arc<dyn Fn(Arc<C>, Value) -> BoxFuture<'static, Result<ToolOutput, AnthropicError>> + Send + Sync>;
+
+/// Registry of tools and the async handlers that execute them.
+///
+/// Cloning is cheap: handlers and the context are reference-counted.
+pub struct ToolRegistry<C = ()> {
+    context: Arc<C>,
+    entries: Vec<(Tool, Handler<C>)>,
+}
+
+impl<C> Clone for ToolRegistry<C> {
+    fn clone(&self) -> Self {
+        Self { context: Arc::clone(&self.context), entries: self.entries.clone() }
+    }
+}
+
+impl<C> fmt::Debug for ToolRegistry<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.entries.iter().map(|(tool, _)| tool.name.as_str()).collect();
+        f.debug_struct("ToolRegistry").field("tools", &names).finish_non_exhaustive()
+    }
+}
+
+impl Default for ToolRegistry<()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolRegistry<()> {
+    /// Empty registry whose handlers need no shared state.
+    pub fn new() -> Self {
+        Self::with_context(())
+    }
+}
+
+impl<C: Send + Sync + 'static> ToolRegistry<C> {
+    /// Empty registry sharing `context` with every handler.
+    pub fn with_context(context: C) -> Self {
+        Self::with_shared_context(Arc::new(context))
+    }
+
+    /// Like [`ToolRegistry::with_context`] for a context that is already
+    /// shared elsewhere.
+    pub fn with_shared_context(context: Arc<C>) -> Self {
+        Self { context, entries: Vec::new() }
+    }
+
+    /// The context passed to handlers.
+    pub fn context(&self) -> &Arc<C> {
+        &self.context
+    }
+
+    /// Register `tool` with the handler that runs it. Registering a name
+    /// twice replaces the earlier definition and handler.
+    pub fn register<F, Fut>(mut self, tool: Tool, handler: F) -> Self
+    where
+        F: Fn(Arc<C>, Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ToolOutput, AnthropicError>> + Send + 'static,
+    {
+        self.insert(tool, Arc::new(move |context, input| Box::pin(handler(context, input))));
+        self
+    }
+
+    /// Register a [`TypedTool`](crate::typed_tool::TypedTool); its definition
+    /// and input parsing come from the trait.
+    #[cfg(feature = "schemars")]
+    pub fn register_typed<T>(mut self, tool: T) -> Self
+    where
+        T: crate::typed_tool::TypedTool + 'static,
+    {
+        let definition = tool.definition();
+        let tool = Arc::new(tool);
+        self.insert(
+            definition,
+            Arc::new(move |_context, input| {
+                let tool = Arc::clone(&tool);
+                Box::pin(async move { tool.invoke(input).await })
+            }),
+        );
+        self
+    }
+
+    fn insert(&mut self, tool: Tool, handler: Handler<C>) {
+        match self.entries.iter_mut().find(|(existing, _)| existing.name == tool.name) {
+            Some(entry) => *entry = (tool, handler),
+            None => self.entries.push((tool, handler)),
+        }
+    }
+
+    /// Tool definitions in registration order, ready for
+    /// [`MessagesRequestBuilder::tools`](crate::types::MessagesRequestBuilder::tools).
+    pub fn tools(&self) -> Vec<Tool> {
+        self.entries.iter().map(|(tool, _)| tool.clone()).collect()
+    }
+
+    /// Whether a tool named `name` is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|(tool, _)| tool.name == name)
+    }
+
+    /// Run the handler registered for `name`. Unknown tools produce a
+    /// [`ToolOutput::error`] listing the available ones.
+    pub fn call(&self, name: &str, input: Value) -> BoxFuture<'static, Result<ToolOutput, AnthropicError>> {
+        match self.entries.iter().find(|(tool, _)| tool.name == name) {
+            Some((_, handler)) => handler(Arc::clone(&self.context), input),
+            None => {
+                let available: Vec<&str> = self.entries.iter().map(|(tool, _)| tool.name.as_str()).collect();
+                let message = format!("unknown tool {name}; available tools: {}", available.join(", "));
+                Box::pin(async move { Ok(ToolOutput::error(message)) })
+            }
+        }
+    }
+
+    /// Executor for [`run_tool_loop`](crate::run_tool_loop) that dispatches
+    /// each call through this registry.
+    pub fn executor(&self) -> impl FnMut(String, Value) -> BoxFuture<'static, Result<ToolOutput, AnthropicError>> {
+        let registry = self.clone();
+        move |name, input| registry.call(&name, input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn tool(name: &str) -> Tool {
+        Tool::new(name, format!("{name} tool"), json!({"type": "object"}))
+    }
+
+    #[tokio::test]
+    async fn dispatches_by_name_with_shared_context() {
+        let registry = ToolRegistry::with_context(AtomicUsize::new(0))
+            .register(tool("count"), |counter: Arc<AtomicUsize>, _input| async move {
+                Ok(ToolOutput::ok((counter.fetch_add(1, Ordering::SeqCst) + 1).to_string()))
+            })
+            .register(tool("echo"), |_, input| async move { Ok(ToolOutput::ok(input["text"].to_string())) });
+
+        assert_eq!(registry.tools().iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), vec!["count", "echo"]);
+        assert!(registry.contains("echo"));
+
+        let mut executor = registry.executor();
+        assert_eq!(executor("count".into(), json!({})).await.unwrap(), ToolOutput::ok("1"));
+        assert_eq!(executor("count".into(), json!({})).await.unwrap(), ToolOutput::ok("2"));
+        assert_eq!(executor("echo".into(), json!({"text": "hi"})).await.unwrap(), ToolOutput::ok("\"hi\""));
+        assert_eq!(registry.context().load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn unknown_tools_become_tool_errors() {
+        let registry = ToolRegistry::new()
+            .register(tool("a"), |_, _| async { Ok(ToolOutput::ok("a")) })
+            .register(tool("b"), |_, _| async { Ok(ToolOutput::ok("b")) });
+        let output = registry.call("c", json!({})).await.unwrap();
+        assert_eq!(output, ToolOutput::error("unknown tool c; available tools: a, b"));
+    }
+
+    #[tokio::test]
+    async fn re_registering_replaces_the_handler_in_place() {
+        let registry = ToolRegistry::new()
+            .register(tool("a"), |_, _| async { Ok(ToolOutput::ok("old")) })
+            .register(tool("b"), |_, _| async { Ok(ToolOutput::ok("b")) })
+            .register(Tool::new("a", "new a", json!({"type": "object"})), |_, _| async { Ok(ToolOutput::ok("new")) });
+        let tools = registry.tools();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0].description, "new a");
+        assert_eq!(registry.call("a", json!({})).await.unwrap(), ToolOutput::ok("new"));
+        assert_eq!(format!("{registry:?}"), r#"ToolRegistry { tools: ["a", "b"], .. }"#);
+    }
+
+    #[cfg(feature = "schemars")]
+    #[tokio::test]
+    async fn typed_tools_parse_their_input() {
+        use crate::typed_tool::TypedTool;
+
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        struct Input {
+            city: String,
+        }
+
+        struct Weather;
+
+        impl TypedTool for Weather {
+            type Input = Input;
+
+            fn name(&self) -> &str {
+                "weather"
+            }
+
+            fn description(&self) -> &str {
+                "Weather for a city"
+            }
+
+            async fn call(&self, input: Input) -> Result<ToolOutput, AnthropicError> {
+                Ok(ToolOutput::ok(format!("sunny in {}", input.city)))
+            }
+        }
+
+        let registry = ToolRegistry::new().register_typed(Weather);
+        assert_eq!(registry.tools()[0].input_schema["required"], json!(["city"]));
+        assert_eq!(registry.call("weather", json!({"city": "Oslo"})).await.unwrap(), ToolOutput::ok("sunny in Oslo"));
+        assert!(registry.call("weather", json!({})).await.unwrap().is_error);
+    }
+}
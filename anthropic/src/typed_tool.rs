@@ -0,0 +1,250 @@
+//! Strongly typed tools with generated JSON schemas (`schemars` feature).
+//!
+//! Implement [`TypedTool`] for a handler whose input is a plain Rust type
+//! deriving `Deserialize` and `JsonSchema`. [`TypedTool::definition`]
+//! produces the [`Tool`] (with `input_schema` generated from the input type)
+//! and [`TypedTool::invoke`] parses the raw `tool_use` input before calling
+//! the handler. Inputs that fail to deserialize are reported back to the
+//! model as `tool_result` errors so it can correct itself, instead of
+//! aborting the loop.
+//!
+//! ```no_run
+//! use anthropic::typed_tool::{typed_executor, TypedTool};
+//! use anthropic::{AnthropicError, ToolOutput};
+//! use schemars::JsonSchema;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, JsonSchema)]
+//! struct WeatherInput {
+//!     /// City name, e.g. "Paris".
+//!     city: String,
+//! }
+//!
+//! struct GetWeather;
+//!
+//! impl TypedTool for GetWeather {
+//!     type Input = WeatherInput;
+//!
+//!     fn name(&self) -> &str {
+//!         "get_weather"
+//!     }
+//!
+//!     fn description(&self) -> &str {
+//!         "Fetch the current weather for a city"
+//!     }
+//!
+//!     async fn call(&self, input: WeatherInput) -> Result<ToolOutput, AnthropicError> {
+//!         Ok(ToolOutput::ok(format!("sunny in {}", input.city)))
+//!     }
+//! }
+//!
+//! # async fn run(client: anthropic::Client, builder: anthropic::types::MessagesRequestBuilder) -> Result<(), AnthropicError> {
+//! let request = builder.tool(GetWeather.definition()).build()?;
+//! let outcome =
+//!     anthropic::run_tool_loop(&client, request, typed_executor(GetWeather), Default::default()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::AnthropicError;
+use crate::tool_loop::ToolOutput;
+use crate::types::Tool;
+
+/// A tool whose input is deserialized into [`TypedTool::Input`] before the
+/// handler runs.
+pub trait TypedTool: Send + Sync {
+    /// Input type; its JSON schema becomes the tool's `input_schema`. Doc
+    /// comments on fields become property descriptions.
+    type Input: DeserializeOwned + JsonSchema + Send;
+
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// Run the tool. Return `Ok(ToolOutput::error(..))` for failures the
+    /// model should see, and `Err(..)` only to abort the loop.
+    fn call(&self, input: Self::Input) -> impl Future<Output = Result<ToolOutput, AnthropicError>> + Send;
+
+    /// The tool definition to send with the request.
+    fn definition(&self) -> Tool {
+        Tool::new(self.name(), self.description(), input_schema::<Self::Input>())
+    }
+
+    /// Parse a raw `tool_use` input and call the tool. Inputs that do not
+    /// match [`TypedTool::Input`] produce a [`ToolOutput::error`] describing
+    /// the problem.
+    fn invoke(&self, input: Value) -> impl Future<Output = Result<ToolOutput, AnthropicError>> + Send {
+        async move {
+            match serde_json::from_value::<Self::Input>(input) {
+                Ok(input) => self.call(input).await,
+                Err(err) => Ok(ToolOutput::error(format!("invalid input for tool `{}`: {err}", self.name()))),
+            }
+        }
+    }
+}
+
+/// Conversion from a tool function's return value into a [`ToolOutput`].
+///
+/// Used by the `#[tool]` attribute macro (`macros` feature). `Err` values
+/// become [`ToolOutput::error`] results carrying the error's `Display`
+/// text, so the model sees the failure instead of the loop aborting.
+pub trait IntoToolOutput {
+    fn into_tool_output(self) -> ToolOutput;
+}
+
+impl IntoToolOutput for ToolOutput {
+    fn into_tool_output(self) -> ToolOutput {
+        self
+    }
+}
+
+impl IntoToolOutput for String {
+    fn into_tool_output(self) -> ToolOutput {
+        ToolOutput::ok(self)
+    }
+}
+
+impl IntoToolOutput for &str {
+    fn into_tool_output(self) -> ToolOutput {
+        ToolOutput::ok(self)
+    }
+}
+
+impl IntoToolOutput for Value {
+    fn into_tool_output(self) -> ToolOutput {
+        ToolOutput::ok(self.to_string())
+    }
+}
+
+impl<T: IntoToolOutput, E: std::fmt::Display> IntoToolOutput for Result<T, E> {
+    fn into_tool_output(self) -> ToolOutput {
+        match self {
+            Ok(value) => value.into_tool_output(),
+            Err(err) => ToolOutput::error(err.to_string()),
+        }
+    }
+}
+
+/// JSON schema of `T` in the shape expected by `input_schema`: subschemas
+/// are inlined and the `$schema` keyword is dropped.
+pub fn input_schema<T: JsonSchema>() -> Value {
+    let generator = SchemaSettings::draft2020_12().with(|settings| settings.inline_subschemas = true).into_generator();
+    let mut schema = generator.into_root_schema_for::<T>().to_value();
+    if let Some(object) = schema.as_object_mut() {
+        object.remove("$schema");
+    }
+    schema
+}
+
+/// Executor for [`run_tool_loop`](crate::run_tool_loop) that dispatches
+/// every call to `tool`. Calls naming any other tool get a
+/// [`ToolOutput::error`].
+pub fn typed_executor<T>(tool: T) -> impl FnMut(String, Value) -> BoxFuture<'static, Result<ToolOutput, AnthropicError>>
+where
+    T: TypedTool + 'static,
+{
+    let tool = Arc::new(tool);
+    move |name, input| {
+        let tool = Arc::clone(&tool);
+        Box::pin(async move {
+            if name != tool.name() {
+                return Ok(ToolOutput::error(format!("unknown tool {name}")));
+            }
+            tool.invoke(input).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Deserialize, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    enum Unit {
+        Celsius,
+        Fahrenheit,
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    struct WeatherInput {
+        /// City name.
+        city: String,
+        unit: Option<Unit>,
+    }
+
+    struct GetWeather;
+
+    impl TypedTool for GetWeather {
+        type Input = WeatherInput;
+
+        fn name(&self) -> &str {
+            "get_weather"
+        }
+
+        fn description(&self) -> &str {
+            "Fetch the weather"
+        }
+
+        async fn call(&self, input: WeatherInput) -> Result<ToolOutput, AnthropicError> {
+            let unit = match input.unit {
+                Some(Unit::Fahrenheit) => "F",
+                _ => "C",
+            };
+            Ok(ToolOutput::ok(format!("{}: 22{unit}", input.city)))
+        }
+    }
+
+    #[test]
+    fn definition_carries_generated_schema() {
+        let tool = GetWeather.definition();
+        assert_eq!(tool.name, "get_weather");
+        assert_eq!(tool.description, "Fetch the weather");
+        let schema = &tool.input_schema;
+        assert_eq!(schema["type"], "object");
+        assert!(schema.get("$schema").is_none());
+        assert_eq!(schema["required"], json!(["city"]));
+        assert_eq!(schema["properties"]["city"]["description"], "City name.");
+        // Subschemas are inlined rather than referenced through `$defs`.
+        assert!(schema.get("$defs").is_none());
+        assert!(schema["properties"]["unit"].to_string().contains("fahrenheit"));
+    }
+
+    #[tokio::test]
+    async fn invoke_parses_input_and_reports_bad_input() {
+        let ok = GetWeather.invoke(json!({"city": "Paris", "unit": "fahrenheit"})).await.unwrap();
+        assert_eq!(ok, ToolOutput::ok("Paris: 22F"));
+
+        let err = GetWeather.invoke(json!({"unit": "kelvin"})).await.unwrap();
+        assert!(err.is_error);
+        assert!(err.text().starts_with("invalid input for tool `get_weather`:"), "{}", err.text());
+    }
+
+    #[test]
+    fn return_values_convert_into_tool_output() {
+        assert_eq!("done".into_tool_output(), ToolOutput::ok("done"));
+        assert_eq!(json!({"temp": 22}).into_tool_output(), ToolOutput::ok(r#"{"temp":22}"#));
+        let failed: Result<String, std::io::Error> = Err(std::io::Error::other("disk full"));
+        assert_eq!(failed.into_tool_output(), ToolOutput::error("disk full"));
+    }
+
+    #[tokio::test]
+    async fn typed_executor_rejects_other_tool_names() {
+        let mut executor = typed_executor(GetWeather);
+        let output = executor("get_time".into(), json!({})).await.unwrap();
+        assert_eq!(output, ToolOutput::error("unknown tool get_time"));
+        let output = executor("get_weather".into(), json!({"city": "Oslo"})).await.unwrap();
+        assert_eq!(output.text(), "Oslo: 22C");
+    }
+}
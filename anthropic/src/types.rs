@@ -1,9 +1,16 @@
 //! Types for Anthropic's Messages API.
 
+use std::fmt;
+use std::path::Path;
+
 use backoff::ExponentialBackoff;
 use serde::{Deserialize, Serialize};
 
+use crate::computer_use::{BashTool, BuiltinTool, ComputerTool, TextEditorTool};
+use crate::documents::encode_pdf;
 use crate::error::AnthropicError;
+use crate::files::FILES_API_BETA;
+use crate::images::encode_image;
 
 /// Per-request retry policy override.
 ///
@@ -150,6 +157,10 @@ impl CacheControl {
 pub enum ContentBlock {
     Text {
         text: String,
+        /// Sources backing this text, present when the model cites a
+        /// document with [`CitationsConfig`] enabled.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        citations: Option<Vec<Citation>>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
         cache_control: Option<CacheControl>,
     },
@@ -169,6 +180,18 @@ pub enum ContentBlock {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         cache_control: Option<CacheControl>,
     },
+    /// Retrieved passage from a search or RAG pipeline, cited with
+    /// [`Citation::SearchResultLocation`]. Accepted in user messages and
+    /// inside [`ToolResultContent::Blocks`]. `content` holds text blocks.
+    SearchResult {
+        source: String,
+        title: String,
+        content: Vec<ContentBlock>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        citations: Option<CitationsConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
     ToolUse {
         id: String,
         name: String,
@@ -192,12 +215,62 @@ pub enum ContentBlock {
     RedactedThinking {
         data: String,
     },
+    /// Call to a server tool (web search, web fetch). Executed by the API,
+    /// never by the client.
+    ServerToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    WebSearchToolResult {
+        tool_use_id: String,
+        content: WebSearchToolResultContent,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    WebFetchToolResult {
+        tool_use_id: String,
+        content: WebFetchToolResultContent,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    CodeExecutionToolResult {
+        tool_use_id: String,
+        content: CodeExecutionToolResultContent,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    /// Call to a tool on a remote MCP server (see [`McpServer`]). Executed by
+    /// the API, never by the client.
+    McpToolUse {
+        id: String,
+        name: String,
+        server_name: String,
+        input: serde_json::Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    McpToolResult {
+        tool_use_id: String,
+        #[serde(default)]
+        is_error: bool,
+        content: ToolResultContent,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    /// Block type this client does not model yet (e.g. a newer server tool
+    /// result), kept as raw JSON so responses still parse and the block is
+    /// sent back unchanged when the turn is replayed.
+    #[serde(untagged)]
+    Other(serde_json::Value),
 }
 
 impl ContentBlock {
     /// Plain text block.
     pub fn text(text: impl Into<String>) -> Self {
-        Self::Text { text: text.into(), cache_control: None }
+        Self::Text { text: text.into(), citations: None, cache_control: None }
     }
 
     /// Image block backed by inline base64 data.
@@ -208,11 +281,36 @@ impl ContentBlock {
         }
     }
 
+    /// Image block from raw image bytes.
+    ///
+    /// The media type is detected from the magic bytes (JPEG, PNG, GIF, or
+    /// WebP) and the data is checked against the API's size and dimension
+    /// limits before being base64-encoded. With the `image` feature, images
+    /// larger than [`RECOMMENDED_LONG_EDGE`](crate::images::RECOMMENDED_LONG_EDGE)
+    /// are downscaled first.
+    pub fn image_from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self, AnthropicError> {
+        let (media_type, data) = encode_image(bytes.as_ref())?;
+        Ok(Self::image_base64(media_type.as_str(), data))
+    }
+
+    /// Image block read from a file on disk; see [`ContentBlock::image_from_bytes`].
+    pub fn image_from_path(path: impl AsRef<Path>) -> Result<Self, AnthropicError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|err| AnthropicError::InvalidRequest(format!("failed to read {}: {err}", path.display())))?;
+        Self::image_from_bytes(bytes)
+    }
+
     /// Image block that references a remote URL.
     pub fn image_url(url: impl Into<String>) -> Self {
         Self::Image { source: ImageSource::Url { url: url.into() }, cache_control: None }
     }
 
+    /// Image block backed by a file uploaded through the Files API.
+    pub fn image_file(file_id: impl Into<String>) -> Self {
+        Self::Image { source: ImageSource::File { file_id: file_id.into() }, cache_control: None }
+    }
+
     /// Document block backed by inline base64-encoded data.
     pub fn document_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
         Self::Document {
@@ -235,6 +333,17 @@ impl ContentBlock {
         }
     }
 
+    /// Document block backed by a file uploaded through the Files API.
+    pub fn document_file(file_id: impl Into<String>) -> Self {
+        Self::Document {
+            source: DocumentSource::File { file_id: file_id.into() },
+            title: None,
+            context: None,
+            citations: None,
+            cache_control: None,
+        }
+    }
+
     /// Document block backed by inline text (useful for text files).
     pub fn document_text(text: impl Into<String>) -> Self {
         Self::Document {
@@ -246,6 +355,54 @@ impl ContentBlock {
         }
     }
 
+    /// Document block with custom content: one text block per chunk. Citations
+    /// on such documents reference whole chunks, which makes this a good fit
+    /// for the output of [`chunk_text`](crate::documents::chunk_text).
+    pub fn document_chunks<I, S>(chunks: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::Document {
+            source: DocumentSource::Content { content: chunks.into_iter().map(Self::text).collect() },
+            title: None,
+            context: None,
+            citations: None,
+            cache_control: None,
+        }
+    }
+
+    /// PDF document block from raw bytes, checked against the API's size and
+    /// page limits before being base64-encoded.
+    pub fn pdf_from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self, AnthropicError> {
+        Ok(Self::document_base64("application/pdf", encode_pdf(bytes.as_ref())?))
+    }
+
+    /// PDF document block read from a file on disk; see [`ContentBlock::pdf_from_bytes`].
+    pub fn pdf_from_path(path: impl AsRef<Path>) -> Result<Self, AnthropicError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|err| AnthropicError::InvalidRequest(format!("failed to read {}: {err}", path.display())))?;
+        Self::pdf_from_bytes(bytes)
+    }
+
+    /// Search result block with one text block per entry of `content`.
+    /// Citations are enabled; turn them off with
+    /// [`with_citations(false)`](ContentBlock::with_citations).
+    pub fn search_result<I, S>(source: impl Into<String>, title: impl Into<String>, content: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::SearchResult {
+            source: source.into(),
+            title: title.into(),
+            content: content.into_iter().map(Self::text).collect(),
+            citations: Some(CitationsConfig { enabled: true }),
+            cache_control: None,
+        }
+    }
+
     /// Tool-use block representing a call requested by the model.
     pub fn tool_use(id: impl Into<String>, name: impl Into<String>, input: serde_json::Value) -> Self {
         Self::ToolUse { id: id.into(), name: name.into(), input, cache_control: None }
@@ -288,18 +445,54 @@ impl ContentBlock {
 
     /// Attach a `cache_control` marker to a block that supports caching.
     ///
-    /// Blocks that do not support caching (thinking / redacted thinking) are
-    /// returned unchanged.
+    /// Blocks that do not support caching (thinking / redacted thinking) and
+    /// [`ContentBlock::Other`] blocks are returned unchanged.
     pub fn with_cache_control(mut self, cache: CacheControl) -> Self {
         match &mut self {
             Self::Text { cache_control, .. }
             | Self::Image { cache_control, .. }
             | Self::Document { cache_control, .. }
+            | Self::SearchResult { cache_control, .. }
             | Self::ToolUse { cache_control, .. }
-            | Self::ToolResult { cache_control, .. } => {
+            | Self::ToolResult { cache_control, .. }
+            | Self::ServerToolUse { cache_control, .. }
+            | Self::WebSearchToolResult { cache_control, .. }
+            | Self::WebFetchToolResult { cache_control, .. }
+            | Self::CodeExecutionToolResult { cache_control, .. }
+            | Self::McpToolUse { cache_control, .. }
+            | Self::McpToolResult { cache_control, .. } => {
                 *cache_control = Some(cache);
             }
-            Self::Thinking { .. } | Self::RedactedThinking { .. } => {}
+            Self::Thinking { .. } | Self::RedactedThinking { .. } | Self::Other(_) => {}
+        }
+        self
+    }
+
+    /// Set the title of a document or search result block. Other blocks are
+    /// returned unchanged.
+    pub fn with_title(mut self, value: impl Into<String>) -> Self {
+        match &mut self {
+            Self::Document { title, .. } => *title = Some(value.into()),
+            Self::SearchResult { title, .. } => *title = value.into(),
+            _ => {}
+        }
+        self
+    }
+
+    /// Set the context of a document block: metadata passed to the model
+    /// but never cited. Other blocks are returned unchanged.
+    pub fn with_context(mut self, value: impl Into<String>) -> Self {
+        if let Self::Document { context, .. } = &mut self {
+            *context = Some(value.into());
+        }
+        self
+    }
+
+    /// Enable or disable citations on a document or search result block.
+    /// Other blocks are returned unchanged.
+    pub fn with_citations(mut self, enabled: bool) -> Self {
+        if let Self::Document { citations, .. } | Self::SearchResult { citations, .. } = &mut self {
+            *citations = Some(CitationsConfig { enabled });
         }
         self
     }
@@ -312,6 +505,14 @@ impl ContentBlock {
         }
     }
 
+    /// Citations attached to a text block. Empty for every other block kind.
+    pub fn citations(&self) -> &[Citation] {
+        match self {
+            Self::Text { citations: Some(citations), .. } => citations,
+            _ => &[],
+        }
+    }
+
     /// Return the tool-use id, name, and input if this block is a [`ContentBlock::ToolUse`].
     pub fn as_tool_use(&self) -> Option<(&str, &str, &serde_json::Value)> {
         match self {
@@ -319,22 +520,89 @@ impl ContentBlock {
             _ => None,
         }
     }
+
+    /// Return the execution result if this block is a successful
+    /// [`ContentBlock::CodeExecutionToolResult`].
+    pub fn as_code_execution_result(&self) -> Option<&CodeExecutionResult> {
+        match self {
+            Self::CodeExecutionToolResult { content: CodeExecutionToolResultContent::Result(result), .. } => {
+                Some(result)
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the id, name, and input if this block is a [`ContentBlock::ServerToolUse`].
+    pub fn as_server_tool_use(&self) -> Option<(&str, &str, &serde_json::Value)> {
+        match self {
+            Self::ServerToolUse { id, name, input, .. } => Some((id.as_str(), name.as_str(), input)),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this block (or a block nested inside it) references
+    /// a file uploaded through the Files API.
+    pub fn references_file(&self) -> bool {
+        match self {
+            Self::Image { source: ImageSource::File { .. }, .. }
+            | Self::Document { source: DocumentSource::File { .. }, .. } => true,
+            Self::Document { source: DocumentSource::Content { content }, .. }
+            | Self::ToolResult { content: ToolResultContent::Blocks(content), .. } => {
+                content.iter().any(Self::references_file)
+            }
+            _ => false,
+        }
+    }
+
+    /// Return the id, server name, tool name, and input if this block is a
+    /// [`ContentBlock::McpToolUse`].
+    pub fn as_mcp_tool_use(&self) -> Option<(&str, &str, &str, &serde_json::Value)> {
+        match self {
+            Self::McpToolUse { id, server_name, name, input, .. } => {
+                Some((id.as_str(), server_name.as_str(), name.as_str(), input))
+            }
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum ImageSource {
-    Base64 { media_type: String, data: String },
-    Url { url: String },
+    Base64 {
+        media_type: String,
+        data: String,
+    },
+    Url {
+        url: String,
+    },
+    /// File uploaded through the Files API.
+    File {
+        file_id: String,
+    },
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum DocumentSource {
-    Base64 { media_type: String, data: String },
-    Text { media_type: String, data: String },
-    Url { url: String },
-    Content { content: Vec<ContentBlock> },
+    Base64 {
+        media_type: String,
+        data: String,
+    },
+    Text {
+        media_type: String,
+        data: String,
+    },
+    Url {
+        url: String,
+    },
+    Content {
+        content: Vec<ContentBlock>,
+    },
+    /// File uploaded through the Files API.
+    File {
+        file_id: String,
+    },
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -342,6 +610,86 @@ pub struct CitationsConfig {
     pub enabled: bool,
 }
 
+/// Location inside a cited source, attached to [`ContentBlock::Text`] blocks.
+///
+/// `document_index` counts every `document` block in the request, in order
+/// of appearance. Character and block ranges are 0-indexed; page ranges are
+/// 1-indexed. All `end_*` bounds are exclusive. Use
+/// [`MessagesRequest::resolve_citation`] to map a citation back to the block
+/// it came from.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Citation {
+    /// Character span inside a plain-text document.
+    CharLocation {
+        cited_text: String,
+        document_index: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        document_title: Option<String>,
+        start_char_index: usize,
+        end_char_index: usize,
+    },
+    /// Page span inside a PDF document.
+    PageLocation {
+        cited_text: String,
+        document_index: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        document_title: Option<String>,
+        start_page_number: u32,
+        end_page_number: u32,
+    },
+    /// Content-block span inside a custom-content document.
+    ContentBlockLocation {
+        cited_text: String,
+        document_index: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        document_title: Option<String>,
+        start_block_index: usize,
+        end_block_index: usize,
+    },
+    /// Content-block span inside a `search_result` block.
+    SearchResultLocation {
+        cited_text: String,
+        search_result_index: usize,
+        source: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        start_block_index: usize,
+        end_block_index: usize,
+    },
+    /// Result returned by the server-side web search tool.
+    WebSearchResultLocation {
+        cited_text: String,
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        encrypted_index: String,
+    },
+}
+
+impl Citation {
+    /// The verbatim text the model is citing.
+    pub fn cited_text(&self) -> &str {
+        match self {
+            Self::CharLocation { cited_text, .. }
+            | Self::PageLocation { cited_text, .. }
+            | Self::ContentBlockLocation { cited_text, .. }
+            | Self::SearchResultLocation { cited_text, .. }
+            | Self::WebSearchResultLocation { cited_text, .. } => cited_text,
+        }
+    }
+
+    /// Index of the cited `document` block, if this citation points at one.
+    pub fn document_index(&self) -> Option<usize> {
+        match self {
+            Self::CharLocation { document_index, .. }
+            | Self::PageLocation { document_index, .. }
+            | Self::ContentBlockLocation { document_index, .. } => Some(*document_index),
+            Self::SearchResultLocation { .. } | Self::WebSearchResultLocation { .. } => None,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 #[serde(untagged)]
 pub enum ToolResultContent {
@@ -349,6 +697,78 @@ pub enum ToolResultContent {
     Blocks(Vec<ContentBlock>),
 }
 
+/// Payload of a [`ContentBlock::WebSearchToolResult`]: either the list of
+/// results or an error reported by the search tool.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum WebSearchToolResultContent {
+    Results(Vec<WebSearchResult>),
+    Error(WebSearchToolError),
+}
+
+/// Single web search hit. `encrypted_content` must be passed back unchanged
+/// in follow-up turns so the model can cite the result.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename = "web_search_result")]
+pub struct WebSearchResult {
+    pub url: String,
+    pub title: String,
+    pub encrypted_content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_age: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename = "web_search_tool_result_error")]
+pub struct WebSearchToolError {
+    pub error_code: String,
+}
+
+/// Payload of a [`ContentBlock::WebFetchToolResult`].
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum WebFetchToolResultContent {
+    /// The fetched page, delivered as a `document` block.
+    #[serde(rename = "web_fetch_result")]
+    Result {
+        url: String,
+        content: Box<ContentBlock>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retrieved_at: Option<String>,
+    },
+    #[serde(rename = "web_fetch_tool_result_error")]
+    Error { error_code: String },
+}
+
+/// Payload of a [`ContentBlock::CodeExecutionToolResult`].
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum CodeExecutionToolResultContent {
+    #[serde(rename = "code_execution_result")]
+    Result(CodeExecutionResult),
+    #[serde(rename = "code_execution_tool_result_error")]
+    Error { error_code: String },
+}
+
+/// Outcome of a single code execution run inside the container.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct CodeExecutionResult {
+    pub stdout: String,
+    pub stderr: String,
+    pub return_code: i32,
+    /// Files written by the run. Download them through the Files API using
+    /// their `file_id`.
+    #[serde(default)]
+    pub content: Vec<CodeExecutionOutput>,
+}
+
+/// Reference to a file produced by code execution.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename = "code_execution_output")]
+pub struct CodeExecutionOutput {
+    pub file_id: String,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Message {
     pub role: Role,
@@ -401,6 +821,75 @@ impl From<String> for SystemPrompt {
     }
 }
 
+/// Beta flag required by requests that set `mcp_servers`.
+const MCP_CLIENT_BETA: &str = "mcp-client-2025-04-04";
+
+/// Remote MCP server the API connects to on the caller's behalf (the MCP
+/// connector). Its tools are called through [`ContentBlock::McpToolUse`]
+/// blocks and executed by the API.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename = "url")]
+pub struct McpServer {
+    pub url: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_configuration: Option<McpToolConfiguration>,
+}
+
+impl fmt::Debug for McpServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Requests are routinely debug-printed and traced; never print the
+        // bearer token.
+        f.debug_struct("McpServer")
+            .field("url", &self.url)
+            .field("name", &self.name)
+            .field("authorization_token", &self.authorization_token.as_ref().map(|_| "<redacted>"))
+            .field("tool_configuration", &self.tool_configuration)
+            .finish()
+    }
+}
+
+impl McpServer {
+    /// MCP server reachable at `url`, referred to as `name` in tool calls.
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self { url: url.into(), name: name.into(), authorization_token: None, tool_configuration: None }
+    }
+
+    /// OAuth bearer token sent to the server.
+    pub fn authorization_token(mut self, token: impl Into<String>) -> Self {
+        self.authorization_token = Some(token.into());
+        self
+    }
+
+    /// Only expose these tools from the server.
+    pub fn allowed_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tool_configuration.get_or_insert_with(Default::default).allowed_tools =
+            Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Enable or disable every tool from the server.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.tool_configuration.get_or_insert_with(Default::default).enabled = Some(enabled);
+        self
+    }
+}
+
+/// Tool allowlist for an [`McpServer`].
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+pub struct McpToolConfiguration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_tools: Option<Vec<String>>,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct Metadata {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -429,15 +918,432 @@ impl Tool {
     }
 }
 
+/// Entry of the request's `tools` list: a custom tool executed by the
+/// caller, an Anthropic-defined tool executed by the caller (computer use,
+/// bash, text editor), or a server tool executed by the API.
+///
+/// Every tool type converts into a `ToolDefinition` via `From`, so the
+/// request builders accept any mix of them.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ToolDefinition {
+    Server(ServerTool),
+    Builtin(BuiltinTool),
+    Custom(Tool),
+}
+
+impl ToolDefinition {
+    /// Name the model uses to call this tool.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Server(tool) => tool.name(),
+            Self::Builtin(tool) => tool.name(),
+            Self::Custom(tool) => &tool.name,
+        }
+    }
+
+    /// Returns `true` if the API executes this tool itself.
+    pub fn is_server_tool(&self) -> bool {
+        matches!(self, Self::Server(_))
+    }
+
+    /// Borrow the custom tool definition, if this is one.
+    pub fn as_custom(&self) -> Option<&Tool> {
+        match self {
+            Self::Custom(tool) => Some(tool),
+            Self::Server(_) | Self::Builtin(_) => None,
+        }
+    }
+
+    /// `anthropic-beta` flag the API requires before it accepts this tool.
+    pub fn required_beta(&self) -> Option<&'static str> {
+        match self {
+            Self::Server(tool) => tool.required_beta(),
+            Self::Builtin(tool) => tool.required_beta(),
+            Self::Custom(_) => None,
+        }
+    }
+}
+
+impl From<Tool> for ToolDefinition {
+    fn from(tool: Tool) -> Self {
+        Self::Custom(tool)
+    }
+}
+
+impl From<ServerTool> for ToolDefinition {
+    fn from(tool: ServerTool) -> Self {
+        Self::Server(tool)
+    }
+}
+
+impl From<BuiltinTool> for ToolDefinition {
+    fn from(tool: BuiltinTool) -> Self {
+        Self::Builtin(tool)
+    }
+}
+
+impl From<ComputerTool> for ToolDefinition {
+    fn from(tool: ComputerTool) -> Self {
+        Self::Builtin(BuiltinTool::Computer(tool))
+    }
+}
+
+impl From<BashTool> for ToolDefinition {
+    fn from(tool: BashTool) -> Self {
+        Self::Builtin(BuiltinTool::Bash(tool))
+    }
+}
+
+impl From<TextEditorTool> for ToolDefinition {
+    fn from(tool: TextEditorTool) -> Self {
+        Self::Builtin(BuiltinTool::TextEditor(tool))
+    }
+}
+
+impl From<WebSearchTool> for ToolDefinition {
+    fn from(tool: WebSearchTool) -> Self {
+        Self::Server(ServerTool::WebSearch(tool))
+    }
+}
+
+impl From<WebFetchTool> for ToolDefinition {
+    fn from(tool: WebFetchTool) -> Self {
+        Self::Server(ServerTool::WebFetch(tool))
+    }
+}
+
+impl From<CodeExecutionTool> for ToolDefinition {
+    fn from(tool: CodeExecutionTool) -> Self {
+        Self::Server(ServerTool::CodeExecution(tool))
+    }
+}
+
+/// Versioned server tool definitions. The API runs these tools and returns
+/// their calls and results as [`ContentBlock::ServerToolUse`] and the
+/// matching `*_tool_result` blocks.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum ServerTool {
+    #[serde(rename = "web_search_20250305")]
+    WebSearch(WebSearchTool),
+    #[serde(rename = "web_fetch_20250910")]
+    WebFetch(WebFetchTool),
+    #[serde(rename = "code_execution_20250522")]
+    CodeExecution(CodeExecutionTool),
+}
+
+impl ServerTool {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::WebSearch(tool) => &tool.name,
+            Self::WebFetch(tool) => &tool.name,
+            Self::CodeExecution(tool) => &tool.name,
+        }
+    }
+
+    /// `anthropic-beta` flag the API requires before it accepts this tool.
+    pub fn required_beta(&self) -> Option<&'static str> {
+        match self {
+            Self::WebSearch(_) => None,
+            Self::WebFetch(_) => Some("web-fetch-2025-09-10"),
+            Self::CodeExecution(_) => Some("code-execution-2025-05-22"),
+        }
+    }
+}
+
+/// Server-side web search tool (`web_search_20250305`).
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct WebSearchTool {
+    pub name: String,
+    /// Maximum number of searches the model may run in a single request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_uses: Option<u32>,
+    /// Only return results from these domains. Mutually exclusive with
+    /// `blocked_domains`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_domains: Option<Vec<String>>,
+    /// Never return results from these domains.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_domains: Option<Vec<String>>,
+    /// Approximate location used to localize results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_location: Option<UserLocation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+impl Default for WebSearchTool {
+    fn default() -> Self {
+        Self {
+            name: "web_search".into(),
+            max_uses: None,
+            allowed_domains: None,
+            blocked_domains: None,
+            user_location: None,
+            cache_control: None,
+        }
+    }
+}
+
+impl WebSearchTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_uses(mut self, max_uses: u32) -> Self {
+        self.max_uses = Some(max_uses);
+        self
+    }
+
+    pub fn allowed_domains<I, S>(mut self, domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_domains = Some(domains.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn blocked_domains<I, S>(mut self, domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.blocked_domains = Some(domains.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn user_location(mut self, location: UserLocation) -> Self {
+        self.user_location = Some(location);
+        self
+    }
+
+    pub fn with_cache_control(mut self, cache: CacheControl) -> Self {
+        self.cache_control = Some(cache);
+        self
+    }
+}
+
+/// Approximate user location for [`WebSearchTool`]. Every field is optional.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "type", rename = "approximate")]
+pub struct UserLocation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Two-letter ISO country code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// IANA timezone identifier (e.g. "Europe/Paris").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+/// Server-side web fetch tool (`web_fetch_20250910`).
+///
+/// Requires the `web-fetch-2025-09-10` beta, which the client adds
+/// automatically whenever this tool is present on a request.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct WebFetchTool {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_uses: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_domains: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_domains: Option<Vec<String>>,
+    /// Enable citations on fetched documents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub citations: Option<CitationsConfig>,
+    /// Truncate fetched content to roughly this many tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_content_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+impl Default for WebFetchTool {
+    fn default() -> Self {
+        Self {
+            name: "web_fetch".into(),
+            max_uses: None,
+            allowed_domains: None,
+            blocked_domains: None,
+            citations: None,
+            max_content_tokens: None,
+            cache_control: None,
+        }
+    }
+}
+
+impl WebFetchTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_uses(mut self, max_uses: u32) -> Self {
+        self.max_uses = Some(max_uses);
+        self
+    }
+
+    pub fn allowed_domains<I, S>(mut self, domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_domains = Some(domains.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn blocked_domains<I, S>(mut self, domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.blocked_domains = Some(domains.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn citations(mut self, enabled: bool) -> Self {
+        self.citations = Some(CitationsConfig { enabled });
+        self
+    }
+
+    pub fn max_content_tokens(mut self, max_content_tokens: u32) -> Self {
+        self.max_content_tokens = Some(max_content_tokens);
+        self
+    }
+
+    pub fn with_cache_control(mut self, cache: CacheControl) -> Self {
+        self.cache_control = Some(cache);
+        self
+    }
+}
+
+/// Server-side code execution tool (`code_execution_20250522`).
+///
+/// Code runs in a sandboxed container that persists across requests: pass
+/// the id from [`MessagesResponse::container`] back through
+/// [`MessagesRequestBuilder::container`] (or let
+/// [`Conversation`](crate::conversation::Conversation) do it) to keep files
+/// and state between turns. Requires the `code-execution-2025-05-22` beta,
+/// which the client adds automatically.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct CodeExecutionTool {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+impl Default for CodeExecutionTool {
+    fn default() -> Self {
+        Self { name: "code_execution".into(), cache_control: None }
+    }
+}
+
+impl CodeExecutionTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cache_control(mut self, cache: CacheControl) -> Self {
+        self.cache_control = Some(cache);
+        self
+    }
+}
+
+/// Code execution container attached to a response.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Container {
+    pub id: String,
+    pub expires_at: String,
+}
+
+/// Returns `true` if any message references a Files API upload.
+pub(crate) fn references_files(messages: &[Message]) -> bool {
+    messages.iter().flat_map(|m| m.content.iter()).any(ContentBlock::references_file)
+}
+
+/// Merge explicitly requested betas with those implied by the request's
+/// tools, preserving order and dropping duplicates.
+pub(crate) fn collect_betas(
+    explicit: &[String],
+    tools: Option<&[ToolDefinition]>,
+    extra: impl IntoIterator<Item = &'static str>,
+) -> Vec<String> {
+    let mut betas: Vec<String> = Vec::new();
+    let implied = tools.unwrap_or_default().iter().filter_map(|t| t.required_beta()).chain(extra);
+    for beta in explicit.iter().map(String::as_str).chain(implied.map(|b| -> &str { b })) {
+        if !betas.iter().any(|b| b == beta) {
+            betas.push(beta.to_string());
+        }
+    }
+    betas
+}
+
+/// How the model may use the request's tools.
+///
+/// `disable_parallel_tool_use` limits the model to at most one tool call
+/// per turn (exactly one for `Any` and `Tool`).
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum ToolChoice {
-    Auto,
-    Any,
-    Tool { name: String },
+    Auto {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        disable_parallel_tool_use: Option<bool>,
+    },
+    Any {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        disable_parallel_tool_use: Option<bool>,
+    },
+    Tool {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        disable_parallel_tool_use: Option<bool>,
+    },
     None,
 }
 
+impl ToolChoice {
+    /// The model decides whether to call tools.
+    pub fn auto() -> Self {
+        Self::Auto { disable_parallel_tool_use: None }
+    }
+
+    /// The model must call at least one tool.
+    pub fn any() -> Self {
+        Self::Any { disable_parallel_tool_use: None }
+    }
+
+    /// The model must call the tool named `name`.
+    pub fn tool(name: impl Into<String>) -> Self {
+        Self::Tool { name: name.into(), disable_parallel_tool_use: None }
+    }
+
+    /// Allow at most one tool call per turn. No-op on [`ToolChoice::None`].
+    pub fn disable_parallel_tool_use(mut self) -> Self {
+        match &mut self {
+            Self::Auto { disable_parallel_tool_use }
+            | Self::Any { disable_parallel_tool_use }
+            | Self::Tool { disable_parallel_tool_use, .. } => *disable_parallel_tool_use = Some(true),
+            Self::None => {}
+        }
+        self
+    }
+
+    /// Returns `true` if parallel tool use is disabled.
+    pub fn parallel_tool_use_disabled(&self) -> bool {
+        match self {
+            Self::Auto { disable_parallel_tool_use }
+            | Self::Any { disable_parallel_tool_use }
+            | Self::Tool { disable_parallel_tool_use, .. } => *disable_parallel_tool_use == Some(true),
+            Self::None => false,
+        }
+    }
+}
+
 /// Extended-thinking configuration attached to a request.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "snake_case", tag = "type")]
@@ -446,6 +1352,13 @@ pub enum ThinkingConfig {
     Disabled,
 }
 
+/// `anthropic-beta` flag that lets the model think between tool calls, with
+/// a thinking budget that spans the whole assistant turn.
+pub const INTERLEAVED_THINKING_BETA: &str = "interleaved-thinking-2025-05-14";
+
+/// Smallest `budget_tokens` the API accepts.
+pub const MIN_THINKING_BUDGET: u32 = 1024;
+
 impl ThinkingConfig {
     pub fn enabled(budget_tokens: u32) -> Self {
         Self::Enabled { budget_tokens }
@@ -484,18 +1397,71 @@ pub struct MessagesRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tools: Option<Vec<Tool>>,
+    pub tools: Option<Vec<ToolDefinition>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<ToolChoice>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub thinking: Option<ThinkingConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub service_tier: Option<ServiceTier>,
+    /// Id of a code execution container to reuse.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// Remote MCP servers the API may call tools on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mcp_servers: Option<Vec<McpServer>>,
+    /// Extra `anthropic-beta` flags sent with this request, on top of the
+    /// client-wide `beta` and any flag implied by the request's tools.
+    /// Carried in memory only; never serialized.
+    #[serde(skip, default)]
+    pub betas: Vec<String>,
     /// Per-request retry policy. Carried in memory only; never serialized.
     #[serde(skip, default)]
     pub retry_policy: RetryPolicy,
 }
 
+impl MessagesRequest {
+    /// Returns `true` if the interleaved-thinking beta is enabled.
+    pub fn interleaved_thinking(&self) -> bool {
+        self.betas.iter().any(|beta| beta == INTERLEAVED_THINKING_BETA)
+    }
+
+    /// Check that an enabled thinking budget is at least
+    /// [`MIN_THINKING_BUDGET`] and below `max_tokens`. With the
+    /// interleaved-thinking beta the budget covers the whole turn and may
+    /// exceed `max_tokens`.
+    pub fn validate_thinking(&self) -> Result<(), AnthropicError> {
+        let Some(ThinkingConfig::Enabled { budget_tokens }) = self.thinking else {
+            return Ok(());
+        };
+        if budget_tokens < MIN_THINKING_BUDGET {
+            return Err(AnthropicError::InvalidRequest(format!(
+                "thinking budget_tokens must be at least {MIN_THINKING_BUDGET} (got {budget_tokens})"
+            )));
+        }
+        if budget_tokens >= self.max_tokens && !self.interleaved_thinking() {
+            return Err(AnthropicError::InvalidRequest(format!(
+                "thinking budget_tokens ({budget_tokens}) must be less than max_tokens ({}) unless interleaved \
+                 thinking is enabled",
+                self.max_tokens
+            )));
+        }
+        Ok(())
+    }
+
+    /// Every `anthropic-beta` flag this request needs: the explicit
+    /// [`betas`](Self::betas) followed by flags implied by its tools.
+    pub fn required_betas(&self) -> Vec<String> {
+        let mcp = self.mcp_servers.as_ref().is_some_and(|servers| !servers.is_empty());
+        let files = references_files(&self.messages);
+        collect_betas(
+            &self.betas,
+            self.tools.as_deref(),
+            mcp.then_some(MCP_CLIENT_BETA).into_iter().chain(files.then_some(FILES_API_BETA)),
+        )
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct MessagesRequestBuilder {
     model: Option<String>,
@@ -508,10 +1474,13 @@ pub struct MessagesRequestBuilder {
     top_p: Option<f64>,
     top_k: Option<u32>,
     stream: Option<bool>,
-    tools: Option<Vec<Tool>>,
+    tools: Option<Vec<ToolDefinition>>,
     tool_choice: Option<ToolChoice>,
     thinking: Option<ThinkingConfig>,
     service_tier: Option<ServiceTier>,
+    container: Option<String>,
+    mcp_servers: Option<Vec<McpServer>>,
+    betas: Vec<String>,
     retry_policy: RetryPolicy,
 }
 
@@ -555,38 +1524,87 @@ impl MessagesRequestBuilder {
         self
     }
 
-    pub fn top_p(mut self, top_p: f64) -> Self {
-        self.top_p = Some(top_p);
+    pub fn top_p(mut self, top_p: f64) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    pub fn top_k(mut self, top_k: u32) -> Self {
+        self.top_k = Some(top_k);
+        self
+    }
+
+    pub fn stream(mut self, stream: bool) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+
+    /// Replace the tool list. Accepts custom [`Tool`]s, server tools, or a
+    /// mix of [`ToolDefinition`]s.
+    pub fn tools<I, T>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<ToolDefinition>,
+    {
+        self.tools = Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Append a single tool to the tool list.
+    pub fn tool(mut self, tool: impl Into<ToolDefinition>) -> Self {
+        self.tools.get_or_insert_with(Vec::new).push(tool.into());
+        self
+    }
+
+    pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    pub fn thinking(mut self, thinking: ThinkingConfig) -> Self {
+        self.thinking = Some(thinking);
         self
     }
 
-    pub fn top_k(mut self, top_k: u32) -> Self {
-        self.top_k = Some(top_k);
+    /// Enable the interleaved-thinking beta so the model can think between
+    /// tool calls. The thinking budget may then exceed `max_tokens`.
+    pub fn interleaved_thinking(mut self) -> Self {
+        if !self.betas.iter().any(|beta| beta == INTERLEAVED_THINKING_BETA) {
+            self.betas.push(INTERLEAVED_THINKING_BETA.into());
+        }
         self
     }
 
-    pub fn stream(mut self, stream: bool) -> Self {
-        self.stream = Some(stream);
+    pub fn service_tier(mut self, tier: ServiceTier) -> Self {
+        self.service_tier = Some(tier);
         self
     }
 
-    pub fn tools(mut self, tools: Vec<Tool>) -> Self {
-        self.tools = Some(tools);
+    /// Reuse an existing code execution container (see [`CodeExecutionTool`]).
+    pub fn container(mut self, container_id: impl Into<String>) -> Self {
+        self.container = Some(container_id.into());
         self
     }
 
-    pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
-        self.tool_choice = Some(tool_choice);
+    /// Connect the API to a remote MCP server for this request. The
+    /// `mcp-client-2025-04-04` beta is added automatically.
+    pub fn mcp_server(mut self, server: McpServer) -> Self {
+        self.mcp_servers.get_or_insert_with(Vec::new).push(server);
         self
     }
 
-    pub fn thinking(mut self, thinking: ThinkingConfig) -> Self {
-        self.thinking = Some(thinking);
+    /// Replace the list of remote MCP servers.
+    pub fn mcp_servers(mut self, servers: impl IntoIterator<Item = McpServer>) -> Self {
+        self.mcp_servers = Some(servers.into_iter().collect());
         self
     }
 
-    pub fn service_tier(mut self, tier: ServiceTier) -> Self {
-        self.service_tier = Some(tier);
+    /// Send an extra `anthropic-beta` flag with this request.
+    ///
+    /// Flags required by the request's tools are added automatically; use
+    /// this for betas the SDK cannot infer.
+    pub fn beta(mut self, beta: impl Into<String>) -> Self {
+        self.betas.push(beta.into());
         self
     }
 
@@ -666,6 +1684,9 @@ impl MessagesRequestBuilder {
             tool_choice: self.tool_choice,
             thinking: self.thinking,
             service_tier: self.service_tier,
+            container: self.container,
+            mcp_servers: self.mcp_servers,
+            betas: self.betas,
             retry_policy: self.retry_policy,
         })
     }
@@ -692,6 +1713,92 @@ pub struct Usage {
     pub cache_read_input_tokens: Option<u32>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub service_tier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_tool_use: Option<ServerToolUsage>,
+}
+
+impl std::ops::AddAssign<&Usage> for Usage {
+    /// Accumulate another request's usage, e.g. across the turns of a tool
+    /// loop. Token counts saturate instead of overflowing; `service_tier`
+    /// keeps the most recent value.
+    fn add_assign(&mut self, other: &Usage) {
+        fn add_optional(total: &mut Option<u32>, other: Option<u32>) {
+            if let Some(other) = other {
+                *total = Some(total.unwrap_or(0).saturating_add(other));
+            }
+        }
+
+        self.input_tokens = self.input_tokens.saturating_add(other.input_tokens);
+        self.output_tokens = self.output_tokens.saturating_add(other.output_tokens);
+        add_optional(&mut self.cache_creation_input_tokens, other.cache_creation_input_tokens);
+        add_optional(&mut self.cache_read_input_tokens, other.cache_read_input_tokens);
+        if other.service_tier.is_some() {
+            self.service_tier.clone_from(&other.service_tier);
+        }
+        if let Some(other) = &other.server_tool_use {
+            let total = self.server_tool_use.get_or_insert_with(ServerToolUsage::default);
+            total.web_search_requests = total.web_search_requests.saturating_add(other.web_search_requests);
+            total.web_fetch_requests = total.web_fetch_requests.saturating_add(other.web_fetch_requests);
+        }
+    }
+}
+
+impl Usage {
+    /// All input tokens billed for the request: uncached input plus cache
+    /// writes and cache reads.
+    pub fn total_input_tokens(&self) -> u32 {
+        self.input_tokens
+            .saturating_add(self.cache_creation_input_tokens.unwrap_or(0))
+            .saturating_add(self.cache_read_input_tokens.unwrap_or(0))
+    }
+
+    /// Token cost in US dollars at `pricing`. Server tool requests (web
+    /// search, web fetch) are billed separately and not included.
+    pub fn cost(&self, pricing: &ModelPricing) -> f64 {
+        let per_token = |tokens: u32, usd_per_mtok: f64| f64::from(tokens) * usd_per_mtok / 1_000_000.0;
+        per_token(self.input_tokens, pricing.input)
+            + per_token(self.output_tokens, pricing.output)
+            + per_token(self.cache_creation_input_tokens.unwrap_or(0), pricing.cache_write)
+            + per_token(self.cache_read_input_tokens.unwrap_or(0), pricing.cache_read)
+    }
+}
+
+/// Per-model token prices in US dollars per million tokens, used by
+/// [`Usage::cost`]. Prices change, so they are supplied by the caller rather
+/// than built into the crate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+impl ModelPricing {
+    /// Prices for uncached input and output tokens. Cache writes default to
+    /// 1.25x and cache reads to 0.1x the input price (5-minute cache).
+    pub fn new(input: f64, output: f64) -> Self {
+        Self { input, output, cache_write: input * 1.25, cache_read: input * 0.1 }
+    }
+
+    pub fn cache_write(mut self, usd_per_mtok: f64) -> Self {
+        self.cache_write = usd_per_mtok;
+        self
+    }
+
+    pub fn cache_read(mut self, usd_per_mtok: f64) -> Self {
+        self.cache_read = usd_per_mtok;
+        self
+    }
+}
+
+/// Number of server tool invocations billed for a request.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ServerToolUsage {
+    #[serde(default)]
+    pub web_search_requests: u32,
+    #[serde(default)]
+    pub web_fetch_requests: u32,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
@@ -705,6 +1812,9 @@ pub struct MessagesResponse {
     pub stop_reason: Option<StopReason>,
     pub stop_sequence: Option<String>,
     pub usage: Usage,
+    /// Code execution container used by this response, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<Container>,
 }
 
 impl MessagesResponse {
@@ -719,6 +1829,18 @@ impl MessagesResponse {
         out
     }
 
+    /// Concatenate the text of every `thinking` block, e.g. for audit logs.
+    /// `redacted_thinking` blocks are encrypted and not included.
+    pub fn thinking(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Thinking { thinking, .. } => Some(thinking.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Borrow the first text block, if any.
     pub fn first_text(&self) -> Option<&str> {
         self.content.iter().find_map(|b| b.as_text())
@@ -733,6 +1855,34 @@ impl MessagesResponse {
     pub fn has_tool_use(&self) -> bool {
         self.tool_uses().next().is_some()
     }
+
+    /// Iterate over every successful code execution run in the response.
+    pub fn code_execution_results(&self) -> impl Iterator<Item = &CodeExecutionResult> {
+        self.content.iter().filter_map(|b| b.as_code_execution_result())
+    }
+
+    /// Ids of every file produced by code execution in this response, in
+    /// order. Download them with the Files API.
+    pub fn output_file_ids(&self) -> Vec<&str> {
+        self.code_execution_results().flat_map(|r| r.content.iter().map(|o| o.file_id.as_str())).collect()
+    }
+
+    /// Iterate over every server tool call (web search, web fetch) the API
+    /// ran while producing this response.
+    pub fn server_tool_uses(&self) -> impl Iterator<Item = (&str, &str, &serde_json::Value)> {
+        self.content.iter().filter_map(|b| b.as_server_tool_use())
+    }
+
+    /// Iterate over every MCP tool call the API made while producing this
+    /// response, as `(id, server_name, name, input)`.
+    pub fn mcp_tool_uses(&self) -> impl Iterator<Item = (&str, &str, &str, &serde_json::Value)> {
+        self.content.iter().filter_map(|b| b.as_mcp_tool_use())
+    }
+
+    /// Iterate over every citation attached to the response's text blocks.
+    pub fn citations(&self) -> impl Iterator<Item = &Citation> {
+        self.content.iter().flat_map(|b| b.citations())
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -742,6 +1892,7 @@ pub enum ContentBlockDelta {
     InputJsonDelta { partial_json: String },
     ThinkingDelta { thinking: String },
     SignatureDelta { signature: String },
+    CitationsDelta { citation: Citation },
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -753,12 +1904,16 @@ pub struct MessageDeltaUsage {
     pub cache_creation_input_tokens: Option<u32>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cache_read_input_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_tool_use: Option<ServerToolUsage>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct MessageDelta {
     pub stop_reason: Option<StopReason>,
     pub stop_sequence: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<Container>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
@@ -927,6 +2082,92 @@ mod tests {
         );
     }
 
+    #[test]
+    fn content_block_text_with_citations_roundtrip() {
+        let block = ContentBlock::Text {
+            text: "the grass is green".into(),
+            citations: Some(vec![
+                Citation::CharLocation {
+                    cited_text: "The grass is green.".into(),
+                    document_index: 0,
+                    document_title: Some("Facts".into()),
+                    start_char_index: 0,
+                    end_char_index: 19,
+                },
+                Citation::PageLocation {
+                    cited_text: "Page two.".into(),
+                    document_index: 1,
+                    document_title: None,
+                    start_page_number: 2,
+                    end_page_number: 3,
+                },
+            ]),
+            cache_control: None,
+        };
+        roundtrip(
+            &block,
+            json!({
+                "type": "text",
+                "text": "the grass is green",
+                "citations": [
+                    {
+                        "type": "char_location",
+                        "cited_text": "The grass is green.",
+                        "document_index": 0,
+                        "document_title": "Facts",
+                        "start_char_index": 0,
+                        "end_char_index": 19
+                    },
+                    {
+                        "type": "page_location",
+                        "cited_text": "Page two.",
+                        "document_index": 1,
+                        "start_page_number": 2,
+                        "end_page_number": 3
+                    }
+                ]
+            }),
+        );
+        assert_eq!(block.citations().len(), 2);
+        assert_eq!(block.citations()[1].cited_text(), "Page two.");
+        assert_eq!(block.citations()[1].document_index(), Some(1));
+    }
+
+    #[test]
+    fn citation_variants_deserialize_from_api_payloads() {
+        let citations: Vec<Citation> = serde_json::from_value(json!([
+            {
+                "type": "content_block_location",
+                "cited_text": "chunk",
+                "document_index": 2,
+                "document_title": null,
+                "start_block_index": 0,
+                "end_block_index": 1
+            },
+            {
+                "type": "search_result_location",
+                "cited_text": "passage",
+                "search_result_index": 0,
+                "source": "https://example.com/kb/1",
+                "title": "KB 1",
+                "start_block_index": 0,
+                "end_block_index": 1
+            },
+            {
+                "type": "web_search_result_location",
+                "cited_text": "web",
+                "url": "https://example.com",
+                "title": "Example",
+                "encrypted_index": "EqgfCio"
+            }
+        ]))
+        .unwrap();
+        assert!(matches!(citations[0], Citation::ContentBlockLocation { document_index: 2, .. }));
+        assert!(matches!(citations[1], Citation::SearchResultLocation { search_result_index: 0, .. }));
+        assert_eq!(citations[1].document_index(), None);
+        assert!(matches!(&citations[2], Citation::WebSearchResultLocation { url, .. } if url == "https://example.com"));
+    }
+
     #[test]
     fn tool_serializes_with_optional_cache_control() {
         let tool = Tool::new("get_weather", "fetch weather", json!({"type": "object"}));
@@ -944,12 +2185,452 @@ mod tests {
         assert_eq!(value["cache_control"], json!({"type": "ephemeral"}));
     }
 
+    #[test]
+    fn server_tool_definitions_serialize_with_versioned_type() {
+        let search: ToolDefinition = WebSearchTool::new()
+            .max_uses(3)
+            .allowed_domains(["docs.rs", "rust-lang.org"])
+            .user_location(UserLocation {
+                city: Some("Paris".into()),
+                country: Some("FR".into()),
+                timezone: Some("Europe/Paris".into()),
+                ..Default::default()
+            })
+            .into();
+        roundtrip(
+            &search,
+            json!({
+                "type": "web_search_20250305",
+                "name": "web_search",
+                "max_uses": 3,
+                "allowed_domains": ["docs.rs", "rust-lang.org"],
+                "user_location": {
+                    "type": "approximate",
+                    "city": "Paris",
+                    "country": "FR",
+                    "timezone": "Europe/Paris"
+                }
+            }),
+        );
+        assert!(search.is_server_tool());
+        assert_eq!(search.name(), "web_search");
+        assert_eq!(search.required_beta(), None);
+
+        let fetch: ToolDefinition = WebFetchTool::new().blocked_domains(["example.com"]).citations(true).into();
+        roundtrip(
+            &fetch,
+            json!({
+                "type": "web_fetch_20250910",
+                "name": "web_fetch",
+                "blocked_domains": ["example.com"],
+                "citations": {"enabled": true}
+            }),
+        );
+        assert_eq!(fetch.required_beta(), Some("web-fetch-2025-09-10"));
+    }
+
+    #[test]
+    fn tool_definition_deserializes_custom_tools() {
+        let custom: ToolDefinition = serde_json::from_value(json!({
+            "name": "get_weather",
+            "description": "fetch weather",
+            "input_schema": {"type": "object"}
+        }))
+        .unwrap();
+        assert!(!custom.is_server_tool());
+        assert_eq!(custom.as_custom().unwrap().name, "get_weather");
+    }
+
+    #[test]
+    fn messages_request_mixes_custom_and_server_tools() {
+        let req = MessagesRequestBuilder::new("m", vec![Message::user("hi")], 10)
+            .tools(vec![Tool::new("t", "d", json!({}))])
+            .tool(WebSearchTool::new())
+            .tool(WebFetchTool::new())
+            .beta("custom-beta")
+            .beta("web-fetch-2025-09-10")
+            .build()
+            .unwrap();
+        let tools = req.tools.as_ref().unwrap();
+        assert_eq!(tools.iter().map(|t| t.name()).collect::<Vec<_>>(), vec!["t", "web_search", "web_fetch"]);
+        assert_eq!(req.required_betas(), vec!["custom-beta".to_string(), "web-fetch-2025-09-10".to_string()]);
+
+        let value = serde_json::to_value(&req).unwrap();
+        assert_eq!(value["tools"][1]["type"], "web_search_20250305");
+        assert!(value.get("betas").is_none());
+    }
+
+    #[test]
+    fn server_tool_result_blocks_roundtrip() {
+        roundtrip(
+            &ContentBlock::ServerToolUse {
+                id: "srvtoolu_1".into(),
+                name: "web_search".into(),
+                input: json!({"query": "rust"}),
+                cache_control: None,
+            },
+            json!({"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search", "input": {"query": "rust"}}),
+        );
+
+        roundtrip(
+            &ContentBlock::WebSearchToolResult {
+                tool_use_id: "srvtoolu_1".into(),
+                content: WebSearchToolResultContent::Results(vec![WebSearchResult {
+                    url: "https://www.rust-lang.org".into(),
+                    title: "Rust".into(),
+                    encrypted_content: "enc".into(),
+                    page_age: Some("April 30, 2025".into()),
+                }]),
+                cache_control: None,
+            },
+            json!({
+                "type": "web_search_tool_result",
+                "tool_use_id": "srvtoolu_1",
+                "content": [{
+                    "type": "web_search_result",
+                    "url": "https://www.rust-lang.org",
+                    "title": "Rust",
+                    "encrypted_content": "enc",
+                    "page_age": "April 30, 2025"
+                }]
+            }),
+        );
+
+        roundtrip(
+            &ContentBlock::WebSearchToolResult {
+                tool_use_id: "srvtoolu_2".into(),
+                content: WebSearchToolResultContent::Error(WebSearchToolError {
+                    error_code: "max_uses_exceeded".into(),
+                }),
+                cache_control: None,
+            },
+            json!({
+                "type": "web_search_tool_result",
+                "tool_use_id": "srvtoolu_2",
+                "content": {"type": "web_search_tool_result_error", "error_code": "max_uses_exceeded"}
+            }),
+        );
+    }
+
+    #[test]
+    fn web_fetch_tool_result_roundtrip() {
+        roundtrip(
+            &ContentBlock::WebFetchToolResult {
+                tool_use_id: "srvtoolu_3".into(),
+                content: WebFetchToolResultContent::Result {
+                    url: "https://example.com/a".into(),
+                    content: Box::new(ContentBlock::document_text("page body")),
+                    retrieved_at: Some("2025-09-10T00:00:00Z".into()),
+                },
+                cache_control: None,
+            },
+            json!({
+                "type": "web_fetch_tool_result",
+                "tool_use_id": "srvtoolu_3",
+                "content": {
+                    "type": "web_fetch_result",
+                    "url": "https://example.com/a",
+                    "content": {
+                        "type": "document",
+                        "source": {"type": "text", "media_type": "text/plain", "data": "page body"}
+                    },
+                    "retrieved_at": "2025-09-10T00:00:00Z"
+                }
+            }),
+        );
+
+        roundtrip(
+            &ContentBlock::WebFetchToolResult {
+                tool_use_id: "srvtoolu_4".into(),
+                content: WebFetchToolResultContent::Error { error_code: "url_not_accessible".into() },
+                cache_control: None,
+            },
+            json!({
+                "type": "web_fetch_tool_result",
+                "tool_use_id": "srvtoolu_4",
+                "content": {"type": "web_fetch_tool_result_error", "error_code": "url_not_accessible"}
+            }),
+        );
+    }
+
+    #[test]
+    fn mcp_server_debug_redacts_the_token() {
+        let server = McpServer::new("tracker", "https://mcp.example.com/sse").authorization_token("secret-token");
+        let rendered = format!("{server:?}");
+        assert!(rendered.contains("<redacted>"), "{rendered}");
+        assert!(!rendered.contains("secret-token"), "{rendered}");
+        assert!(format!("{:?}", McpServer::new("tracker", "https://mcp.example.com/sse")).contains("None"));
+    }
+
+    #[test]
+    fn mcp_servers_serialize_and_require_beta() {
+        let req = MessagesRequestBuilder::new("m", vec![Message::user("hi")], 10)
+            .mcp_server(
+                McpServer::new("tracker", "https://mcp.example.com/sse")
+                    .authorization_token("tok")
+                    .allowed_tools(["search_issues"]),
+            )
+            .beta("mcp-client-2025-04-04")
+            .build()
+            .unwrap();
+        let value = serde_json::to_value(&req).unwrap();
+        assert_eq!(
+            value["mcp_servers"],
+            json!([{
+                "type": "url",
+                "url": "https://mcp.example.com/sse",
+                "name": "tracker",
+                "authorization_token": "tok",
+                "tool_configuration": {"allowed_tools": ["search_issues"]}
+            }])
+        );
+        assert_eq!(req.required_betas(), vec!["mcp-client-2025-04-04".to_string()]);
+
+        let plain = MessagesRequestBuilder::new("m", vec![Message::user("hi")], 10).build().unwrap();
+        assert!(serde_json::to_value(&plain).unwrap().get("mcp_servers").is_none());
+        assert!(plain.required_betas().is_empty());
+    }
+
+    #[test]
+    fn image_from_path_sniffs_and_encodes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pixel.gif");
+        std::fs::write(&path, b"GIF89a\x01\x00\x01\x00\x00\x00\x00;").unwrap();
+        match ContentBlock::image_from_path(&path).unwrap() {
+            ContentBlock::Image { source: ImageSource::Base64 { media_type, data }, .. } => {
+                assert_eq!(media_type, "image/gif");
+                assert_eq!(data, "R0lGODlhAQABAAAAADs=");
+            }
+            other => panic!("unexpected block {other:?}"),
+        }
+        assert!(ContentBlock::image_from_path(dir.path().join("missing.png")).is_err());
+    }
+
+    #[test]
+    fn file_sources_require_files_beta() {
+        assert_eq!(serde_json::to_value(ImageSource::File { file_id: "file_1".into() }).unwrap()["type"], "file");
+        let nested = ContentBlock::tool_result_blocks("tu_1", vec![ContentBlock::image_file("file_1")]);
+        assert!(nested.references_file());
+        assert!(!ContentBlock::image_url("https://example.com/a.png").references_file());
+
+        let req = MessagesRequestBuilder::new("m", vec![Message::new(Role::User, vec![nested])], 10).build().unwrap();
+        assert_eq!(req.required_betas(), vec![FILES_API_BETA.to_string()]);
+    }
+
+    #[test]
+    fn mcp_tool_blocks_roundtrip() {
+        roundtrip(
+            &ContentBlock::McpToolUse {
+                id: "mcptoolu_1".into(),
+                name: "search_issues".into(),
+                server_name: "tracker".into(),
+                input: json!({"query": "bug"}),
+                cache_control: None,
+            },
+            json!({
+                "type": "mcp_tool_use",
+                "id": "mcptoolu_1",
+                "name": "search_issues",
+                "server_name": "tracker",
+                "input": {"query": "bug"}
+            }),
+        );
+        roundtrip(
+            &ContentBlock::McpToolResult {
+                tool_use_id: "mcptoolu_1".into(),
+                is_error: false,
+                content: ToolResultContent::Blocks(vec![ContentBlock::text("2 issues")]),
+                cache_control: None,
+            },
+            json!({
+                "type": "mcp_tool_result",
+                "tool_use_id": "mcptoolu_1",
+                "is_error": false,
+                "content": [{"type": "text", "text": "2 issues"}]
+            }),
+        );
+    }
+
+    #[test]
+    fn unknown_block_types_are_kept_verbatim() {
+        let block = json!({
+            "type": "bash_code_execution_tool_result",
+            "tool_use_id": "srvtoolu_1",
+            "content": {"type": "bash_code_execution_result", "stdout": "hi\n", "stderr": "", "return_code": 0}
+        });
+        roundtrip(&ContentBlock::Other(block.clone()), block.clone());
+
+        let response: MessagesResponse = serde_json::from_value(json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [block, {"type": "text", "text": "done"}],
+            "model": "m",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 1, "output_tokens": 1}
+        }))
+        .unwrap();
+        assert!(matches!(response.content[0], ContentBlock::Other(_)));
+        assert_eq!(response.text(), "done");
+    }
+
+    #[test]
+    fn code_execution_tool_and_request_container_serialize() {
+        let req = MessagesRequestBuilder::new("m", vec![Message::user("hi")], 10)
+            .tool(CodeExecutionTool::new())
+            .container("container_011")
+            .build()
+            .unwrap();
+        let value = serde_json::to_value(&req).unwrap();
+        assert_eq!(value["tools"][0], json!({"type": "code_execution_20250522", "name": "code_execution"}));
+        assert_eq!(value["container"], "container_011");
+        assert_eq!(req.required_betas(), vec!["code-execution-2025-05-22".to_string()]);
+    }
+
+    #[test]
+    fn code_execution_tool_result_roundtrip() {
+        roundtrip(
+            &ContentBlock::CodeExecutionToolResult {
+                tool_use_id: "srvtoolu_1".into(),
+                content: CodeExecutionToolResultContent::Result(CodeExecutionResult {
+                    stdout: "4\n".into(),
+                    stderr: String::new(),
+                    return_code: 0,
+                    content: vec![CodeExecutionOutput { file_id: "file_01".into() }],
+                }),
+                cache_control: None,
+            },
+            json!({
+                "type": "code_execution_tool_result",
+                "tool_use_id": "srvtoolu_1",
+                "content": {
+                    "type": "code_execution_result",
+                    "stdout": "4\n",
+                    "stderr": "",
+                    "return_code": 0,
+                    "content": [{"type": "code_execution_output", "file_id": "file_01"}]
+                }
+            }),
+        );
+        roundtrip(
+            &ContentBlock::CodeExecutionToolResult {
+                tool_use_id: "srvtoolu_2".into(),
+                content: CodeExecutionToolResultContent::Error { error_code: "execution_time_exceeded".into() },
+                cache_control: None,
+            },
+            json!({
+                "type": "code_execution_tool_result",
+                "tool_use_id": "srvtoolu_2",
+                "content": {"type": "code_execution_tool_result_error", "error_code": "execution_time_exceeded"}
+            }),
+        );
+    }
+
+    #[test]
+    fn messages_response_collects_code_execution_files_and_container() {
+        let resp: MessagesResponse = serde_json::from_value(json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {"type": "server_tool_use", "id": "srvtoolu_1", "name": "code_execution", "input": {"code": "plot()"}},
+                {
+                    "type": "code_execution_tool_result",
+                    "tool_use_id": "srvtoolu_1",
+                    "content": {
+                        "type": "code_execution_result",
+                        "stdout": "",
+                        "stderr": "warning",
+                        "return_code": 0,
+                        "content": [
+                            {"type": "code_execution_output", "file_id": "file_a"},
+                            {"type": "code_execution_output", "file_id": "file_b"}
+                        ]
+                    }
+                },
+                {
+                    "type": "code_execution_tool_result",
+                    "tool_use_id": "srvtoolu_2",
+                    "content": {"type": "code_execution_tool_result_error", "error_code": "unavailable"}
+                }
+            ],
+            "model": "claude",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 1, "output_tokens": 1},
+            "container": {"id": "container_011", "expires_at": "2025-05-23T00:00:00Z"}
+        }))
+        .unwrap();
+        assert_eq!(resp.code_execution_results().count(), 1);
+        assert_eq!(resp.code_execution_results().next().unwrap().stderr, "warning");
+        assert_eq!(resp.output_file_ids(), vec!["file_a", "file_b"]);
+        assert_eq!(resp.container.as_ref().unwrap().id, "container_011");
+    }
+
     #[test]
     fn tool_choice_none_variant_serializes() {
         let choice = ToolChoice::None;
         roundtrip(&choice, json!({"type": "none"}));
     }
 
+    #[test]
+    fn usage_accumulates_across_requests() {
+        let mut total = Usage { input_tokens: 10, output_tokens: 5, ..Default::default() };
+        total += &Usage {
+            input_tokens: 3,
+            output_tokens: 2,
+            cache_creation_input_tokens: Some(100),
+            cache_read_input_tokens: Some(40),
+            service_tier: Some("standard".into()),
+            server_tool_use: Some(ServerToolUsage { web_search_requests: 1, web_fetch_requests: 0 }),
+        };
+        total += &Usage { input_tokens: 1, cache_read_input_tokens: Some(140), ..Default::default() };
+        assert_eq!(
+            total,
+            Usage {
+                input_tokens: 14,
+                output_tokens: 7,
+                cache_creation_input_tokens: Some(100),
+                cache_read_input_tokens: Some(180),
+                service_tier: Some("standard".into()),
+                server_tool_use: Some(ServerToolUsage { web_search_requests: 1, web_fetch_requests: 0 }),
+            }
+        );
+    }
+
+    #[test]
+    fn usage_cost_prices_every_token_kind() {
+        let usage = Usage {
+            input_tokens: 1_000_000,
+            output_tokens: 200_000,
+            cache_creation_input_tokens: Some(400_000),
+            cache_read_input_tokens: Some(2_000_000),
+            ..Default::default()
+        };
+        assert_eq!(usage.total_input_tokens(), 3_400_000);
+        let pricing = ModelPricing::new(3.0, 15.0);
+        assert_eq!(pricing.cache_write, 3.75);
+        assert!((usage.cost(&pricing) - (3.0 + 3.0 + 1.5 + 0.6)).abs() < 1e-9);
+        assert!((usage.cost(&pricing.cache_read(0.0)) - 7.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tool_choice_serializes_disable_parallel_tool_use() {
+        roundtrip(&ToolChoice::auto(), json!({"type": "auto"}));
+        roundtrip(
+            &ToolChoice::any().disable_parallel_tool_use(),
+            json!({"type": "any", "disable_parallel_tool_use": true}),
+        );
+        let choice = ToolChoice::tool("get_weather").disable_parallel_tool_use();
+        assert!(choice.parallel_tool_use_disabled());
+        roundtrip(&choice, json!({"type": "tool", "name": "get_weather", "disable_parallel_tool_use": true}));
+        assert!(!ToolChoice::None.disable_parallel_tool_use().parallel_tool_use_disabled());
+
+        let request = MessagesRequestBuilder::new("m", vec![Message::user("hi")], 16).build().unwrap();
+        assert!(serde_json::to_value(request).unwrap().get("tool_choice").is_none());
+    }
+
     #[test]
     fn thinking_config_enabled_serializes() {
         roundtrip(&ThinkingConfig::enabled(1024), json!({"type": "enabled", "budget_tokens": 1024}));
@@ -1000,6 +2681,24 @@ mod tests {
         MessagesRequestBuilder::new("m", vec![Message::user("hi")], 10).temperature(1.0).build().unwrap();
     }
 
+    #[test]
+    fn messages_request_validates_thinking_budget() {
+        let build = |max_tokens, budget| {
+            MessagesRequestBuilder::new("m", vec![Message::user("hi")], max_tokens)
+                .thinking(ThinkingConfig::enabled(budget))
+        };
+        build(2048, 1024).build().unwrap().validate_thinking().unwrap();
+        let err = build(2048, 512).build().unwrap().validate_thinking().unwrap_err();
+        assert!(format!("{err}").contains("at least 1024"), "{err}");
+        let err = build(2048, 2048).build().unwrap().validate_thinking().unwrap_err();
+        assert!(format!("{err}").contains("less than max_tokens"), "{err}");
+
+        let req = build(2048, 8192).interleaved_thinking().interleaved_thinking().build().unwrap();
+        req.validate_thinking().unwrap();
+        assert!(req.interleaved_thinking());
+        assert_eq!(req.required_betas(), vec![INTERLEAVED_THINKING_BETA]);
+    }
+
     #[test]
     fn messages_request_builder_requires_model() {
         let err =
@@ -1017,7 +2716,7 @@ mod tests {
             .thinking(ThinkingConfig::enabled(1024))
             .service_tier(ServiceTier::Auto)
             .tools(vec![Tool::new("t", "d", json!({}))])
-            .tool_choice(ToolChoice::Auto)
+            .tool_choice(ToolChoice::auto())
             .build()
             .unwrap();
         assert_eq!(req.model, "claude");
@@ -1120,6 +2819,17 @@ mod tests {
         assert_eq!(usage.service_tier.as_deref(), Some("auto"));
     }
 
+    #[test]
+    fn usage_deserializes_server_tool_use() {
+        let usage: Usage = serde_json::from_value(json!({
+            "input_tokens": 12,
+            "output_tokens": 5,
+            "server_tool_use": {"web_search_requests": 2}
+        }))
+        .unwrap();
+        assert_eq!(usage.server_tool_use, Some(ServerToolUsage { web_search_requests: 2, web_fetch_requests: 0 }));
+    }
+
     #[test]
     fn messages_response_text_concats_text_blocks() {
         let resp = MessagesResponse {
@@ -1135,6 +2845,7 @@ mod tests {
             stop_reason: Some(StopReason::EndTurn),
             stop_sequence: None,
             usage: Usage::default(),
+            container: None,
         };
         assert_eq!(resp.text(), "hello world");
         assert_eq!(resp.first_text(), Some("hello "));
@@ -1177,6 +2888,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn stream_event_citations_delta_roundtrip() {
+        let evt = MessagesStreamEvent::ContentBlockDelta {
+            index: 0,
+            delta: ContentBlockDelta::CitationsDelta {
+                citation: Citation::CharLocation {
+                    cited_text: "x".into(),
+                    document_index: 0,
+                    document_title: None,
+                    start_char_index: 0,
+                    end_char_index: 1,
+                },
+            },
+        };
+        roundtrip(
+            &evt,
+            json!({
+                "type": "content_block_delta",
+                "index": 0,
+                "delta": {
+                    "type": "citations_delta",
+                    "citation": {
+                        "type": "char_location",
+                        "cited_text": "x",
+                        "document_index": 0,
+                        "start_char_index": 0,
+                        "end_char_index": 1
+                    }
+                }
+            }),
+        );
+    }
+
     #[test]
     fn system_prompt_text_conversion() {
         let prompt: SystemPrompt = "hello".into();
@@ -1,10 +1,12 @@
 //! Integration tests for the Message Batches API.
 
+use std::time::Duration;
+
 use anthropic::batches::{
     BatchProcessingStatus, BatchRequest, BatchRequestResult, CreateBatchRequest, ListBatchesParams,
 };
 use anthropic::types::{Message, MessagesRequestBuilder};
-use anthropic::{AnthropicError, Client};
+use anthropic::{AnthropicError, CancellationToken, Client};
 use serde_json::json;
 use wiremock::matchers::{body_json, method, path, query_param};
 use wiremock::{Mock, MockServer, ResponseTemplate};
@@ -195,3 +197,44 @@ async fn batch_endpoints_surface_api_errors() {
         other => panic!("expected Api error, got {other:?}"),
     }
 }
+
+#[tokio::test]
+async fn wait_for_batch_polls_until_ended() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/messages/batches/msgbatch_7"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(sample_batch_json("msgbatch_7", "in_progress")))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/messages/batches/msgbatch_7"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(sample_batch_json("msgbatch_7", "ended")))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let batch = client.wait_for_batch("msgbatch_7", Duration::from_millis(10)).await.unwrap();
+    assert!(batch.is_complete());
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn wait_for_batch_with_cancellation_stops_when_cancelled() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/messages/batches/msgbatch_8"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(sample_batch_json("msgbatch_8", "in_progress")))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        canceller.cancel();
+    });
+    let err = client.wait_for_batch_with_cancellation("msgbatch_8", Duration::from_secs(60), &token).await.unwrap_err();
+    assert!(matches!(err, AnthropicError::Cancelled), "{err}");
+}
@@ -0,0 +1,279 @@
+//! Integration tests for the Files API endpoints and file-based sources.
+
+use anthropic::files::{ListFilesParams, FILES_API_BETA};
+use anthropic::types::{ContentBlock, Message, MessagesRequestBuilder, Role};
+use anthropic::{AnthropicError, Client};
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn metadata(id: &str, filename: &str, mime_type: &str) -> Value {
+    json!({
+        "id": id,
+        "type": "file",
+        "filename": filename,
+        "mime_type": mime_type,
+        "size_bytes": 11,
+        "created_at": "2025-04-14T00:00:00Z"
+    })
+}
+
+fn client(server: &MockServer) -> Client {
+    Client::builder().api_key("test-key").api_base(server.uri()).build().unwrap()
+}
+
+fn rate_limited() -> ResponseTemplate {
+    ResponseTemplate::new(429).insert_header("retry-after", "0").set_body_json(json!({
+        "type": "error",
+        "error": {"type": "rate_limit_error", "message": "slow down"}
+    }))
+}
+
+#[tokio::test]
+async fn upload_file_sends_multipart_with_inferred_mime_type() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/files"))
+        .and(header("anthropic-beta", FILES_API_BETA))
+        .respond_with(ResponseTemplate::new(200).set_body_json(metadata("file_1", "notes.md", "text/markdown")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("notes.md");
+    std::fs::write(&file, "hello files").unwrap();
+
+    let uploaded = client(&server).upload_file(&file).await.unwrap();
+    assert_eq!(uploaded.id, "file_1");
+
+    let requests = server.received_requests().await.unwrap();
+    let content_type = requests[0].headers.get("content-type").unwrap().to_str().unwrap();
+    assert!(content_type.starts_with("multipart/form-data; boundary="), "{content_type}");
+    let body = String::from_utf8_lossy(&requests[0].body);
+    assert!(body.contains(r#"name="file"; filename="notes.md""#), "{body}");
+    assert!(body.contains("Content-Type: text/markdown"), "{body}");
+    assert!(body.contains("hello files"), "{body}");
+}
+
+#[tokio::test]
+async fn upload_file_reports_missing_paths() {
+    let server = MockServer::start().await;
+    let err = client(&server).upload_file("/definitely/not/here.pdf").await.unwrap_err();
+    assert!(matches!(err, AnthropicError::InvalidRequest(_)));
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn upload_file_from_reader_streams_the_body() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/files"))
+        .and(header("anthropic-beta", FILES_API_BETA))
+        .respond_with(ResponseTemplate::new(200).set_body_json(metadata("file_2", "data.csv", "text/csv")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let reader = std::io::Cursor::new(b"a,b\n1,2\n".to_vec());
+    let uploaded = client(&server).upload_file_from_reader(reader, "data.csv", "text/csv").await.unwrap();
+    assert_eq!(uploaded.filename, "data.csv");
+
+    let requests = server.received_requests().await.unwrap();
+    let body = String::from_utf8_lossy(&requests[0].body);
+    assert!(body.contains(r#"filename="data.csv""#), "{body}");
+    assert!(body.contains("a,b\n1,2\n"), "{body}");
+}
+
+#[tokio::test]
+async fn upload_file_retries_429_with_a_fresh_form() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/files"))
+        .respond_with(rate_limited())
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/files"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(metadata("file_3", "notes.md", "text/markdown")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("notes.md");
+    std::fs::write(&file, "hello again").unwrap();
+
+    let uploaded = client(&server).upload_file(&file).await.unwrap();
+    assert_eq!(uploaded.id, "file_3");
+
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    for request in &requests {
+        let body = String::from_utf8_lossy(&request.body);
+        assert!(body.contains("hello again"), "{body}");
+    }
+}
+
+#[tokio::test]
+async fn list_files_forwards_pagination_parameters() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/files"))
+        .and(query_param("limit", "2"))
+        .and(query_param("after_id", "file_0"))
+        .and(header("anthropic-beta", FILES_API_BETA))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [metadata("file_1", "a.pdf", "application/pdf"), metadata("file_2", "b.png", "image/png")],
+            "has_more": true,
+            "first_id": "file_1",
+            "last_id": "file_2"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let list = client(&server).list_files(&ListFilesParams::new().after_id("file_0").limit(2)).await.unwrap();
+    assert_eq!(list.data.len(), 2);
+    assert!(list.has_more);
+    assert_eq!(list.last_id.as_deref(), Some("file_2"));
+}
+
+#[tokio::test]
+async fn get_file_metadata_and_delete_file() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/files/file_1"))
+        .and(header("anthropic-beta", FILES_API_BETA))
+        .respond_with(ResponseTemplate::new(200).set_body_json(metadata("file_1", "a.pdf", "application/pdf")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("DELETE"))
+        .and(path("/v1/files/file_1"))
+        .and(header("anthropic-beta", FILES_API_BETA))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "file_1", "type": "file_deleted"})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let file = client.get_file_metadata("file_1").await.unwrap();
+    assert_eq!(file.mime_type, "application/pdf");
+    let deleted = client.delete_file("file_1").await.unwrap();
+    assert_eq!(deleted.deleted_type, "file_deleted");
+}
+
+#[tokio::test]
+async fn download_file_streams_bytes() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/files/file_out/content"))
+        .and(header("anthropic-beta", FILES_API_BETA))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(b"\x89PNG binary".to_vec(), "image/png"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut stream = client(&server).download_file("file_out").await.unwrap();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        bytes.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(bytes, b"\x89PNG binary");
+}
+
+#[tokio::test]
+async fn download_file_retries_429() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/files/file_out/content"))
+        .respond_with(rate_limited())
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/files/file_out/content"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(b"late bytes".to_vec(), "text/plain"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut stream = client(&server).download_file("file_out").await.unwrap();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        bytes.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(bytes, b"late bytes");
+}
+
+#[tokio::test]
+async fn download_file_surfaces_api_errors() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/files/file_in/content"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "type": "error",
+            "error": {"type": "invalid_request_error", "message": "File is not downloadable"}
+        })))
+        .mount(&server)
+        .await;
+
+    let err = match client(&server).download_file("file_in").await {
+        Ok(_) => panic!("expected an error"),
+        Err(err) => err,
+    };
+    match err {
+        AnthropicError::Api(api) => assert_eq!(api.message, "File is not downloadable"),
+        other => panic!("unexpected error {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn messages_with_file_sources_add_the_files_beta() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(header("anthropic-beta", FILES_API_BETA))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "A quarterly report."}],
+            "model": "claude-3-5-sonnet-20240620",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let message = Message::new(
+        Role::User,
+        vec![
+            ContentBlock::document_file("file_1"),
+            ContentBlock::image_file("file_2"),
+            ContentBlock::text("What is this?"),
+        ],
+    );
+    let request = MessagesRequestBuilder::new("claude-3-5-sonnet-20240620", vec![message], 128).build().unwrap();
+    client(&server).messages(request).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["messages"][0]["content"][0]["source"], json!({"type": "file", "file_id": "file_1"}));
+    assert_eq!(body["messages"][0]["content"][1]["source"], json!({"type": "file", "file_id": "file_2"}));
+}
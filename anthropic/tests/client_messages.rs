@@ -1,9 +1,12 @@
 //! Integration tests for `Client::messages` using a wiremock-backed server.
 
-use anthropic::types::{Message, MessagesRequestBuilder, Role, StopReason};
-use anthropic::{AnthropicError, Client};
+use std::time::Duration;
+
+use anthropic::types::{Message, MessagesRequestBuilder, MessagesStreamEvent, Role, StopReason};
+use anthropic::{AnthropicError, CancellationToken, Client};
+use futures_util::StreamExt;
 use serde_json::json;
-use wiremock::matchers::{body_json, header, method, path};
+use wiremock::matchers::{body_json, header, headers, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 fn build_client(server: &MockServer) -> Client {
@@ -82,6 +85,37 @@ async fn messages_beta_header_is_forwarded() {
     client.messages(sample_request()).await.expect("ok");
 }
 
+#[tokio::test]
+async fn messages_adds_betas_required_by_tools() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(headers("anthropic-beta", vec!["prompt-caching-2024-07-31", "web-fetch-2025-09-10"]))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_02",
+            "type": "message",
+            "role": "assistant",
+            "content": [],
+            "model": "claude",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 1, "output_tokens": 0}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client =
+        Client::builder().api_key("test-key").api_base(server.uri()).beta("prompt-caching-2024-07-31").build().unwrap();
+    let request = MessagesRequestBuilder::new("claude", vec![Message::user("hi")], 10)
+        .tool(anthropic::types::WebFetchTool::new().max_uses(2))
+        .build()
+        .unwrap();
+
+    client.messages(request).await.expect("ok");
+}
+
 #[tokio::test]
 async fn messages_surfaces_api_errors() {
     let server = MockServer::start().await;
@@ -286,3 +320,57 @@ async fn messages_preserves_conversation_roundtrip() {
     assert_eq!(name, "get_weather");
     assert_eq!(input["city"], "Paris");
 }
+
+#[tokio::test]
+async fn messages_with_cancellation_drops_the_in_flight_request() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(30)))
+        .mount(&server)
+        .await;
+
+    let client = build_client(&server);
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        canceller.cancel();
+    });
+    let started = std::time::Instant::now();
+    let err = client.messages_with_cancellation(sample_request(), &token).await.unwrap_err();
+    assert!(matches!(err, AnthropicError::Cancelled), "{err}");
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn messages_stream_with_cancellation_ends_the_stream() {
+    let sse = [
+        json!({"type": "message_start", "message": {
+            "id": "msg_1", "type": "message", "role": "assistant", "content": [],
+            "model": "claude-3-5-sonnet-20240620", "stop_reason": null, "stop_sequence": null,
+            "usage": {"input_tokens": 3, "output_tokens": 1}
+        }}),
+        json!({"type": "message_stop"}),
+    ]
+    .iter()
+    .map(|event| format!("event: {}\ndata: {event}\n\n", event["type"].as_str().unwrap()))
+    .collect::<String>();
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+        .mount(&server)
+        .await;
+
+    let client = build_client(&server);
+    let token = CancellationToken::new();
+    let mut stream = client.messages_stream_with_cancellation(sample_request(), &token).await.unwrap();
+    assert!(matches!(stream.next().await, Some(Ok(MessagesStreamEvent::MessageStart { .. }))));
+    token.cancel();
+    assert!(matches!(stream.next().await, Some(Err(AnthropicError::Cancelled))));
+    assert!(stream.next().await.is_none());
+
+    let err = client.messages_stream_with_cancellation(sample_request(), &token).await.err().unwrap();
+    assert!(matches!(err, AnthropicError::Cancelled));
+}
@@ -0,0 +1,91 @@
+//! Integration tests for `Conversation` against a wiremock-backed server.
+
+use anthropic::types::{CodeExecutionTool, Message, MessagesRequestBuilder};
+use anthropic::{Client, Conversation};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> Client {
+    Client::builder().api_key("test-key").api_base(server.uri()).build().unwrap()
+}
+
+#[tokio::test]
+async fn conversation_reuses_code_execution_container() {
+    let server = MockServer::start().await;
+
+    // Follow-up turns must carry the container id returned by the first.
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(json!({"container": "container_011"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_2",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "The file still has 3 rows."}],
+            "model": "claude",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 30, "output_tokens": 10},
+            "container": {"id": "container_011", "expires_at": "2025-05-23T00:00:00Z"}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {
+                    "type": "server_tool_use",
+                    "id": "srvtoolu_1",
+                    "name": "code_execution",
+                    "input": {"code": "open('data.csv', 'w').write('a\\nb\\nc')"}
+                },
+                {
+                    "type": "code_execution_tool_result",
+                    "tool_use_id": "srvtoolu_1",
+                    "content": {
+                        "type": "code_execution_result",
+                        "stdout": "",
+                        "stderr": "",
+                        "return_code": 0,
+                        "content": [{"type": "code_execution_output", "file_id": "file_csv"}]
+                    }
+                },
+                {"type": "text", "text": "Wrote data.csv."}
+            ],
+            "model": "claude",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 20, "output_tokens": 40},
+            "container": {"id": "container_011", "expires_at": "2025-05-23T00:00:00Z"}
+        })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    let request = MessagesRequestBuilder::new("claude", vec![Message::user("Write a CSV")], 256)
+        .tool(CodeExecutionTool::new())
+        .build()
+        .unwrap();
+    let mut conversation = Conversation::new(request);
+
+    let first = conversation.send(&client(&server)).await.unwrap();
+    assert_eq!(first.output_file_ids(), vec!["file_csv"]);
+    assert_eq!(first.code_execution_results().next().unwrap().return_code, 0);
+    assert_eq!(conversation.container(), Some("container_011"));
+
+    let second = conversation.send_message(&client(&server), Message::user("How many rows?")).await.unwrap();
+    assert_eq!(second.text(), "The file still has 3 rows.");
+    assert_eq!(conversation.messages().len(), 4);
+
+    let requests = server.received_requests().await.unwrap();
+    let first_body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert!(first_body.get("container").is_none());
+    assert_eq!(requests[0].headers.get("anthropic-beta").unwrap(), "code-execution-2025-05-22");
+}
@@ -0,0 +1,156 @@
+//! Integration tests for `Client::extract` and friends.
+#![cfg(feature = "schemars")]
+
+use anthropic::extract::ExtractConfig;
+use anthropic::types::{Message, MessagesRequest, MessagesRequestBuilder};
+use anthropic::{AnthropicError, Client};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Contact details mentioned in the text.
+#[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+struct Contact {
+    name: String,
+    email: Option<String>,
+}
+
+fn client(server: &MockServer) -> Client {
+    Client::builder().api_key("test-key").api_base(server.uri()).build().unwrap()
+}
+
+fn request() -> MessagesRequest {
+    MessagesRequestBuilder::new(
+        "claude-3-5-sonnet-20240620",
+        vec![Message::user("Reach Ada Lovelace at ada@example.com.")],
+        256,
+    )
+    .build()
+    .unwrap()
+}
+
+fn tool_use_response(input: Value) -> Value {
+    json!({
+        "id": "msg_extract",
+        "type": "message",
+        "role": "assistant",
+        "content": [{"type": "tool_use", "id": "tu_1", "name": "structured_output", "input": input}],
+        "model": "claude-3-5-sonnet-20240620",
+        "stop_reason": "tool_use",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 30, "output_tokens": 12}
+    })
+}
+
+#[tokio::test]
+async fn extract_forces_the_synthesized_tool() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(tool_use_response(json!({"name": "Ada Lovelace", "email": "ada@example.com"}))),
+        )
+        .mount(&server)
+        .await;
+
+    let contact: Contact = client(&server).extract(request()).await.unwrap();
+    assert_eq!(contact, Contact { name: "Ada Lovelace".into(), email: Some("ada@example.com".into()) });
+
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["tool_choice"], json!({"type": "tool", "name": "structured_output"}));
+    assert_eq!(body["tools"][0]["name"], "structured_output");
+    assert_eq!(body["tools"][0]["input_schema"]["required"], json!(["name"]));
+}
+
+#[tokio::test]
+async fn extract_re_asks_with_the_validation_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tool_use_response(json!({"email": 42}))))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tool_use_response(json!({"name": "Ada Lovelace"}))))
+        .mount(&server)
+        .await;
+
+    let contact: Contact = client(&server).extract_with(request(), ExtractConfig::new().max_retries(2)).await.unwrap();
+    assert_eq!(contact, Contact { name: "Ada Lovelace".into(), email: None });
+
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    let body: Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let feedback = &body["messages"][2]["content"][0];
+    assert_eq!(feedback["type"], "tool_result");
+    assert_eq!(feedback["tool_use_id"], "tu_1");
+    assert_eq!(feedback["is_error"], true);
+}
+
+#[tokio::test]
+async fn extract_reports_when_the_tool_is_not_called() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_refusal",
+            "type": "message",
+            "role": "assistant",
+            "content": [],
+            "model": "claude-3-5-sonnet-20240620",
+            "stop_reason": "refusal",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 30, "output_tokens": 0}
+        })))
+        .mount(&server)
+        .await;
+
+    let err = client(&server).extract::<Contact>(request()).await.unwrap_err();
+    assert!(matches!(err, AnthropicError::Extraction(ref msg) if msg.contains("Refusal")), "{err}");
+}
+
+#[tokio::test]
+async fn extract_streaming_assembles_partial_json() {
+    let events = [
+        json!({"type": "message_start", "message": {
+            "id": "msg_stream", "type": "message", "role": "assistant", "content": [],
+            "model": "claude-3-5-sonnet-20240620", "stop_reason": null, "stop_sequence": null,
+            "usage": {"input_tokens": 30, "output_tokens": 1}
+        }}),
+        json!({"type": "content_block_start", "index": 0, "content_block": {
+            "type": "tool_use", "id": "tu_1", "name": "structured_output", "input": {}
+        }}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {
+            "type": "input_json_delta", "partial_json": "{\"name\": \"Ada "
+        }}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {
+            "type": "input_json_delta", "partial_json": "Lovelace\"}"
+        }}),
+        json!({"type": "content_block_stop", "index": 0}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "tool_use", "stop_sequence": null},
+            "usage": {"output_tokens": 12}}),
+        json!({"type": "message_stop"}),
+    ];
+    let sse: String =
+        events.iter().map(|event| format!("event: {}\ndata: {event}\n\n", event["type"].as_str().unwrap())).collect();
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+        .mount(&server)
+        .await;
+
+    let contact: Contact = client(&server).extract_streaming(request(), ExtractConfig::default()).await.unwrap();
+    assert_eq!(contact, Contact { name: "Ada Lovelace".into(), email: None });
+
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["stream"], true);
+}
@@ -0,0 +1,261 @@
+//! Integration tests for the local MCP client bridge.
+#![cfg(feature = "mcp")]
+
+use anthropic::mcp::McpClient;
+use anthropic::tool_loop::{run_tool_loop, ToolLoopConfig};
+use anthropic::types::{ContentBlock, Message, MessagesRequestBuilder, ToolResultContent};
+use anthropic::{AnthropicError, Client};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Tiny MCP server speaking newline-delimited JSON-RPC over any byte stream.
+///
+/// Tools: `add` (sums `a` and `b`), `screenshot` (returns an image), and
+/// `fail` (reports a tool error). `tools/list` is split over two pages.
+async fn serve<R, W>(reader: R, mut writer: W)
+where
+    R: tokio::io::AsyncRead + Unpin,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let request: Value = serde_json::from_str(&line).unwrap();
+        let Some(id) = request.get("id").cloned() else { continue };
+        let params = &request["params"];
+        let reply = match request["method"].as_str().unwrap() {
+            "initialize" => json!({
+                "result": {
+                    "protocolVersion": params["protocolVersion"],
+                    "capabilities": {"tools": {}},
+                    "serverInfo": {"name": "mini", "version": "0.0.1"}
+                }
+            }),
+            "tools/list" if params.get("cursor").is_none() => json!({
+                "result": {
+                    "tools": [{
+                        "name": "add",
+                        "description": "Add two numbers",
+                        "inputSchema": {"type": "object", "properties": {"a": {"type": "number"}, "b": {"type": "number"}}}
+                    }],
+                    "nextCursor": "page-2"
+                }
+            }),
+            "tools/list" => json!({
+                "result": {
+                    "tools": [
+                        {"name": "screenshot", "inputSchema": {"type": "object"}},
+                        {"name": "fail", "inputSchema": {"type": "object"}}
+                    ]
+                }
+            }),
+            "tools/call" => {
+                // Interleave a notification to make sure the client skips it.
+                let progress = json!({"jsonrpc": "2.0", "method": "notifications/progress", "params": {}});
+                writer.write_all(format!("{progress}\n").as_bytes()).await.unwrap();
+                let args = &params["arguments"];
+                match params["name"].as_str().unwrap() {
+                    "add" => {
+                        let sum = args["a"].as_f64().unwrap() + args["b"].as_f64().unwrap();
+                        json!({"result": {"content": [{"type": "text", "text": sum.to_string()}]}})
+                    }
+                    "screenshot" => json!({
+                        "result": {
+                            "content": [
+                                {"type": "text", "text": "current screen:"},
+                                {"type": "image", "data": "iVBORw0KGgo=", "mimeType": "image/png"}
+                            ]
+                        }
+                    }),
+                    "fail" => json!({"result": {"content": [{"type": "text", "text": "disk full"}], "isError": true}}),
+                    other => json!({"error": {"code": -32602, "message": format!("unknown tool {other}")}}),
+                }
+            }
+            other => json!({"error": {"code": -32601, "message": format!("unknown method {other}")}}),
+        };
+        let mut reply = reply;
+        reply["jsonrpc"] = json!("2.0");
+        reply["id"] = id;
+        writer.write_all(format!("{reply}\n").as_bytes()).await.unwrap();
+    }
+}
+
+async fn in_memory_client() -> McpClient {
+    let (client_side, server_side) = tokio::io::duplex(64 * 1024);
+    let (server_read, server_write) = tokio::io::split(server_side);
+    tokio::spawn(serve(server_read, server_write));
+    let (client_read, client_write) = tokio::io::split(client_side);
+    McpClient::from_io(client_read, client_write).await.unwrap()
+}
+
+#[tokio::test]
+async fn lists_tools_across_pages_and_calls_them() {
+    let mcp = in_memory_client().await;
+    assert_eq!(mcp.server_info()["serverInfo"]["name"], "mini");
+
+    let tools = mcp.list_tools().await.unwrap();
+    let names: Vec<_> = tools.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, vec!["add", "screenshot", "fail"]);
+    assert_eq!(tools[0].description, "Add two numbers");
+    assert_eq!(tools[0].input_schema["properties"]["a"]["type"], "number");
+
+    let result = mcp.call_tool("add", json!({"a": 2, "b": 3})).await.unwrap();
+    assert!(!result.is_error);
+    assert_eq!(result.to_content_blocks(), vec![ContentBlock::text("5")]);
+
+    let err = mcp.call_tool("nope", json!({})).await.unwrap_err();
+    assert!(matches!(err, AnthropicError::McpRpc { code: -32602, .. }));
+}
+
+#[tokio::test]
+async fn executor_drives_the_tool_loop() {
+    let mcp = in_memory_client().await;
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_tool",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {"type": "tool_use", "id": "tu_1", "name": "screenshot", "input": {}},
+                {"type": "tool_use", "id": "tu_2", "name": "fail", "input": {}},
+                {"type": "tool_use", "id": "tu_3", "name": "missing", "input": {}}
+            ],
+            "model": "claude-3-5-sonnet-20240620",
+            "stop_reason": "tool_use",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 20, "output_tokens": 15}
+        })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_final",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "The screen shows a login form."}],
+            "model": "claude-3-5-sonnet-20240620",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 30, "output_tokens": 10}
+        })))
+        .mount(&server)
+        .await;
+
+    let client = Client::builder().api_key("test-key").api_base(server.uri()).build().unwrap();
+    let request =
+        MessagesRequestBuilder::new("claude-3-5-sonnet-20240620", vec![Message::user("What's on screen?")], 256)
+            .tools(mcp.list_tools().await.unwrap())
+            .build()
+            .unwrap();
+
+    let response = run_tool_loop(&client, request, mcp.executor(), ToolLoopConfig::default()).await.unwrap().response;
+    assert_eq!(response.text(), "The screen shows a login form.");
+
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let results: Vec<ContentBlock> = serde_json::from_value(body["messages"][2]["content"].clone()).unwrap();
+    assert_eq!(
+        results[0],
+        ContentBlock::tool_result_blocks(
+            "tu_1",
+            vec![ContentBlock::text("current screen:"), ContentBlock::image_base64("image/png", "iVBORw0KGgo=")]
+        )
+    );
+    assert_eq!(results[1], ContentBlock::tool_result_error("tu_2", "disk full"));
+    match &results[2] {
+        ContentBlock::ToolResult { is_error: Some(true), content: ToolResultContent::Text(text), .. } => {
+            assert_eq!(text, "unknown tool missing");
+        }
+        other => panic!("unexpected block {other:?}"),
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn spawns_a_stdio_server() {
+    // A scripted server that answers the three requests the client sends, in
+    // order: initialize (id 1), tools/list (id 2), and tools/call (id 3).
+    let script = r#"
+        while IFS= read -r line; do
+            case "$line" in
+                *'"method":"initialize"'*)
+                    echo 'server starting' ;
+                    echo '{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"sh"}}}' ;;
+                *'"method":"tools/list"'*)
+                    echo '{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"echo","inputSchema":{"type":"object"}}]}}' ;;
+                *'"method":"tools/call"'*)
+                    echo '{"jsonrpc":"2.0","id":99,"method":"ping"}' ;
+                    read -r pong ;
+                    echo '{"jsonrpc":"2.0","id":3,"result":{"content":[{"type":"text","text":"echoed"}]}}' ;;
+            esac
+        done
+    "#;
+    let mut command = tokio::process::Command::new("bash");
+    command.arg("-c").arg(script);
+
+    let mcp = McpClient::spawn(command).await.unwrap();
+    assert_eq!(mcp.server_info()["serverInfo"]["name"], "sh");
+    let tools = mcp.list_tools().await.unwrap();
+    assert_eq!(tools[0].name, "echo");
+    let result = mcp.call_tool("echo", json!({})).await.unwrap();
+    assert_eq!(result.to_content_blocks(), vec![ContentBlock::text("echoed")]);
+}
+
+#[tokio::test]
+async fn streamable_http_tracks_the_session_and_reads_sse() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/mcp"))
+        .and(body_partial_json(json!({"method": "initialize"})))
+        .respond_with(ResponseTemplate::new(200).insert_header("mcp-session-id", "session-42").set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {"protocolVersion": "2025-03-26", "capabilities": {}, "serverInfo": {"name": "http"}}
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/mcp"))
+        .and(header("mcp-session-id", "session-42"))
+        .and(header("mcp-protocol-version", "2025-03-26"))
+        .and(body_partial_json(json!({"method": "notifications/initialized"})))
+        .respond_with(ResponseTemplate::new(202))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let sse = concat!(
+        "event: message\n",
+        "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\",\"params\":{}}\n\n",
+        "event: message\n",
+        "data: {\"jsonrpc\":\"2.0\",\"id\":2,\"result\":{\"tools\":[{\"name\":\"search\",\"description\":\"Search docs\",\"inputSchema\":{\"type\":\"object\"}}]}}\n\n",
+    );
+    Mock::given(method("POST"))
+        .and(path("/mcp"))
+        .and(header("mcp-session-id", "session-42"))
+        .and(header("mcp-protocol-version", "2025-03-26"))
+        .and(body_partial_json(json!({"method": "tools/list"})))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mcp = McpClient::connect_http(format!("{}/mcp", server.uri())).await.unwrap();
+    assert_eq!(mcp.server_info()["serverInfo"]["name"], "http");
+    let tools = mcp.list_tools().await.unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0].name, "search");
+    assert_eq!(tools[0].description, "Search docs");
+
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests[0].headers.get("mcp-protocol-version").is_none());
+}
@@ -2,10 +2,19 @@
 
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use anthropic::tool_loop::{run_tool_loop, ToolLoopConfig, ToolOutput};
-use anthropic::types::{Message, MessagesRequestBuilder, Tool, ToolChoice};
-use anthropic::{AnthropicError, Client};
+use anthropic::documents::{search_results, Passage};
+use anthropic::tool_loop::{
+    run_tool_loop, run_tool_loop_streaming, ToolCancelPolicy, ToolDecision, ToolLoopBudget, ToolLoopConfig,
+    ToolLoopEvent, ToolLoopHooks, ToolLoopIteration, ToolLoopStopReason, ToolOutput,
+};
+use anthropic::tool_registry::ToolRegistry;
+use anthropic::types::{
+    McpServer, Message, MessagesRequestBuilder, ModelPricing, Role, StopReason, ThinkingConfig, Tool, ToolChoice,
+};
+use anthropic::{AnthropicError, CancellationToken, Client};
+use futures_util::future::BoxFuture;
 use serde_json::json;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
@@ -25,7 +34,7 @@ fn tool_request() -> anthropic::types::MessagesRequest {
                 "required": ["city"]
             }),
         )])
-        .tool_choice(ToolChoice::Auto)
+        .tool_choice(ToolChoice::auto())
         .build()
         .unwrap()
 }
@@ -101,7 +110,8 @@ async fn tool_loop_runs_single_tool_then_returns_final_response() {
         ToolLoopConfig::default(),
     )
     .await
-    .unwrap();
+    .unwrap()
+    .response;
 
     assert_eq!(response.text(), "It's sunny in Paris.");
     assert_eq!(call_count.load(Ordering::SeqCst), 1);
@@ -156,7 +166,8 @@ async fn tool_loop_handles_multiple_tool_calls_in_one_turn() {
         ToolLoopConfig::default(),
     )
     .await
-    .unwrap();
+    .unwrap()
+    .response;
 
     assert_eq!(response.text(), "Paris is sunny, Rome is rainy.");
     let locked = calls.lock().unwrap();
@@ -190,7 +201,8 @@ async fn tool_loop_propagates_tool_errors_to_model() {
         ToolLoopConfig::default(),
     )
     .await
-    .unwrap();
+    .unwrap()
+    .response;
 
     assert_eq!(response.text(), "That city is unknown.");
 }
@@ -207,17 +219,19 @@ async fn tool_loop_bails_out_on_iteration_limit() {
         .await;
 
     let client = client(&server);
-    let err =
+    let outcome =
         run_tool_loop(&client, tool_request(), |_n, _i| async { Ok(ToolOutput::ok("sunny")) }, ToolLoopConfig::new(2))
             .await
-            .unwrap_err();
+            .unwrap();
 
-    match err {
-        AnthropicError::InvalidRequest(msg) => {
-            assert!(msg.contains("2 iterations"));
-        }
-        other => panic!("expected InvalidRequest, got {other:?}"),
-    }
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::MaxIterations);
+    assert_eq!(outcome.iterations.len(), 2);
+    assert!(outcome.response.has_tool_use());
+    // user, then (assistant, tool results) twice: the last results are kept
+    // so the conversation can be resumed.
+    assert_eq!(outcome.messages.len(), 5);
+    assert_eq!(outcome.messages[4].role, Role::User);
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
 }
 
 #[tokio::test]
@@ -273,8 +287,1199 @@ async fn tool_loop_returns_directly_when_no_tool_use() {
         ToolLoopConfig::default(),
     )
     .await
-    .unwrap();
+    .unwrap()
+    .response;
 
     assert_eq!(response.text(), "Just chatting.");
     assert_eq!(call_count.load(Ordering::SeqCst), 0);
 }
+
+#[tokio::test]
+async fn tool_loop_never_dispatches_server_tools() {
+    let server = MockServer::start().await;
+
+    // The model runs a web search on the server side and calls a custom tool
+    // in the same turn. Only the custom call may reach the executor.
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_tool",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search", "input": {"query": "paris"}},
+                {
+                    "type": "web_search_tool_result",
+                    "tool_use_id": "srvtoolu_1",
+                    "content": [{
+                        "type": "web_search_result",
+                        "url": "https://example.com/paris",
+                        "title": "Paris",
+                        "encrypted_content": "enc"
+                    }]
+                },
+                {"type": "tool_use", "id": "tu_1", "name": "get_weather", "input": {"city": "Paris"}}
+            ],
+            "model": "claude-3-5-sonnet-20240620",
+            "stop_reason": "tool_use",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 20, "output_tokens": 15, "server_tool_use": {"web_search_requests": 1}}
+        })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Sunny, per the web.")))
+        .mount(&server)
+        .await;
+
+    let names = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+    let names_clone = Arc::clone(&names);
+    let request = MessagesRequestBuilder::new("claude-3-5-sonnet-20240620", vec![Message::user("Weather?")], 256)
+        .tool(anthropic::types::WebSearchTool::new())
+        .tool(Tool::new("get_weather", "Fetch the weather", json!({"type": "object"})))
+        .build()
+        .unwrap();
+
+    let client = client(&server);
+    let response = run_tool_loop(
+        &client,
+        request,
+        move |name, _input| {
+            let names = Arc::clone(&names_clone);
+            async move {
+                names.lock().unwrap().push(name);
+                Ok(ToolOutput::ok("sunny"))
+            }
+        },
+        ToolLoopConfig::default(),
+    )
+    .await
+    .unwrap()
+    .response;
+
+    assert_eq!(response.text(), "Sunny, per the web.");
+    assert_eq!(*names.lock().unwrap(), vec!["get_weather".to_string()]);
+
+    // The follow-up request must replay the server blocks and answer only the
+    // custom tool call.
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let assistant = &body["messages"][1]["content"];
+    assert_eq!(assistant[0]["type"], "server_tool_use");
+    assert_eq!(assistant[1]["type"], "web_search_tool_result");
+    let results = body["messages"][2]["content"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["tool_use_id"], "tu_1");
+}
+
+#[tokio::test]
+async fn tool_loop_reuses_code_execution_container() {
+    let server = MockServer::start().await;
+
+    let mut first = tool_use_response("tu_1", "Paris");
+    first["container"] = json!({"id": "container_011", "expires_at": "2025-05-23T00:00:00Z"});
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(first))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Done.")))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let response = run_tool_loop(
+        &client,
+        tool_request(),
+        |_name, _input| async { Ok(ToolOutput::ok("sunny")) },
+        ToolLoopConfig::default(),
+    )
+    .await
+    .unwrap()
+    .response;
+    assert_eq!(response.text(), "Done.");
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(body["container"], "container_011");
+}
+
+#[tokio::test]
+async fn tool_loop_ignores_mcp_tool_blocks() {
+    let server = MockServer::start().await;
+
+    // A response that only contains MCP connector calls is final: the API
+    // already ran them, so the loop must not dispatch or continue.
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_mcp",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {
+                    "type": "mcp_tool_use",
+                    "id": "mcptoolu_1",
+                    "name": "search_issues",
+                    "server_name": "tracker",
+                    "input": {"query": "bug"}
+                },
+                {
+                    "type": "mcp_tool_result",
+                    "tool_use_id": "mcptoolu_1",
+                    "is_error": false,
+                    "content": [{"type": "text", "text": "2 open issues"}]
+                },
+                {"type": "text", "text": "There are 2 open issues."}
+            ],
+            "model": "claude-3-5-sonnet-20240620",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 20, "output_tokens": 15}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let request = MessagesRequestBuilder::new("claude-3-5-sonnet-20240620", vec![Message::user("Open bugs?")], 256)
+        .mcp_server(McpServer::new("tracker", "https://mcp.example.com/sse"))
+        .build()
+        .unwrap();
+
+    let client = client(&server);
+    let response = run_tool_loop(
+        &client,
+        request,
+        |name, _input| async move { panic!("executor called for {name}") },
+        ToolLoopConfig::default(),
+    )
+    .await
+    .unwrap()
+    .response;
+
+    assert_eq!(response.text(), "There are 2 open issues.");
+    assert_eq!(response.mcp_tool_uses().count(), 1);
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests[0].headers.get("anthropic-beta").unwrap(), "mcp-client-2025-04-04");
+}
+
+#[tokio::test]
+async fn tool_loop_returns_search_results_from_tools() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tool_use_response("tu_1", "Paris")))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Sunny.")))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    run_tool_loop(
+        &client,
+        tool_request(),
+        |_name, _input| async move {
+            Ok(ToolOutput::blocks(search_results([
+                Passage::new("https://weather.example/paris", "Paris forecast", "Sunny, 22C."),
+                Passage::new("https://weather.example/paris", "Paris forecast", "Light wind."),
+            ])))
+        },
+        ToolLoopConfig::default(),
+    )
+    .await
+    .unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(
+        body["messages"][2]["content"][0]["content"],
+        json!([{
+            "type": "search_result",
+            "source": "https://weather.example/paris",
+            "title": "Paris forecast",
+            "content": [{"type": "text", "text": "Sunny, 22C."}, {"type": "text", "text": "Light wind."}],
+            "citations": {"enabled": true}
+        }])
+    );
+}
+
+#[tokio::test]
+async fn tool_loop_dispatches_through_registry() {
+    let server = MockServer::start().await;
+
+    let mut first = tool_use_response("tu_1", "Paris");
+    first["content"].as_array_mut().unwrap().push(json!({
+        "type": "tool_use",
+        "id": "tu_2",
+        "name": "get_time",
+        "input": {}
+    }));
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(first))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Sunny.")))
+        .mount(&server)
+        .await;
+
+    let registry = ToolRegistry::with_context(AtomicUsize::new(0)).register(
+        Tool::new("get_weather", "Fetch the current weather for a city", json!({"type": "object"})),
+        |calls: Arc<AtomicUsize>, input| async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(ToolOutput::ok(format!("Sunny in {}", input["city"].as_str().unwrap())))
+        },
+    );
+    let request = MessagesRequestBuilder::new("claude-3-5-sonnet-20240620", vec![Message::user("Weather?")], 256)
+        .tools(registry.tools())
+        .build()
+        .unwrap();
+
+    let client = client(&server);
+    let response =
+        run_tool_loop(&client, request, registry.executor(), ToolLoopConfig::default()).await.unwrap().response;
+    assert_eq!(response.id, "msg_final");
+    assert_eq!(registry.context().load(Ordering::SeqCst), 1);
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["tools"][0]["name"], "get_weather");
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let results = &body["messages"][2]["content"];
+    assert_eq!(results[0]["content"], "Sunny in Paris");
+    assert_eq!(results[1]["tool_use_id"], "tu_2");
+    assert_eq!(results[1]["is_error"], true);
+}
+
+#[cfg(feature = "jsonschema")]
+#[tokio::test]
+async fn tool_loop_rejects_inputs_that_violate_the_schema() {
+    let server = MockServer::start().await;
+
+    let mut invalid = tool_use_response("tu_1", "Paris");
+    invalid["content"][1]["input"] = json!({"town": "Paris"});
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(invalid))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Which city?")))
+        .mount(&server)
+        .await;
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let executor_calls = Arc::clone(&calls);
+    let client = client(&server);
+    let outcome = run_tool_loop(
+        &client,
+        tool_request(),
+        move |_name, _input| {
+            executor_calls.fetch_add(1, Ordering::SeqCst);
+            async { Ok(ToolOutput::ok("sunny")) }
+        },
+        ToolLoopConfig::default().validate_inputs(true),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(outcome.validation_failures, 1);
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let result = &body["messages"][2]["content"][0];
+    assert_eq!(result["tool_use_id"], "tu_1");
+    assert_eq!(result["is_error"], true);
+    assert_eq!(
+        result["content"],
+        "input for tool `get_weather` does not match its input_schema:\n- \"city\" is a required property"
+    );
+}
+
+fn three_city_response() -> serde_json::Value {
+    json!({
+        "id": "msg_tool",
+        "type": "message",
+        "role": "assistant",
+        "content": [
+            {"type": "tool_use", "id": "tu_1", "name": "get_weather", "input": {"city": "Paris"}},
+            {"type": "tool_use", "id": "tu_2", "name": "get_weather", "input": {"city": "Rome"}},
+            {"type": "tool_use", "id": "tu_3", "name": "get_weather", "input": {"city": "Oslo"}}
+        ],
+        "model": "claude-3-5-sonnet-20240620",
+        "stop_reason": "tool_use",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 20, "output_tokens": 15}
+    })
+}
+
+/// Runs the three-city turn and returns the peak number of tool calls in
+/// flight plus the `tool_use_id`s of the follow-up request, in order.
+async fn run_three_cities(request: anthropic::types::MessagesRequest, config: ToolLoopConfig) -> (usize, Vec<String>) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(three_city_response()))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Done.")))
+        .mount(&server)
+        .await;
+
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (in_flight_clone, peak_clone) = (Arc::clone(&in_flight), Arc::clone(&peak));
+    let client = client(&server);
+    run_tool_loop(
+        &client,
+        request,
+        move |_name, input| {
+            let (in_flight, peak) = (Arc::clone(&in_flight_clone), Arc::clone(&peak_clone));
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                // The first call is the slowest, so completion order differs
+                // from call order.
+                let delay = if input["city"] == "Paris" { 60 } else { 20 };
+                tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(ToolOutput::ok(input["city"].as_str().unwrap()))
+            }
+        },
+        config,
+    )
+    .await
+    .unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let results = body["messages"][2]["content"].as_array().unwrap();
+    for (result, city) in results.iter().zip(["Paris", "Rome", "Oslo"]) {
+        assert_eq!(result["content"], city);
+    }
+    let ids = results.iter().map(|r| r["tool_use_id"].as_str().unwrap().to_string()).collect();
+    (peak.load(Ordering::SeqCst), ids)
+}
+
+#[tokio::test]
+async fn tool_loop_runs_tool_calls_in_parallel_in_order() {
+    let (peak, ids) = run_three_cities(tool_request(), ToolLoopConfig::default().max_parallel_tools(2)).await;
+    assert_eq!(peak, 2);
+    assert_eq!(ids, ["tu_1", "tu_2", "tu_3"]);
+
+    let (peak, _) = run_three_cities(tool_request(), ToolLoopConfig::default()).await;
+    assert_eq!(peak, 1);
+}
+
+#[tokio::test]
+async fn tool_loop_honors_disable_parallel_tool_use() {
+    let mut request = tool_request();
+    request.tool_choice = Some(ToolChoice::auto().disable_parallel_tool_use());
+    let (peak, ids) = run_three_cities(request, ToolLoopConfig::default().max_parallel_tools(8)).await;
+    assert_eq!(peak, 1);
+    assert_eq!(ids, ["tu_1", "tu_2", "tu_3"]);
+}
+
+#[tokio::test]
+async fn tool_loop_outcome_records_transcript_usage_and_tool_counts() {
+    let server = MockServer::start().await;
+
+    let mut first = three_city_response();
+    first["usage"] = json!({"input_tokens": 100, "output_tokens": 30, "cache_creation_input_tokens": 500});
+    first["content"][2]["name"] = json!("get_time");
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(first))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    let mut last = text_response("Done.");
+    last["usage"] = json!({"input_tokens": 20, "output_tokens": 5, "cache_read_input_tokens": 500});
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(last))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let outcome =
+        run_tool_loop(&client, tool_request(), |_n, _i| async { Ok(ToolOutput::ok("ok")) }, ToolLoopConfig::default())
+            .await
+            .unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Finished);
+    assert_eq!(outcome.response.text(), "Done.");
+    assert_eq!(outcome.iterations.len(), 2);
+    assert_eq!(outcome.iterations[0].response.id, "msg_tool");
+    assert_eq!(outcome.iterations[1].tools_duration, std::time::Duration::ZERO);
+    assert!(outcome.duration >= outcome.iterations.iter().map(|i| i.model_duration).sum());
+
+    assert_eq!(outcome.usage.input_tokens, 120);
+    assert_eq!(outcome.usage.output_tokens, 35);
+    assert_eq!(outcome.usage.cache_creation_input_tokens, Some(500));
+    assert_eq!(outcome.usage.cache_read_input_tokens, Some(500));
+
+    assert_eq!(outcome.tool_calls.get("get_weather"), Some(&2));
+    assert_eq!(outcome.tool_calls.get("get_time"), Some(&1));
+
+    // The transcript ends with the final assistant turn.
+    let roles: Vec<Role> = outcome.messages.iter().map(|m| m.role).collect();
+    assert_eq!(roles, [Role::User, Role::Assistant, Role::User, Role::Assistant]);
+    assert_eq!(outcome.messages[3].content, outcome.response.content);
+}
+
+/// Denies Rome, rewrites Oslo to Bergen, aborts on Tokyo, and records every
+/// hook invocation.
+#[derive(Default)]
+struct ApprovalHooks {
+    log: std::sync::Mutex<Vec<String>>,
+}
+
+impl ToolLoopHooks for ApprovalHooks {
+    fn before_model_call<'a>(&'a self, request: &'a anthropic::types::MessagesRequest) -> BoxFuture<'a, ()> {
+        self.log.lock().unwrap().push(format!("model({})", request.messages.len()));
+        Box::pin(async {})
+    }
+
+    fn before_tool<'a>(&'a self, _name: &'a str, input: &'a serde_json::Value) -> BoxFuture<'a, ToolDecision> {
+        Box::pin(async move {
+            match input["city"].as_str().unwrap() {
+                "Rome" => ToolDecision::Deny("Rome is off limits".into()),
+                "Oslo" => ToolDecision::Modify(json!({"city": "Bergen"})),
+                "Tokyo" => ToolDecision::Abort,
+                _ => ToolDecision::Approve,
+            }
+        })
+    }
+
+    fn after_tool<'a>(
+        &'a self,
+        name: &'a str,
+        input: &'a serde_json::Value,
+        output: &'a mut ToolOutput,
+    ) -> BoxFuture<'a, ()> {
+        self.log.lock().unwrap().push(format!("after({name}, {})", input["city"]));
+        *output = ToolOutput::ok(output.text().to_uppercase());
+        Box::pin(async {})
+    }
+
+    fn on_iteration<'a>(&'a self, index: usize, iteration: &'a ToolLoopIteration) -> BoxFuture<'a, ()> {
+        self.log.lock().unwrap().push(format!("iteration({index}, {})", iteration.response.id));
+        Box::pin(async {})
+    }
+}
+
+#[tokio::test]
+async fn tool_loop_hooks_approve_deny_and_modify_calls() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(three_city_response()))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Done.")))
+        .mount(&server)
+        .await;
+
+    let hooks = Arc::new(ApprovalHooks::default());
+    let executed = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let executed_clone = Arc::clone(&executed);
+    let mut config = ToolLoopConfig::default().max_parallel_tools(3);
+    config.hooks = Some(hooks.clone());
+    let client = client(&server);
+    let outcome = run_tool_loop(
+        &client,
+        tool_request(),
+        move |_name, input| {
+            let city = input["city"].as_str().unwrap().to_string();
+            executed_clone.lock().unwrap().push(city.clone());
+            async move { Ok(ToolOutput::ok(format!("{city}: sunny"))) }
+        },
+        config,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Finished);
+    assert_eq!(*executed.lock().unwrap(), ["Paris", "Bergen"]);
+    assert_eq!(
+        *hooks.log.lock().unwrap(),
+        [
+            "model(1)",
+            r#"after(get_weather, "Paris")"#,
+            r#"after(get_weather, "Bergen")"#,
+            "iteration(0, msg_tool)",
+            "model(3)",
+            "iteration(1, msg_final)",
+        ]
+    );
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let results = &body["messages"][2]["content"];
+    assert_eq!(results[0]["content"], "PARIS: SUNNY");
+    assert_eq!(results[1]["content"], "tool call denied: Rome is off limits");
+    assert_eq!(results[1]["is_error"], true);
+    assert_eq!(results[2]["content"], "BERGEN: SUNNY");
+}
+
+#[tokio::test]
+async fn tool_loop_hooks_can_abort_the_loop() {
+    let server = MockServer::start().await;
+    let mut response = three_city_response();
+    response["content"][1]["input"] = json!({"city": "Tokyo"});
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(response))
+        .mount(&server)
+        .await;
+
+    let executed = Arc::new(AtomicUsize::new(0));
+    let executed_clone = Arc::clone(&executed);
+    let client = client(&server);
+    let outcome = run_tool_loop(
+        &client,
+        tool_request(),
+        move |_name, _input| {
+            executed_clone.fetch_add(1, Ordering::SeqCst);
+            async { Ok(ToolOutput::ok("sunny")) }
+        },
+        ToolLoopConfig::default().hooks(ApprovalHooks::default()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Aborted);
+    assert_eq!(executed.load(Ordering::SeqCst), 0);
+    assert_eq!(outcome.iterations.len(), 1);
+    assert_eq!(outcome.messages.len(), 2);
+    assert_eq!(outcome.messages[1].role, Role::Assistant);
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+fn with_stop_reason(mut response: serde_json::Value, stop_reason: &str) -> serde_json::Value {
+    response["stop_reason"] = json!(stop_reason);
+    response
+}
+
+#[tokio::test]
+async fn tool_loop_continues_paused_turns() {
+    let server = MockServer::start().await;
+    let paused = json!({
+        "id": "msg_paused",
+        "type": "message",
+        "role": "assistant",
+        "content": [
+            {"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search", "input": {"query": "paris weather"}}
+        ],
+        "model": "claude-3-5-sonnet-20240620",
+        "stop_reason": "pause_turn",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 20, "output_tokens": 15}
+    });
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(paused))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Sunny.")))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let outcome = run_tool_loop(
+        &client,
+        tool_request(),
+        |_n, _i| async { Ok(ToolOutput::ok("unused")) },
+        ToolLoopConfig::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Finished);
+    assert_eq!(outcome.response.text(), "Sunny.");
+    assert_eq!(outcome.iterations.len(), 2);
+
+    // The paused turn is sent back as the last message so the model resumes it.
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let messages = body["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[1]["role"], "assistant");
+    assert_eq!(messages[1]["content"][0]["type"], "server_tool_use");
+}
+
+#[tokio::test]
+async fn tool_loop_retries_truncated_tool_calls_with_more_tokens() {
+    let server = MockServer::start().await;
+    let mut truncated = with_stop_reason(tool_use_response("tu_1", "Par"), "max_tokens");
+    truncated["content"][1]["input"] = json!({});
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(truncated))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tool_use_response("tu_2", "Paris")))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Sunny.")))
+        .mount(&server)
+        .await;
+
+    let inputs = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let inputs_clone = Arc::clone(&inputs);
+    let client = client(&server);
+    let outcome = run_tool_loop(
+        &client,
+        tool_request(),
+        move |_name, input| {
+            inputs_clone.lock().unwrap().push(input);
+            async { Ok(ToolOutput::ok("sunny")) }
+        },
+        ToolLoopConfig::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Finished);
+    assert_eq!(*inputs.lock().unwrap(), [json!({"city": "Paris"})]);
+    assert_eq!(outcome.iterations.len(), 3);
+
+    let requests = server.received_requests().await.unwrap();
+    let bodies: Vec<serde_json::Value> = requests.iter().map(|r| serde_json::from_slice(&r.body).unwrap()).collect();
+    assert_eq!(bodies[0]["max_tokens"], 256);
+    // The retry repeats the same transcript with a doubled budget.
+    assert_eq!(bodies[1]["max_tokens"], 512);
+    assert_eq!(bodies[1]["messages"], bodies[0]["messages"]);
+    assert_eq!(bodies[2]["messages"][1]["content"][1]["id"], "tu_2");
+}
+
+#[tokio::test]
+async fn tool_loop_retries_once_when_the_request_starts_at_the_token_cap() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(with_stop_reason(tool_use_response("tu_1", "Par"), "max_tokens")),
+        )
+        .mount(&server)
+        .await;
+
+    let mut request = tool_request();
+    request.max_tokens = 16_384;
+    let client = client(&server);
+    let outcome =
+        run_tool_loop(&client, request, |_n, _i| async { Ok(ToolOutput::ok("unused")) }, ToolLoopConfig::default())
+            .await
+            .unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::MaxTokens);
+    let requests = server.received_requests().await.unwrap();
+    let budgets: Vec<u64> = requests
+        .iter()
+        .map(|r| serde_json::from_slice::<serde_json::Value>(&r.body).unwrap()["max_tokens"].as_u64().unwrap())
+        .collect();
+    assert_eq!(budgets, [16_384, 32_768]);
+}
+
+#[tokio::test]
+async fn tool_loop_stops_when_truncation_hits_the_token_cap() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(with_stop_reason(tool_use_response("tu_1", "Par"), "max_tokens")),
+        )
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let outcome = run_tool_loop(
+        &client,
+        tool_request(),
+        |_n, _i| async { Ok(ToolOutput::ok("unused")) },
+        ToolLoopConfig::default().max_tokens_cap(600),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::MaxTokens);
+    let requests = server.received_requests().await.unwrap();
+    let budgets: Vec<u64> = requests
+        .iter()
+        .map(|r| serde_json::from_slice::<serde_json::Value>(&r.body).unwrap()["max_tokens"].as_u64().unwrap())
+        .collect();
+    assert_eq!(budgets, [256, 512, 600]);
+    // Truncated turns never enter the transcript.
+    assert_eq!(outcome.messages.len(), 1);
+}
+
+#[tokio::test]
+async fn tool_loop_surfaces_refusals() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(with_stop_reason(text_response("I can't help with that."), "refusal")),
+        )
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let outcome = run_tool_loop(
+        &client,
+        tool_request(),
+        |_n, _i| async { Ok(ToolOutput::ok("unused")) },
+        ToolLoopConfig::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Refused);
+    assert_eq!(outcome.response.stop_reason, Some(StopReason::Refusal));
+    assert_eq!(outcome.messages.len(), 2);
+}
+
+#[tokio::test]
+async fn tool_loop_sends_thinking_blocks_back_unchanged() {
+    let server = MockServer::start().await;
+    let mut thinking_turn = tool_use_response("tu_1", "Paris");
+    thinking_turn["content"] = json!([
+        {"type": "thinking", "thinking": "The user wants Paris weather.", "signature": "sig_abc"},
+        {"type": "redacted_thinking", "data": "encrypted-blob"},
+        {"type": "tool_use", "id": "tu_1", "name": "get_weather", "input": {"city": "Paris"}}
+    ]);
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(thinking_turn.clone()))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Sunny.")))
+        .mount(&server)
+        .await;
+
+    let mut request = tool_request();
+    request.max_tokens = 4096;
+    request.thinking = Some(ThinkingConfig::enabled(2048));
+    let client = client(&server);
+    let outcome =
+        run_tool_loop(&client, request, |_n, _i| async { Ok(ToolOutput::ok("sunny")) }, ToolLoopConfig::default())
+            .await
+            .unwrap();
+    assert_eq!(outcome.thinking(), vec!["The user wants Paris weather.".to_string(), String::new()]);
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(body["messages"][1]["role"], "assistant");
+    assert_eq!(body["messages"][1]["content"], thinking_turn["content"]);
+}
+
+#[tokio::test]
+async fn tool_loop_rejects_a_thinking_budget_above_max_tokens() {
+    let server = MockServer::start().await;
+    let mut request = tool_request();
+    request.thinking = Some(ThinkingConfig::enabled(2048));
+    let err = run_tool_loop(
+        &client(&server),
+        request.clone(),
+        |_n, _i| async { Ok(ToolOutput::ok("unused")) },
+        ToolLoopConfig::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, AnthropicError::InvalidRequest(_)), "{err}");
+    assert!(server.received_requests().await.unwrap().is_empty());
+
+    // The interleaved-thinking beta lets the budget span the whole turn.
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Sunny.")))
+        .mount(&server)
+        .await;
+    request.betas.push(anthropic::types::INTERLEAVED_THINKING_BETA.into());
+    run_tool_loop(
+        &client(&server),
+        request,
+        |_n, _i| async { Ok(ToolOutput::ok("unused")) },
+        ToolLoopConfig::default(),
+    )
+    .await
+    .unwrap();
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests[0].headers["anthropic-beta"], anthropic::types::INTERLEAVED_THINKING_BETA);
+}
+
+/// Serves a tool call on the first request and a final answer afterwards.
+async fn mount_tool_then_answer(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tool_use_response("tu_1", "Paris")))
+        .up_to_n_times(1)
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(text_response("Sunny.")))
+        .mount(server)
+        .await;
+}
+
+async fn stuck_tool(_name: String, _input: serde_json::Value) -> Result<ToolOutput, AnthropicError> {
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    Ok(ToolOutput::ok("too late"))
+}
+
+#[tokio::test]
+async fn tool_loop_answers_stuck_tools_with_a_timeout_error() {
+    let server = MockServer::start().await;
+    mount_tool_then_answer(&server).await;
+
+    let client = client(&server);
+    let config = ToolLoopConfig::default().tool_timeout(Duration::from_millis(50));
+    let outcome = run_tool_loop(&client, tool_request(), stuck_tool, config).await.unwrap();
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Finished);
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let result = &body["messages"][2]["content"][0];
+    assert_eq!(result["is_error"], true);
+    assert_eq!(result["content"], "tool `get_weather` timed out after 50ms");
+}
+
+#[tokio::test]
+async fn tool_loop_stops_at_the_deadline_with_the_transcript() {
+    let server = MockServer::start().await;
+    mount_tool_then_answer(&server).await;
+
+    let client = client(&server);
+    let config = ToolLoopConfig::default().deadline(Duration::from_millis(200));
+    let outcome = run_tool_loop(&client, tool_request(), stuck_tool, config).await.unwrap();
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::DeadlineExceeded);
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    assert_eq!(outcome.messages.len(), 3);
+    let result = serde_json::to_value(&outcome.messages[2].content[0]).unwrap();
+    assert_eq!(result["content"], "tool `get_weather` did not finish before the tool loop deadline");
+}
+
+#[tokio::test]
+async fn tool_loop_deadline_drops_a_slow_model_call() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tool_use_response("tu_1", "Paris")))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(text_response("Sunny.")).set_delay(Duration::from_secs(10)),
+        )
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let executor = |_n, _i| async { Ok(ToolOutput::ok("sunny")) };
+    let config = ToolLoopConfig::default().deadline(Duration::from_millis(300));
+    let started = std::time::Instant::now();
+    let outcome = run_tool_loop(&client, tool_request(), executor, config).await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5), "{:?}", started.elapsed());
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::DeadlineExceeded);
+    assert_eq!(outcome.iterations.len(), 1);
+    assert_eq!(outcome.messages.len(), 3);
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+    // With nothing to return yet, a deadline during the first call is an error.
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(text_response("Sunny.")).set_delay(Duration::from_secs(10)),
+        )
+        .mount(&server)
+        .await;
+    let config = ToolLoopConfig::default().deadline(Duration::from_millis(100));
+    let client = self::client(&server);
+    let err = run_tool_loop(&client, tool_request(), executor, config).await.unwrap_err();
+    assert!(matches!(err, AnthropicError::DeadlineExceeded), "{err}");
+}
+
+#[tokio::test]
+async fn tool_loop_stops_when_the_budget_is_used_up() {
+    let server = MockServer::start().await;
+    mount_tool_then_answer(&server).await;
+
+    let client = client(&server);
+    let executor = |_n, _i| async { Ok(ToolOutput::ok("sunny")) };
+    let config = ToolLoopConfig::default().budget(ToolLoopBudget::default().max_output_tokens(15));
+    let outcome = run_tool_loop(&client, tool_request(), executor, config).await.unwrap();
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::BudgetExceeded);
+    assert_eq!(outcome.usage.output_tokens, 15);
+    assert_eq!(outcome.messages.len(), 3);
+    assert_eq!(outcome.messages[2].role, Role::User);
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+    // $3 / $15 per million tokens: 20 input + 15 output tokens cost $0.000285.
+    let pricing = ModelPricing::new(3.0, 15.0);
+    let config = ToolLoopConfig::default().budget(ToolLoopBudget::default().max_cost(0.001, pricing));
+    let outcome = run_tool_loop(&client, tool_request(), executor, config).await.unwrap();
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Finished);
+}
+
+#[tokio::test]
+async fn tool_loop_sends_image_results_as_blocks() {
+    let server = MockServer::start().await;
+    mount_tool_then_answer(&server).await;
+
+    let client = client(&server);
+    let executor = |_n, _i| async { Ok(ToolOutput::image_base64("image/png", "iVBORw0KGgo=")) };
+    run_tool_loop(&client, tool_request(), executor, ToolLoopConfig::default()).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(
+        body["messages"][2]["content"][0]["content"],
+        json!([{"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}}])
+    );
+}
+
+fn sse(events: &[serde_json::Value]) -> String {
+    events.iter().map(|event| format!("event: {}\ndata: {event}\n\n", event["type"].as_str().unwrap())).collect()
+}
+
+fn streamed_turn(id: &str, blocks: &[serde_json::Value], stop_reason: &str) -> String {
+    let mut events = vec![json!({"type": "message_start", "message": {
+        "id": id, "type": "message", "role": "assistant", "content": [],
+        "model": "claude-3-5-sonnet-20240620", "stop_reason": null, "stop_sequence": null,
+        "usage": {"input_tokens": 20, "output_tokens": 1}
+    }})];
+    for (index, block) in blocks.iter().enumerate() {
+        let (start, delta) = match block["type"].as_str().unwrap() {
+            "text" => (json!({"type": "text", "text": ""}), json!({"type": "text_delta", "text": block["text"]})),
+            _ => (
+                json!({"type": "tool_use", "id": block["id"], "name": block["name"], "input": {}}),
+                json!({"type": "input_json_delta", "partial_json": block["input"].to_string()}),
+            ),
+        };
+        events.push(json!({"type": "content_block_start", "index": index, "content_block": start}));
+        events.push(json!({"type": "content_block_delta", "index": index, "delta": delta}));
+        events.push(json!({"type": "content_block_stop", "index": index}));
+    }
+    events.push(json!({"type": "message_delta", "delta": {"stop_reason": stop_reason, "stop_sequence": null},
+        "usage": {"output_tokens": 10}}));
+    events.push(json!({"type": "message_stop"}));
+    sse(&events)
+}
+
+#[tokio::test]
+async fn streaming_tool_loop_forwards_deltas_and_tool_events() {
+    let server = MockServer::start().await;
+    let tool_turn = streamed_turn(
+        "msg_tool",
+        &[
+            json!({"type": "text", "text": "Let me check."}),
+            json!({"type": "tool_use", "id": "tu_1", "name": "get_weather", "input": {"city": "Paris"}}),
+        ],
+        "tool_use",
+    );
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(tool_turn, "text/event-stream"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    let final_turn = streamed_turn("msg_final", &[json!({"type": "text", "text": "Sunny."})], "end_turn");
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(final_turn, "text/event-stream"))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&events);
+    // Spawning proves the streaming loop's future is `Send`.
+    let outcome = tokio::spawn(async move {
+        run_tool_loop_streaming(
+            &client,
+            tool_request(),
+            |_n, input| async move { Ok(ToolOutput::ok(format!("{}: sunny", input["city"].as_str().unwrap()))) },
+            ToolLoopConfig::default(),
+            move |event| recorded.lock().unwrap().push(event),
+        )
+        .await
+    })
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Finished);
+    assert_eq!(outcome.response.text(), "Sunny.");
+    assert_eq!(outcome.messages.len(), 4);
+    assert_eq!(outcome.usage.output_tokens, 20);
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            ToolLoopEvent::MessageStarted { iteration: 0 },
+            ToolLoopEvent::TextDelta { iteration: 0, index: 0, text: "Let me check.".into() },
+            ToolLoopEvent::ToolInputDelta { iteration: 0, index: 1, partial_json: r#"{"city":"Paris"}"#.into() },
+            ToolLoopEvent::ToolStarted {
+                id: "tu_1".into(),
+                name: "get_weather".into(),
+                input: json!({"city": "Paris"})
+            },
+            ToolLoopEvent::ToolFinished {
+                id: "tu_1".into(),
+                name: "get_weather".into(),
+                output: ToolOutput::ok("Paris: sunny"),
+            },
+            ToolLoopEvent::MessageStarted { iteration: 1 },
+            ToolLoopEvent::TextDelta { iteration: 1, index: 0, text: "Sunny.".into() },
+        ]
+    );
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["messages"][2]["content"][0]["content"], "Paris: sunny");
+}
+
+#[tokio::test]
+async fn tool_loop_cancellation_lets_running_tools_finish() {
+    let server = MockServer::start().await;
+    mount_tool_then_answer(&server).await;
+
+    let client = client(&server);
+    let token = CancellationToken::new();
+    let cancel_mid_tool = token.clone();
+    let executor = move |_n, _i| {
+        let token = cancel_mid_tool.clone();
+        async move {
+            token.cancel();
+            Ok(ToolOutput::ok("sunny"))
+        }
+    };
+    let config = ToolLoopConfig::default().cancellation(token);
+    let outcome = run_tool_loop(&client, tool_request(), executor, config).await.unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Cancelled);
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    assert_eq!(outcome.messages.len(), 3);
+    let result = serde_json::to_value(&outcome.messages[2].content[0]).unwrap();
+    assert_eq!(result["content"], "sunny");
+}
+
+#[tokio::test]
+async fn tool_loop_does_not_call_the_executor_for_calls_started_after_cancellation() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(three_city_response()))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    let invoked = Arc::new(AtomicUsize::new(0));
+    let invoked_clone = Arc::clone(&invoked);
+    let executor = move |_n, input: serde_json::Value| {
+        // Counted when the executor is called, not when its future runs.
+        invoked_clone.fetch_add(1, Ordering::SeqCst);
+        canceller.cancel();
+        async move { Ok(ToolOutput::ok(input["city"].as_str().unwrap())) }
+    };
+    let config = ToolLoopConfig::default().max_parallel_tools(1).cancellation(token);
+    let outcome = run_tool_loop(&client, tool_request(), executor, config).await.unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Cancelled);
+    assert_eq!(invoked.load(Ordering::SeqCst), 1);
+    let results = serde_json::to_value(&outcome.messages[2].content).unwrap();
+    assert_eq!(results[0]["content"], "Paris");
+    assert_eq!(results[1]["content"], "tool `get_weather` was not run: the tool loop was cancelled");
+    assert_eq!(results[2]["content"], "tool `get_weather` was not run: the tool loop was cancelled");
+}
+
+#[tokio::test]
+async fn tool_loop_cancellation_can_abort_running_tools() {
+    let server = MockServer::start().await;
+    mount_tool_then_answer(&server).await;
+
+    let client = client(&server);
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        canceller.cancel();
+    });
+    let config = ToolLoopConfig::default().cancellation(token).on_cancel(ToolCancelPolicy::AbortRunning);
+    let outcome = run_tool_loop(&client, tool_request(), stuck_tool, config).await.unwrap();
+
+    assert_eq!(outcome.stop_reason, ToolLoopStopReason::Cancelled);
+    let result = serde_json::to_value(&outcome.messages[2].content[0]).unwrap();
+    assert_eq!(result["is_error"], true);
+    assert_eq!(result["content"], "tool `get_weather` was aborted: the tool loop was cancelled");
+}
+
+#[tokio::test]
+async fn tool_loop_cancelled_before_the_first_response_fails() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(text_response("late")).set_delay(Duration::from_secs(30)),
+        )
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        canceller.cancel();
+    });
+    let config = ToolLoopConfig::default().cancellation(token);
+    let err = run_tool_loop(&client, tool_request(), stuck_tool, config).await.unwrap_err();
+    assert!(matches!(err, AnthropicError::Cancelled), "{err}");
+}
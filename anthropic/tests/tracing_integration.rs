@@ -1,7 +1,8 @@
 //! Integration tests for the optional `tracing` feature.
 //!
-//! Verify that `execute_bytes` emits a span with the documented fields on the
-//! happy path, and that the span's `attempts` field reflects every retry.
+//! Verify that the transport emits a span with the documented fields on the
+//! happy path, that the span's `attempts` field reflects every retry, and
+//! that the span stays open while the response body is decoded.
 
 #![cfg(feature = "tracing")]
 
@@ -77,6 +78,10 @@ struct CapturingSubscriber {
     spans: Arc<Mutex<Vec<SpanRecord>>>,
     next_id: Arc<std::sync::atomic::AtomicU64>,
     active: Arc<Mutex<std::collections::HashMap<u64, SpanRecord>>>,
+    /// Names of the currently entered spans, innermost last.
+    entered: Arc<Mutex<Vec<String>>>,
+    /// Targets of events emitted while an `anthropic.http` span was entered.
+    http_events: Arc<Mutex<Vec<String>>>,
 }
 
 impl CapturingSubscriber {
@@ -87,6 +92,8 @@ impl CapturingSubscriber {
                 spans: spans.clone(),
                 next_id: Arc::new(std::sync::atomic::AtomicU64::new(1)),
                 active: Arc::new(Mutex::new(std::collections::HashMap::new())),
+                entered: Arc::new(Mutex::new(Vec::new())),
+                http_events: Arc::new(Mutex::new(Vec::new())),
             },
             spans,
         )
@@ -137,9 +144,20 @@ impl Subscriber for CapturingSubscriber {
     }
 
     fn record_follows_from(&self, _: &Id, _: &Id) {}
-    fn event(&self, _: &Event<'_>) {}
-    fn enter(&self, _: &Id) {}
-    fn exit(&self, _: &Id) {}
+    fn event(&self, event: &Event<'_>) {
+        if self.entered.lock().unwrap().iter().any(|name| name == "anthropic.http") {
+            self.http_events.lock().unwrap().push(event.metadata().target().to_string());
+        }
+    }
+
+    fn enter(&self, id: &Id) {
+        let name = self.active.lock().unwrap().get(&id.into_u64()).map(|record| record.name.clone());
+        self.entered.lock().unwrap().push(name.unwrap_or_default());
+    }
+
+    fn exit(&self, _: &Id) {
+        self.entered.lock().unwrap().pop();
+    }
 
     fn try_close(&self, id: Id) -> bool {
         if let Some(record) = self.active.lock().unwrap().remove(&id.into_u64()) {
@@ -237,3 +255,35 @@ async fn tracing_records_attempt_count_on_retries() {
     // Three attempts: two 429s + one success.
     assert_eq!(http_spans[0].attempts, Some(3));
 }
+
+#[tokio::test]
+async fn tracing_span_covers_response_decoding() {
+    let (subscriber, spans) = CapturingSubscriber::new();
+    let http_events = subscriber.http_events.clone();
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("not json"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = build_client(&server);
+    let request = MessagesRequestBuilder::new("claude", vec![Message::user("hi")], 10).no_retries().build().unwrap();
+    let dispatch = tracing::dispatcher::Dispatch::new(subscriber);
+    async {
+        let err = client.messages(request).await.unwrap_err();
+        assert!(matches!(err, anthropic::AnthropicError::Deserialize(_)), "unexpected error: {err:?}");
+    }
+    .with_subscriber(dispatch)
+    .await;
+
+    // The decode failure is reported from inside the span, and the span's
+    // duration is recorded only once the body has been handled.
+    assert!(http_events.lock().unwrap().iter().any(|target| target == "anthropic::http"));
+    let collected = spans.lock().unwrap().clone();
+    let span = collected.iter().find(|s| s.name == "anthropic.http").expect("anthropic.http span");
+    assert_eq!(span.status, Some(200));
+    assert!(span.duration_ms.is_some(), "duration_ms must be recorded");
+}
@@ -0,0 +1,111 @@
+//! Integration tests for `TypedTool` with `run_tool_loop`.
+#![cfg(feature = "schemars")]
+
+use anthropic::tool_loop::{run_tool_loop, ToolLoopConfig, ToolOutput};
+use anthropic::typed_tool::{typed_executor, TypedTool};
+use anthropic::types::{ContentBlock, Message, MessagesRequestBuilder};
+use anthropic::{AnthropicError, Client};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[derive(Deserialize, JsonSchema)]
+struct AddInput {
+    /// First addend.
+    a: i64,
+    /// Second addend.
+    b: i64,
+}
+
+struct Add;
+
+impl TypedTool for Add {
+    type Input = AddInput;
+
+    fn name(&self) -> &str {
+        "add"
+    }
+
+    fn description(&self) -> &str {
+        "Add two integers"
+    }
+
+    async fn call(&self, input: AddInput) -> Result<ToolOutput, AnthropicError> {
+        Ok(ToolOutput::ok((input.a + input.b).to_string()))
+    }
+}
+
+fn tool_use(id: &str, input: Value) -> Value {
+    json!({
+        "id": "msg_tool",
+        "type": "message",
+        "role": "assistant",
+        "content": [{"type": "tool_use", "id": id, "name": "add", "input": input}],
+        "model": "claude-3-5-sonnet-20240620",
+        "stop_reason": "tool_use",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 20, "output_tokens": 15}
+    })
+}
+
+#[tokio::test]
+async fn bad_input_is_reported_to_the_model_and_the_loop_continues() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tool_use("tu_1", json!({"a": 2}))))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tool_use("tu_2", json!({"a": 2, "b": 3}))))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_final",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "2 + 3 = 5"}],
+            "model": "claude-3-5-sonnet-20240620",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 40, "output_tokens": 12}
+        })))
+        .mount(&server)
+        .await;
+
+    let client = Client::builder().api_key("test-key").api_base(server.uri()).build().unwrap();
+    let request = MessagesRequestBuilder::new("claude-3-5-sonnet-20240620", vec![Message::user("2 + 3?")], 256)
+        .tool(Add.definition())
+        .build()
+        .unwrap();
+
+    let response =
+        run_tool_loop(&client, request, typed_executor(Add), ToolLoopConfig::default()).await.unwrap().response;
+    assert_eq!(response.text(), "2 + 3 = 5");
+
+    let requests = server.received_requests().await.unwrap();
+    let first: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(first["tools"][0]["input_schema"]["required"], json!(["a", "b"]));
+    assert_eq!(first["tools"][0]["input_schema"]["properties"]["b"]["description"], "Second addend.");
+
+    let last: Value = serde_json::from_slice(&requests[2].body).unwrap();
+    let rejected: ContentBlock = serde_json::from_value(last["messages"][2]["content"][0].clone()).unwrap();
+    match rejected {
+        ContentBlock::ToolResult { is_error: Some(true), content, .. } => {
+            let text = serde_json::to_string(&content).unwrap();
+            assert!(text.contains("missing field `b`"), "{text}");
+        }
+        other => panic!("unexpected block {other:?}"),
+    }
+    assert_eq!(last["messages"][4]["content"][0]["content"], "5");
+}
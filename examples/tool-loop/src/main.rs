@@ -31,10 +31,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "required": ["city"]
         }),
     )])
-    .tool_choice(ToolChoice::Auto)
+    .tool_choice(ToolChoice::auto())
     .build()?;
 
-    let response = run_tool_loop(
+    let outcome = run_tool_loop(
         &client,
         request,
         |name, input| async move {
@@ -54,6 +54,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     )
     .await?;
 
-    println!("\nFinal answer:\n{}", response.text());
+    println!("\nFinal answer:\n{}", outcome.response.text());
     Ok(())
 }